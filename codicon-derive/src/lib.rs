@@ -0,0 +1,840 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Derive macros for the `codicon` `Encoder` and `Decoder` traits.
+//!
+//! The derived implementations encode and decode each field in declaration
+//! order, passing a clone of the parameters to every field. By default, the
+//! implementation is generic over all parameters implementing
+//! `codicon::Concat`; use `#[codicon(params = T)]` on the type to implement
+//! the traits for the parameters `T` only.
+//!
+//! Enums first encode a tag identifying the variant followed by the fields of
+//! that variant. The tag is a `u8` unless `#[codicon(tag = T)]` is given on
+//! the enum. Each variant's tag is its explicit discriminant, if any, or one
+//! more than the previous variant's tag. Use `#[codicon(id = expr)]` on a
+//! variant to specify its tag explicitly. Tags given as integer literals must
+//! be distinct.
+//!
+//! Structs given `#[codicon(message)]` decode as a Protocol Buffers message
+//! instead, in which fields may appear in any order. The whole
+//! `codicon::protobuf::Message` is decoded first. Each field with
+//! `#[codicon(params = expr)]` is then taken from it by
+//! `Message::take_field(expr)`, which expects `codicon::protobuf::Field`
+//! parameters and gives the default value for a missing field. The other
+//! fields, usually a single `Message`, receive the fields which remain.
+//! Encoding is unaffected.
+//!
+//! Fields accept the following attributes:
+//!
+//!   * `#[codicon(skip)]`: the field is not encoded and decodes as
+//!     `Default::default()`.
+//!   * `#[codicon(params = expr)]`: the field is encoded and decoded using the
+//!     parameters `expr` instead. The outer parameters are available to the
+//!     expression as `params`.
+//!   * `#[codicon(with = path)]`: the field is encoded with
+//!     `path::encode(&field, writer, params)` and decoded with
+//!     `path::decode(reader, params)`.
+//!
+//! Without explicit parameters, the fields of structs and of each enum variant
+//! are framed by the methods of `codicon::Concat`, which do nothing unless
+//! the parameters override them. Parameters which frame each field with its
+//! name may decode the fields in any order and skip unknown ones; decoding
+//! fails if a field is missing or repeated.
+//!
+//! The derived `encoded_len` sums the `encoded_len` of each field, so nesting
+//! derived types costs no more than encoding them once. Framing, tags and
+//! fields encoded `with` a path are counted by encoding them.
+//!
+//! The error type of the derived implementations is `codicon::Error` unless
+//! `#[codicon(error = T)]` is given on the type. The error type of every field
+//! must convert into it, as must `codicon::Error` for generic implementations.
+//!
+//! Generic implementations require every field type to implement the trait
+//! for the parameters. With explicit parameters, only field types mentioning
+//! a generic parameter of the type are required to in the `where` clause.
+//! Recursive types, such as a `Node` with a `Vec<Node>` field, therefore need
+//! explicit parameters.
+//!
+//! ```rust
+//! use codicon::*;
+//!
+//! #[derive(Clone)]
+//! struct Raw;
+//!
+//! impl<T: ?Sized> Concat<T> for Raw {}
+//!
+//! impl Encoder<Raw> for u8 {
+//!     type Error = std::io::Error;
+//!
+//!     fn encode(&self, mut writer: impl Write, _: Raw) -> std::io::Result<()> {
+//!         writer.write_all(std::slice::from_ref(self))
+//!     }
+//! }
+//!
+//! impl Decoder<Raw> for u8 {
+//!     type Error = std::io::Error;
+//!
+//!     fn decode(mut reader: impl Read, _: Raw) -> std::io::Result<Self> {
+//!         let mut byte = 0u8;
+//!         reader.read_exact(std::slice::from_mut(&mut byte))?;
+//!         Ok(byte)
+//!     }
+//! }
+//!
+//! #[derive(Encoder, Decoder, Debug, PartialEq)]
+//! struct Point {
+//!     x: u8,
+//!     y: u8,
+//!     #[codicon(skip)]
+//!     cached: Option<u8>,
+//! }
+//!
+//! #[derive(Encoder, Decoder, Debug, PartialEq)]
+//! enum Shape {
+//!     Empty,
+//!     Dot(Point),
+//!     #[codicon(id = 7)]
+//!     Line { from: Point, to: Point },
+//! }
+//!
+//! let line = Shape::Line {
+//!     from: Point { x: 1, y: 2, cached: None },
+//!     to: Point { x: 3, y: 4, cached: None },
+//! };
+//!
+//! let mut buf = Vec::new();
+//! line.encode(&mut buf, Raw).unwrap();
+//! assert_eq!(buf, [7, 1, 2, 3, 4]);
+//! assert_eq!(Shape::decode(&mut buf.as_slice(), Raw).unwrap(), line);
+//! assert!(Shape::decode(&mut [9u8].as_ref(), Raw).is_err());
+//!
+//! let framed = line.encode_to_vec(Framed::new(Raw, Uleb128)).unwrap();
+//! assert_eq!(framed, [5, 7, 1, 2, 3, 4]);
+//! ```
+
+use proc_macro2::{TokenStream, TokenTree};
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+use syn::spanned::Spanned;
+use syn::{
+    parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Expr, Fields, GenericParam,
+    Generics, Ident, Path, Result, Type, WherePredicate,
+};
+
+/// Derives `codicon::Encoder` by encoding each field in order.
+#[proc_macro_derive(Encoder, attributes(codicon))]
+pub fn derive_encoder(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    encoder(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Derives `codicon::Decoder` by decoding each field in order.
+#[proc_macro_derive(Decoder, attributes(codicon))]
+pub fn derive_decoder(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    decoder(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+#[derive(Default)]
+struct TypeAttrs {
+    params: Option<Type>,
+    error: Option<Type>,
+    tag: Option<Type>,
+    message: bool,
+}
+
+#[derive(Default)]
+struct VariantAttrs {
+    id: Option<Expr>,
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+    skip: bool,
+    params: Option<Expr>,
+    with: Option<Path>,
+}
+
+impl TypeAttrs {
+    fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut out = Self::default();
+
+        for attr in attrs.iter().filter(|a| a.path().is_ident("codicon")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("params") {
+                    out.params = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("error") {
+                    out.error = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("tag") {
+                    out.tag = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("message") {
+                    out.message = true;
+                } else {
+                    return Err(meta.error("unsupported codicon attribute"));
+                }
+
+                Ok(())
+            })?;
+        }
+
+        Ok(out)
+    }
+}
+
+impl VariantAttrs {
+    fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut out = Self::default();
+
+        for attr in attrs.iter().filter(|a| a.path().is_ident("codicon")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("id") {
+                    out.id = Some(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error("unsupported codicon attribute"));
+                }
+
+                Ok(())
+            })?;
+        }
+
+        Ok(out)
+    }
+}
+
+impl FieldAttrs {
+    fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut out = Self::default();
+
+        for attr in attrs.iter().filter(|a| a.path().is_ident("codicon")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    out.skip = true;
+                } else if meta.path.is_ident("params") {
+                    out.params = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("with") {
+                    out.with = Some(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error("unsupported codicon attribute"));
+                }
+
+                Ok(())
+            })?;
+        }
+
+        Ok(out)
+    }
+}
+
+/// A field together with the name it is bound to in the generated code.
+struct Field<'a> {
+    binding: Ident,
+    member: TokenStream,
+    named: bool,
+    name: String,
+    ty: &'a Type,
+    attrs: FieldAttrs,
+}
+
+fn fields(fields: &Fields) -> Result<Vec<Field<'_>>> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, f)| {
+            let (binding, member, name) = match &f.ident {
+                Some(ident) => (ident.clone(), quote!(#ident), ident.unraw().to_string()),
+                None => {
+                    let index = syn::Index::from(i);
+                    (format_ident!("__field{}", i), quote!(#index), i.to_string())
+                }
+            };
+
+            Ok(Field {
+                binding,
+                member,
+                named: f.ident.is_some(),
+                name,
+                ty: &f.ty,
+                attrs: FieldAttrs::parse(&f.attrs)?,
+            })
+        })
+        .collect()
+}
+
+/// The value of an integer literal tag expression, such as `7` or `-1`.
+fn literal(expr: &Expr) -> Option<i128> {
+    match expr {
+        Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Int(int),
+            ..
+        }) => int.base10_parse().ok(),
+        Expr::Unary(syn::ExprUnary {
+            op: syn::UnOp::Neg(_),
+            expr,
+            ..
+        }) => literal(expr).map(|value| -value),
+        Expr::Group(group) => literal(&group.expr),
+        Expr::Paren(paren) => literal(&paren.expr),
+        _ => None,
+    }
+}
+
+/// The variants of an enum paired with their tag expressions.
+///
+/// Tags which are integer literals, or follow one implicitly, are checked
+/// for duplicates; other expressions are only checked by the compiler.
+fn variants(data: &syn::DataEnum) -> Result<Vec<(&syn::Variant, Expr)>> {
+    let mut next: (Expr, Option<i128>) = (parse_quote!(0), Some(0));
+    let mut seen = Vec::new();
+    let mut out = Vec::new();
+
+    for variant in &data.variants {
+        let attrs = VariantAttrs::parse(&variant.attrs)?;
+        let (id, value) = match (attrs.id, &variant.discriminant) {
+            (Some(id), _) => {
+                let value = literal(&id);
+                (id, value)
+            }
+            (None, Some((_, discriminant))) => (discriminant.clone(), literal(discriminant)),
+            (None, None) => next,
+        };
+
+        if let Some(value) = value {
+            if seen.contains(&value) {
+                return Err(syn::Error::new(
+                    variant.span(),
+                    format!("duplicate id {} for variant `{}`", value, variant.ident),
+                ));
+            }
+
+            seen.push(value);
+        }
+
+        next = (parse_quote!((#id) + 1), value.map(|value| value + 1));
+        out.push((variant, id));
+    }
+
+    Ok(out)
+}
+
+/// The parameter type of the implementation and the generics it requires.
+fn params(attrs: &TypeAttrs, input: &DeriveInput) -> (Type, Generics) {
+    let mut generics = input.generics.clone();
+
+    let params = match &attrs.params {
+        Some(params) => params.clone(),
+        None => {
+            let ident = &input.ident;
+            let (_, ty_generics, _) = input.generics.split_for_impl();
+            generics
+                .params
+                .push(parse_quote!(__P: ::codicon::Concat<#ident #ty_generics>));
+            parse_quote!(__P)
+        }
+    };
+
+    (params, generics)
+}
+
+fn error(attrs: &TypeAttrs) -> Type {
+    attrs
+        .error
+        .clone()
+        .unwrap_or_else(|| parse_quote!(::codicon::Error))
+}
+
+/// Calls a `Concat` method framing fields, if the parameters are generic.
+fn framing(attrs: &TypeAttrs, method: &str, io: TokenStream, arg: TokenStream) -> TokenStream {
+    if attrs.params.is_some() {
+        return quote!();
+    }
+
+    let method = format_ident!("{}", method);
+    quote! {
+        <__P as ::codicon::Concat<Self>>::#method(&params, &mut #io, #arg)
+            .map_err(::core::convert::Into::<Self::Error>::into)?;
+    }
+}
+
+/// Where clauses required by the framing of generic implementations.
+fn framing_bounds(attrs: &TypeAttrs, error: &Type) -> Vec<WherePredicate> {
+    match attrs.params {
+        Some(_) => Vec::new(),
+        None => vec![parse_quote!(#error: ::core::convert::From<::codicon::Error>)],
+    }
+}
+
+/// Frames all of the encoded fields of a struct or variant.
+fn framing_fields(
+    attrs: &TypeAttrs,
+    method: &str,
+    io: TokenStream,
+    fields: &[Field<'_>],
+) -> TokenStream {
+    let names = fields.iter().filter(|f| !f.attrs.skip).map(|f| &f.name);
+    framing(attrs, method, io, quote!(&[#(#names),*]))
+}
+
+fn tag(attrs: &TypeAttrs) -> Type {
+    attrs.tag.clone().unwrap_or_else(|| parse_quote!(u8))
+}
+
+/// Where clauses requiring a field type to implement `trait_<params>`.
+fn bound(ty: &Type, trait_: &Path, params: &Type, error: &Type) -> [WherePredicate; 2] {
+    [
+        parse_quote!(#ty: #trait_<#params>),
+        parse_quote!(<#ty as #trait_<#params>>::Error: ::core::convert::Into<#error>),
+    ]
+}
+
+/// Whether any of `idents` appears in the tokens of `tokens`.
+fn mentions(tokens: TokenStream, idents: &[Ident]) -> bool {
+    tokens.into_iter().any(|token| match token {
+        TokenTree::Ident(ident) => idents.contains(&ident),
+        TokenTree::Group(group) => mentions(group.stream(), idents),
+        _ => false,
+    })
+}
+
+/// Whether the field type `ty` mentions the type being derived.
+fn recursive(input: &DeriveInput, ty: &Type) -> bool {
+    mentions(quote!(#ty), &[input.ident.clone(), format_ident!("Self")])
+}
+
+/// Whether the implementation needs a where clause for the field type `ty`.
+///
+/// As in serde, only types mentioning a generic parameter of the type are
+/// bounded when the parameters are explicit; other types either implement
+/// the trait or fail to compile in the generated body. Types mentioning the
+/// type itself are never bounded, since proving the bound would recurse.
+fn bounded(attrs: &TypeAttrs, input: &DeriveInput, ty: &Type) -> bool {
+    if recursive(input, ty) {
+        return false;
+    }
+
+    if attrs.params.is_none() {
+        return true;
+    }
+
+    let generics: Vec<Ident> = input
+        .generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Type(param) => Some(param.ident.clone()),
+            GenericParam::Const(param) => Some(param.ident.clone()),
+            GenericParam::Lifetime(_) => None,
+        })
+        .collect();
+
+    mentions(quote!(#ty), &generics)
+}
+
+fn bounds<'a>(
+    attrs: &TypeAttrs,
+    input: &DeriveInput,
+    fields: impl IntoIterator<Item = &'a Field<'a>>,
+    trait_: &Path,
+    params: &Type,
+    error: &Type,
+) -> Vec<WherePredicate> {
+    fields
+        .into_iter()
+        .filter(|f| !f.attrs.skip && f.attrs.params.is_none() && f.attrs.with.is_none())
+        .filter(|f| bounded(attrs, input, f.ty))
+        .flat_map(|f| bound(f.ty, trait_, params, error))
+        .collect()
+}
+
+fn field_params(field: &Field<'_>) -> TokenStream {
+    match &field.attrs.params {
+        Some(expr) => quote!(#expr),
+        None => quote!(::core::clone::Clone::clone(&params)),
+    }
+}
+
+/// Encodes a field, or adds its encoded length to `len` if `len` is set.
+///
+/// When computing the length, `writer` is a `Counter`, which counts the
+/// framing and the fields encoded `with` a module.
+fn encode_field(
+    attrs: &TypeAttrs,
+    input: &DeriveInput,
+    field: &Field<'_>,
+    len: bool,
+) -> TokenStream {
+    let binding = &field.binding;
+    let params = field_params(field);
+
+    // Erase the writer type of recursive fields, or every level of nesting
+    // would instantiate the encoder with a new writer type.
+    let writer = if recursive(input, field.ty) {
+        quote!(&mut writer as &mut dyn ::codicon::Write)
+    } else {
+        quote!(&mut writer)
+    };
+
+    if field.attrs.skip {
+        return quote!(let _ = #binding;);
+    }
+
+    let name = &field.name;
+    let framing = framing(attrs, "encode_field", quote!(writer), quote!(#name));
+    let encode = match &field.attrs.with {
+        Some(with) => quote! {
+            #with::encode(#binding, #writer, #params).map_err(::core::convert::Into::<Self::Error>::into)?;
+        },
+        None if len => quote! {
+            len = len.saturating_add(
+                ::codicon::Encoder::encoded_len(#binding, #params)
+                    .map_err(::core::convert::Into::<Self::Error>::into)?,
+            );
+        },
+        None => quote! {
+            ::codicon::Encoder::encode(#binding, #writer, #params)
+                .map_err(::core::convert::Into::<Self::Error>::into)?;
+        },
+    };
+
+    quote!(#framing #encode)
+}
+
+/// Decodes a field, without any framing.
+fn decode_field(input: &DeriveInput, field: &Field<'_>) -> TokenStream {
+    let params = field_params(field);
+
+    // As when encoding, erase the reader type of recursive fields.
+    let reader = if recursive(input, field.ty) {
+        quote!(&mut reader as &mut dyn ::codicon::Read)
+    } else {
+        quote!(&mut reader)
+    };
+
+    match &field.attrs.with {
+        Some(with) => quote! {
+            #with::decode(#reader, #params).map_err(::core::convert::Into::<Self::Error>::into)?
+        },
+        None => quote! {
+            ::codicon::Decoder::decode(#reader, #params)
+                .map_err(::core::convert::Into::<Self::Error>::into)?
+        },
+    }
+}
+
+/// Decodes the fields of a struct or variant into the value `path`.
+///
+/// With generic parameters, `Concat` frames the fields and may find them in
+/// any order, so each field is decoded into a slot as its key arrives.
+fn decode_fields(
+    attrs: &TypeAttrs,
+    input: &DeriveInput,
+    path: TokenStream,
+    fields: &[Field<'_>],
+) -> TokenStream {
+    if attrs.params.is_some() {
+        let decode = fields.iter().map(|f| {
+            let member = &f.member;
+            match f.attrs.skip {
+                true => quote!(#member: ::core::default::Default::default()),
+                false => {
+                    let decode = decode_field(input, f);
+                    quote!(#member: #decode)
+                }
+            }
+        });
+
+        return quote!(::core::result::Result::Ok(#path { #(#decode),* }));
+    }
+
+    let decoded: Vec<_> = fields.iter().filter(|f| !f.attrs.skip).collect();
+    let names = decoded.iter().map(|f| &f.name);
+    let slots: Vec<_> = (0..decoded.len())
+        .map(|i| format_ident!("__slot{}", i))
+        .collect();
+    let types = decoded.iter().map(|f| f.ty);
+    let arms = decoded.iter().zip(&slots).enumerate().map(|(i, (f, slot))| {
+        let decode = decode_field(input, f);
+        quote!(::core::option::Option::Some(#i) if #slot.is_none() => #slot = ::core::option::Option::Some(#decode),)
+    });
+
+    let mut slot = slots.iter();
+    let members = fields.iter().map(|f| {
+        let member = &f.member;
+        match f.attrs.skip {
+            true => quote!(#member: ::core::default::Default::default()),
+            false => {
+                let slot = slot.next();
+                quote! {
+                    #member: #slot.ok_or_else(|| ::codicon::Error::invalid("missing field"))?
+                }
+            }
+        }
+    });
+
+    quote! {
+        let fields: &[&'static str] = &[#(#names),*];
+        let count = <__P as ::codicon::Concat<Self>>::decode_fields(&params, &mut reader, fields)
+            .map_err(::core::convert::Into::<Self::Error>::into)?;
+
+        #(let mut #slots: ::core::option::Option<#types> = ::core::option::Option::None;)*
+        for index in 0..count {
+            match <__P as ::codicon::Concat<Self>>::decode_field(&params, &mut reader, fields, index)
+                .map_err(::core::convert::Into::<Self::Error>::into)?
+            {
+                #(#arms)*
+                ::core::option::Option::Some(_) => {
+                    return ::core::result::Result::Err(::codicon::Error::invalid("duplicate field").into())
+                }
+                ::core::option::Option::None => {}
+            }
+        }
+
+        ::core::result::Result::Ok(#path { #(#members),* })
+    }
+}
+
+/// Decodes the fields of a `message` struct from a `codicon::protobuf::Message`.
+///
+/// Fields with explicit parameters are taken from the message first, so that
+/// the fields without them receive whatever remains.
+fn decode_message(fields: &[Field<'_>]) -> Result<TokenStream> {
+    let mut taken = Vec::new();
+    let mut rest = Vec::new();
+
+    for field in fields {
+        let binding = &field.binding;
+
+        if let Some(with) = &field.attrs.with {
+            return Err(syn::Error::new(
+                with.span(),
+                "codicon cannot decode message fields with a module",
+            ));
+        }
+
+        match (&field.attrs.params, field.attrs.skip) {
+            (_, true) => rest.push(quote! {
+                let #binding = ::core::default::Default::default();
+            }),
+            (Some(params), false) => taken.push(quote! {
+                let #binding = ::codicon::protobuf::Message::take_field(&mut message, #params)
+                    .map_err(::core::convert::Into::<Self::Error>::into)?;
+            }),
+            (None, false) => rest.push(quote! {
+                let #binding = ::core::convert::From::from(::core::mem::take(&mut message));
+            }),
+        }
+    }
+
+    let members = fields.iter().map(|f| {
+        let (member, binding) = (&f.member, &f.binding);
+        quote!(#member: #binding)
+    });
+
+    Ok(quote! {
+        let mut message: ::codicon::protobuf::Message =
+            ::codicon::Decoder::decode(&mut reader, ::core::clone::Clone::clone(&params))
+                .map_err(::core::convert::Into::<Self::Error>::into)?;
+
+        #(#taken)*
+        #(#rest)*
+        ::core::result::Result::Ok(Self { #(#members),* })
+    })
+}
+
+/// A pattern binding every field of a struct or variant by reference.
+fn pattern(path: TokenStream, fields: &[Field<'_>]) -> TokenStream {
+    let fields = fields.iter().map(|f| {
+        let (member, binding) = (&f.member, &f.binding);
+        if f.named {
+            quote!(#binding)
+        } else {
+            quote!(#member: #binding)
+        }
+    });
+
+    quote!(#path { #(#fields),* })
+}
+
+fn encoder(input: &DeriveInput) -> Result<TokenStream> {
+    let attrs = TypeAttrs::parse(&input.attrs)?;
+    let error = error(&attrs);
+    let (params, generics) = params(&attrs, input);
+    let trait_: Path = parse_quote!(::codicon::Encoder);
+    let mut predicates = framing_bounds(&attrs, &error);
+
+    // The bodies of `encode` and `encoded_len`.
+    let mut bodies = [TokenStream::new(), TokenStream::new()];
+    match &input.data {
+        Data::Struct(data) => {
+            let fields = fields(&data.fields)?;
+            predicates.extend(bounds(&attrs, input, &fields, &trait_, &params, &error));
+
+            let pattern = pattern(quote!(Self), &fields);
+            let framing = framing_fields(&attrs, "encode_fields", quote!(writer), &fields);
+            for (body, len) in bodies.iter_mut().zip([false, true]) {
+                let encode = fields.iter().map(|f| encode_field(&attrs, input, f, len));
+                *body = quote! {
+                    let #pattern = self;
+                    #framing
+                    #(#encode)*
+                };
+            }
+        }
+
+        Data::Enum(data) => {
+            let tag = tag(&attrs);
+            if bounded(&attrs, input, &tag) {
+                predicates.extend(bound(&tag, &trait_, &params, &error));
+            }
+
+            let mut arms = [Vec::new(), Vec::new()];
+            for (variant, id) in variants(data)? {
+                let fields = fields(&variant.fields)?;
+                predicates.extend(bounds(&attrs, input, &fields, &trait_, &params, &error));
+
+                let ident = &variant.ident;
+                let pattern = pattern(quote!(Self::#ident), &fields);
+                let framing = framing_fields(&attrs, "encode_fields", quote!(writer), &fields);
+                for (arms, len) in arms.iter_mut().zip([false, true]) {
+                    let encode = fields.iter().map(|f| encode_field(&attrs, input, f, len));
+                    arms.push(quote! {
+                        #pattern => {
+                            let tag: #tag = #id;
+                            ::codicon::Encoder::encode(&tag, &mut writer, ::core::clone::Clone::clone(&params))
+                                .map_err(::core::convert::Into::<Self::Error>::into)?;
+                            #framing
+                            #(#encode)*
+                        }
+                    });
+                }
+            }
+
+            for (body, arms) in bodies.iter_mut().zip(arms) {
+                *body = quote! {
+                    match self {
+                        #(#arms)*
+                    }
+                };
+            }
+        }
+
+        Data::Union(data) => {
+            return Err(syn::Error::new(
+                data.union_token.span(),
+                "codicon cannot derive Encoder for unions",
+            ))
+        }
+    }
+
+    let ident = &input.ident;
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let mut where_clause = where_clause.cloned().unwrap_or_else(|| parse_quote!(where));
+    where_clause.predicates.extend(predicates);
+
+    let [encode, encoded_len] = bodies;
+    Ok(quote! {
+        impl #impl_generics ::codicon::Encoder<#params> for #ident #ty_generics #where_clause {
+            type Error = #error;
+
+            #[allow(unused_mut, unused_variables)]
+            fn encode(&self, mut writer: impl ::codicon::Write, params: #params) -> ::core::result::Result<(), Self::Error> {
+                #encode
+                ::core::result::Result::Ok(())
+            }
+
+            #[allow(unused_mut, unused_variables)]
+            fn encoded_len(&self, params: #params) -> ::core::result::Result<usize, Self::Error> {
+                let mut writer = ::codicon::io::Counter::default();
+                let mut len = 0usize;
+                #encoded_len
+                ::core::result::Result::Ok(writer.count().saturating_add(len))
+            }
+        }
+    })
+}
+
+fn decoder(input: &DeriveInput) -> Result<TokenStream> {
+    let attrs = TypeAttrs::parse(&input.attrs)?;
+    let error = error(&attrs);
+    let (params, generics) = params(&attrs, input);
+    let trait_: Path = parse_quote!(::codicon::Decoder);
+    let mut predicates = framing_bounds(&attrs, &error);
+
+    let body = match &input.data {
+        Data::Struct(data) if attrs.message => decode_message(&fields(&data.fields)?)?,
+
+        Data::Enum(data) if attrs.message => {
+            return Err(syn::Error::new(
+                data.enum_token.span(),
+                "codicon can only decode structs as messages",
+            ))
+        }
+
+        Data::Struct(data) => {
+            let fields = fields(&data.fields)?;
+            predicates.extend(bounds(&attrs, input, &fields, &trait_, &params, &error));
+
+            decode_fields(&attrs, input, quote!(Self), &fields)
+        }
+
+        Data::Enum(data) => {
+            let tag = tag(&attrs);
+            if bounded(&attrs, input, &tag) {
+                predicates.extend(bound(&tag, &trait_, &params, &error));
+            }
+            predicates.push(parse_quote!(#error: ::core::convert::From<::codicon::Error>));
+
+            let mut arms = Vec::new();
+            for (variant, id) in variants(data)? {
+                let fields = fields(&variant.fields)?;
+                predicates.extend(bounds(&attrs, input, &fields, &trait_, &params, &error));
+
+                let ident = &variant.ident;
+                let decode = decode_fields(&attrs, input, quote!(Self::#ident), &fields);
+                arms.push(quote! {
+                    tag if tag == #id => {
+                        #decode
+                    }
+                });
+            }
+
+            quote! {
+                let tag: #tag = ::codicon::Decoder::decode(&mut reader, ::core::clone::Clone::clone(&params))
+                    .map_err(::core::convert::Into::<Self::Error>::into)?;
+
+                match tag {
+                    #(#arms)*
+                    _ => ::core::result::Result::Err(::codicon::Error::invalid("unknown enum tag").into()),
+                }
+            }
+        }
+
+        Data::Union(data) => {
+            return Err(syn::Error::new(
+                data.union_token.span(),
+                "codicon cannot derive Decoder for unions",
+            ))
+        }
+    };
+
+    let ident = &input.ident;
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let mut where_clause = where_clause.cloned().unwrap_or_else(|| parse_quote!(where));
+    where_clause.predicates.extend(predicates);
+
+    Ok(quote! {
+        impl #impl_generics ::codicon::Decoder<#params> for #ident #ty_generics #where_clause {
+            type Error = #error;
+
+            #[allow(unused_mut, unused_variables)]
+            fn decode(mut reader: impl ::codicon::Read, params: #params) -> ::core::result::Result<Self, Self::Error> {
+                #body
+            }
+        }
+    })
+}
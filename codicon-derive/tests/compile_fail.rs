@@ -0,0 +1,73 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checks that invalid derives fail to compile with the expected errors.
+//!
+//! Every file in `tests/ui` is built as a binary depending on `codicon`, and
+//! must fail with an error containing the text of its first line comment.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+const MANIFEST: &str = r#"[package]
+name = "ui"
+version = "0.0.0"
+edition = "2018"
+
+[workspace]
+
+[dependencies]
+codicon = { path = "ROOT", features = ["derive"] }
+"#;
+
+#[test]
+fn compile_fail() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("ui");
+    let bins = dir.join("src").join("bin");
+    let _ = fs::remove_dir_all(&bins);
+    fs::create_dir_all(&bins).unwrap();
+
+    let manifest = MANIFEST.replace("ROOT", &root.display().to_string());
+    fs::write(dir.join("Cargo.toml"), manifest).unwrap();
+
+    // Reuse the resolved dependencies of the workspace, if any.
+    if let Ok(lock) = fs::read(root.join("Cargo.lock")) {
+        fs::write(dir.join("Cargo.lock"), lock).unwrap();
+    }
+
+    let mut cases = Vec::new();
+    for entry in fs::read_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/ui")).unwrap() {
+        let path = entry.unwrap().path();
+        let source = fs::read_to_string(&path).unwrap();
+        let name = path.file_stem().unwrap().to_str().unwrap().to_owned();
+        let expected = source
+            .lines()
+            .next()
+            .unwrap()
+            .trim_start_matches("// ")
+            .to_owned();
+        fs::write(bins.join(path.file_name().unwrap()), source).unwrap();
+        cases.push((name, expected));
+    }
+
+    let mut failures = Vec::new();
+    for (name, expected) in cases {
+        let output = Command::new(env!("CARGO"))
+            .args(["check", "--quiet", "--bin", &name])
+            .current_dir(&dir)
+            .env("CARGO_TARGET_DIR", dir.join("target"))
+            .output()
+            .unwrap();
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if output.status.success() || !stderr.contains(&expected) {
+            failures.push(format!(
+                "{}: expected `{}`, got:\n{}",
+                name, expected, stderr
+            ));
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
@@ -0,0 +1,369 @@
+// SPDX-License-Identifier: Apache-2.0
+
+#![deny(non_shorthand_field_patterns)]
+
+use codicon::*;
+
+#[derive(Encoder, Decoder, Debug, PartialEq)]
+struct Point {
+    x: u8,
+    y: u8,
+    #[codicon(skip)]
+    cached: Option<u8>,
+}
+
+#[derive(Encoder, Decoder, Debug, PartialEq)]
+struct Tuple(u8, #[codicon(skip)] u8, u16);
+
+#[test]
+fn fields_in_order() {
+    let point = Point {
+        x: 1,
+        y: 2,
+        cached: Some(3),
+    };
+
+    let buf = point.encode_to_vec(Be).unwrap();
+    assert_eq!(buf, [1, 2]);
+
+    let decoded = Point::decode_exact(&buf, Be).unwrap();
+    assert_eq!(
+        decoded,
+        Point {
+            cached: None,
+            ..point
+        }
+    );
+
+    let buf = Tuple(1, 2, 0x0304).encode_to_vec(Be).unwrap();
+    assert_eq!(buf, [1, 3, 4]);
+    assert_eq!(Tuple::decode_exact(&buf, Be).unwrap(), Tuple(1, 0, 0x0304));
+}
+
+#[test]
+fn framed_fields() {
+    let points = vec![Point {
+        x: 1,
+        y: 2,
+        cached: None,
+    }];
+
+    let cbor = Cbor::new();
+    let buf = points.encode_to_vec(cbor).unwrap();
+    assert_eq!(buf, [0x81, 0x82, 0x01, 0x02]);
+    assert_eq!(Vec::<Point>::decode_exact(&buf, cbor).unwrap(), points);
+    assert!(Point::decode_exact(&[0x83, 0x01, 0x02, 0x03], cbor).is_err());
+}
+
+#[derive(Encoder, Decoder, Debug, PartialEq)]
+#[codicon(params = Be)]
+struct Mixed {
+    #[codicon(params = Le)]
+    little: u16,
+    big: u16,
+    #[codicon(params = Prefixed::new(Uleb128, params))]
+    items: Vec<u16>,
+}
+
+#[test]
+fn field_params() {
+    let mixed = Mixed {
+        little: 0x0102,
+        big: 0x0102,
+        items: vec![0x0304],
+    };
+
+    let buf = mixed.encode_to_vec(Be).unwrap();
+    assert_eq!(buf, [2, 1, 1, 2, 1, 3, 4]);
+    assert_eq!(Mixed::decode_exact(&buf, Be).unwrap(), mixed);
+}
+
+mod inverted {
+    use codicon::*;
+
+    pub fn encode(value: &u8, writer: impl Write, params: Be) -> Result<(), Error> {
+        (!value).encode(writer, params)
+    }
+
+    pub fn decode(reader: impl Read, params: Be) -> Result<u8, Error> {
+        u8::decode(reader, params).map(|value| !value)
+    }
+}
+
+#[derive(Encoder, Decoder, Debug, PartialEq)]
+#[codicon(params = Be)]
+struct With {
+    #[codicon(with = inverted)]
+    value: u8,
+}
+
+#[test]
+fn with_module() {
+    let buf = With { value: 0x0f }.encode_to_vec(Be).unwrap();
+    assert_eq!(buf, [0xf0]);
+    assert_eq!(With::decode_exact(&buf, Be).unwrap(), With { value: 0x0f });
+}
+
+#[derive(Debug)]
+enum Custom {
+    Codicon(Error),
+}
+
+impl From<Error> for Custom {
+    fn from(e: Error) -> Self {
+        Self::Codicon(e)
+    }
+}
+
+#[derive(Encoder, Decoder, Debug, PartialEq)]
+#[codicon(error = Custom)]
+struct Errors {
+    value: u32,
+}
+
+#[test]
+fn error_type() {
+    let result: Result<Errors, Custom> = Errors::decode(&[0u8, 1][..], Be);
+    match result {
+        Err(Custom::Codicon(e)) => assert!(e.is_eof()),
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[derive(Encoder, Decoder, Debug, PartialEq)]
+#[codicon(tag = u16)]
+enum Message {
+    Ping,
+    #[codicon(id = 0x100)]
+    Data(u8),
+    Close {
+        code: u8,
+    },
+    #[codicon(id = 7)]
+    Reset,
+}
+
+#[test]
+fn tags_and_ids() {
+    let cases = [
+        (Message::Ping, vec![0, 0]),
+        (Message::Data(9), vec![1, 0, 9]),
+        (Message::Close { code: 3 }, vec![1, 1, 3]),
+        (Message::Reset, vec![0, 7]),
+    ];
+
+    for (message, bytes) in &cases {
+        assert_eq!(&message.encode_to_vec(Be).unwrap(), bytes);
+        assert_eq!(&Message::decode_exact(bytes, Be).unwrap(), message);
+    }
+
+    assert!(Message::decode_exact(&[0, 1], Be).is_err());
+}
+
+#[derive(Encoder, Decoder, Debug, PartialEq)]
+struct Pair<T> {
+    first: T,
+    second: T,
+}
+
+#[derive(Encoder, Decoder, Debug, PartialEq)]
+struct Array<const N: usize> {
+    bytes: [u8; N],
+}
+
+#[test]
+fn generics() {
+    let pair = Pair {
+        first: 1u16,
+        second: 2u16,
+    };
+
+    let buf = pair.encode_to_vec(Le).unwrap();
+    assert_eq!(buf, [1, 0, 2, 0]);
+    assert_eq!(Pair::<u16>::decode_exact(&buf, Le).unwrap(), pair);
+
+    let array = Array { bytes: [1, 2, 3] };
+    let buf = array.encode_to_vec(Be).unwrap();
+    assert_eq!(buf, [1, 2, 3]);
+    assert_eq!(Array::<3>::decode_exact(&buf, Be).unwrap(), array);
+}
+
+#[derive(Encoder, Decoder, Debug, PartialEq)]
+#[codicon(params = Cbor)]
+struct Node {
+    value: u8,
+    kids: Vec<Node>,
+}
+
+#[derive(Encoder, Decoder, Debug, PartialEq)]
+#[codicon(params = Cbor)]
+struct Tree<T> {
+    value: T,
+    kids: Vec<Tree<T>>,
+}
+
+#[test]
+fn recursive() {
+    let node = Node {
+        value: 1,
+        kids: vec![Node {
+            value: 2,
+            kids: vec![],
+        }],
+    };
+
+    let buf = node.encode_to_vec(Cbor::new()).unwrap();
+    assert_eq!(Node::decode_exact(&buf, Cbor::new()).unwrap(), node);
+
+    let tree = Tree {
+        value: String::from("root"),
+        kids: vec![Tree {
+            value: String::from("leaf"),
+            kids: vec![],
+        }],
+    };
+
+    let buf = tree.encode_to_vec(Cbor::new()).unwrap();
+    assert_eq!(Tree::decode_exact(&buf, Cbor::new()).unwrap(), tree);
+}
+
+static ENCODES: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+/// A NULL which counts how often it is encoded.
+struct Counted;
+
+impl Encoder<Der> for Counted {
+    type Error = Error;
+
+    fn encode(&self, writer: impl Write, params: Der) -> Result<(), Error> {
+        ENCODES.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        der::Null.encode(writer, params)
+    }
+
+    fn encoded_len(&self, _: Der) -> Result<usize, Error> {
+        Ok(2)
+    }
+}
+
+mod null {
+    use codicon::*;
+
+    pub fn encode(_: &(), writer: impl Write, params: Der) -> Result<(), Error> {
+        der::Null.encode(writer, params)
+    }
+}
+
+#[derive(Encoder)]
+#[codicon(params = Der)]
+struct Level<T> {
+    inner: der::Sequence<(T, u8)>,
+    #[codicon(with = null)]
+    marker: (),
+}
+
+macro_rules! nest {
+    ($value:expr;) => { $value };
+    ($value:expr; $_:tt $($rest:tt)*) => {
+        nest!(Level { inner: der::Sequence(($value, 0)), marker: () }; $($rest)*)
+    };
+}
+
+#[test]
+fn encoded_len() {
+    let value = nest!(Counted; 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20);
+    let buf = value.encode_to_vec(Der::new()).unwrap();
+    assert_eq!(value.encoded_len(Der::new()).unwrap(), buf.len());
+    assert_eq!(ENCODES.load(std::sync::atomic::Ordering::Relaxed), 1);
+}
+
+#[derive(Encoder, Decoder, Debug, Default, PartialEq)]
+#[codicon(params = Protobuf, message)]
+struct Inner {
+    #[codicon(params = protobuf::Field::new(1, ZigZag))]
+    value: i32,
+}
+
+#[derive(Encoder, Decoder, Debug, PartialEq)]
+#[codicon(params = Protobuf, message)]
+struct Outer {
+    unknown: protobuf::Message,
+    #[codicon(params = protobuf::Field::new(1, params))]
+    name: String,
+    #[codicon(params = protobuf::Field::new(2, Framed::new(params, Uleb128)))]
+    inner: Inner,
+    #[codicon(params = protobuf::Field::new(3, protobuf::Packed(params)))]
+    items: Vec<u32>,
+    #[codicon(skip)]
+    cached: Option<u8>,
+}
+
+#[test]
+fn messages() {
+    let protobuf = Protobuf::new();
+
+    // Field 4 is unknown; field 3 arrives both unpacked and packed.
+    let buf = [
+        0x18, 0x05, 0x12, 0x02, 0x08, 0x03, 0x20, 0x07, 0x0a, 0x01, b'a', 0x1a, 0x01, 0x06,
+    ];
+    let outer = Outer::decode_exact(&buf, protobuf).unwrap();
+    assert_eq!(outer.name, "a");
+    assert_eq!(outer.inner, Inner { value: -2 });
+    assert_eq!(outer.items, [5, 6]);
+    assert_eq!(outer.unknown.records().len(), 1);
+    assert_eq!(outer.cached, None);
+
+    let buf = outer.encode_to_vec(protobuf).unwrap();
+    assert_eq!(
+        buf,
+        [0x20, 0x07, 0x0a, 0x01, b'a', 0x12, 0x02, 0x08, 0x03, 0x1a, 0x02, 0x05, 0x06]
+    );
+    assert_eq!(Outer::decode_exact(&buf, protobuf).unwrap(), outer);
+
+    let empty = Outer::decode_exact(&[], protobuf).unwrap();
+    assert_eq!(empty.inner, Inner::default());
+    assert!(empty.items.is_empty());
+}
+
+#[derive(Encoder, Decoder, Debug, PartialEq)]
+struct Keyed {
+    x: u8,
+    #[codicon(skip)]
+    cached: Option<u8>,
+    name: String,
+}
+
+#[test]
+fn keyed_fields() {
+    let msgpack = MsgPack::new().structs(msgpack::Structs::Map);
+    let keyed = Keyed {
+        x: 1,
+        cached: None,
+        name: "a".into(),
+    };
+
+    let buf = keyed.encode_to_vec(msgpack).unwrap();
+    assert_eq!(
+        buf,
+        [0x82, 0xa1, b'x', 0x01, 0xa4, b'n', b'a', b'm', b'e', 0xa1, b'a']
+    );
+    assert_eq!(Keyed::decode_exact(&buf, msgpack).unwrap(), keyed);
+
+    // Any order, skipping the unknown keys "y" and "extra".
+    let buf = [
+        0x84, 0xa1, b'y', 0x92, 0x01, 0x80, 0xa4, b'n', b'a', b'm', b'e', 0xa1, b'a', 0xa5, b'e',
+        b'x', b't', b'r', b'a', 0xc0, 0xa1, b'x', 0x01,
+    ];
+    assert_eq!(Keyed::decode_exact(&buf, msgpack).unwrap(), keyed);
+
+    for buf in [
+        &[0x81, 0xa1, b'x', 0x01][..],
+        &[
+            0x83, 0xa1, b'x', 0x01, 0xa1, b'x', 0x01, 0xa4, b'n', b'a', b'm', b'e', 0xa0,
+        ],
+    ] {
+        assert!(matches!(
+            Keyed::decode_exact(buf, msgpack).unwrap_err(),
+            Error::InvalidValue { .. }
+        ));
+    }
+}
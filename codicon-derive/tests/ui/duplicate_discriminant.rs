@@ -0,0 +1,12 @@
+// duplicate id 1 for variant `Reset`
+use codicon::*;
+
+#[derive(Decoder)]
+enum Message {
+    Close = 1,
+    #[codicon(id = 0)]
+    Open,
+    Reset,
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+// duplicate id 2 for variant `C`
+use codicon::*;
+
+#[derive(Encoder)]
+enum Message {
+    #[codicon(id = 1)]
+    A,
+    B,
+    #[codicon(id = 2)]
+    C,
+}
+
+fn main() {}
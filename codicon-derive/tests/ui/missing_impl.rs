@@ -0,0 +1,12 @@
+// is not implemented for `Opaque`
+use codicon::*;
+
+struct Opaque;
+
+#[derive(Encoder)]
+#[codicon(params = Be)]
+struct Wrapper {
+    inner: Opaque,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+// is not implemented for `Vec<Node>`
+use codicon::*;
+
+// Recursive types need explicit parameters.
+#[derive(Encoder)]
+struct Node {
+    value: u8,
+    kids: Vec<Node>,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+// codicon cannot derive Encoder for unions
+use codicon::*;
+
+#[derive(Encoder)]
+union Bits {
+    int: u32,
+    float: f32,
+}
+
+fn main() {}
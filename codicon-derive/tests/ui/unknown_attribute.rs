@@ -0,0 +1,10 @@
+// unsupported codicon attribute
+use codicon::*;
+
+#[derive(Encoder)]
+struct Point {
+    #[codicon(rename = "X")]
+    x: u8,
+}
+
+fn main() {}
@@ -0,0 +1,597 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! The Bencode format, used by BitTorrent.
+//!
+//! Under the `Bencode` parameters, integers encode as Bencode integers,
+//! strings as byte strings, `Vec` and slices as lists and `BTreeMap`s with
+//! `String` or `Vec<u8>` keys as dictionaries. Byte strings use the
+//! `ByteString` parameters instead, since `Vec<u8>` is a list of integers
+//! under `Bencode`. A `Value` holds any Bencode value.
+//!
+//! Each value has a single valid encoding, which decoding enforces: integers
+//! and lengths may not have leading zeros, `-0` is invalid and dictionary
+//! keys must be unique and sorted. A decoded value thus encodes back to the
+//! exact same bytes, as computing the info hash of a torrent requires.
+//! Lists and dictionaries may only nest as deep as the depth limit of the
+//! parameters' `Limits`, 128 by default.
+//!
+//! ```rust
+//! use codicon::*;
+//! use codicon::bencode::{ByteString, Value};
+//! use std::collections::BTreeMap;
+//!
+//! let bencode = Bencode::new();
+//! assert_eq!((-3i32).encode_to_vec(bencode).unwrap(), b"i-3e");
+//! assert_eq!("spam".encode_to_vec(bencode).unwrap(), b"4:spam");
+//! assert_eq!([1u8, 2][..].encode_to_vec(ByteString(bencode)).unwrap(), b"2:\x01\x02");
+//! assert_eq!(vec![1u8, 2].encode_to_vec(bencode).unwrap(), b"li1ei2ee");
+//!
+//! let mut map = BTreeMap::new();
+//! map.insert("foo".to_string(), 42u64);
+//! map.insert("bar".to_string(), 7);
+//! assert_eq!(map.encode_to_vec(bencode).unwrap(), b"d3:bari7e3:fooi42ee");
+//!
+//! assert!(i32::decode_exact(b"i03e", bencode).is_err());
+//! assert!(i32::decode_exact(b"i-0e", bencode).is_err());
+//! assert!(BTreeMap::<String, u64>::decode_exact(b"d3:fooi42e3:bari7ee", bencode).is_err());
+//!
+//! let torrent = b"d8:announce3:url4:infod6:lengthi5e4:name1:aee";
+//! let value = Value::decode_exact(&torrent[..], bencode).unwrap();
+//! let info = match &value {
+//!     Value::Dict(dict) => &dict[&b"info"[..]],
+//!     _ => unreachable!(),
+//! };
+//!
+//! assert_eq!(info.encode_to_vec(bencode).unwrap(), b"d6:lengthi5e4:name1:ae");
+//! assert_eq!(value.encode_to_vec(bencode).unwrap(), torrent);
+//! ```
+
+use crate::io::{self, byte, Prepend, Read, Write};
+use crate::limits::Limits;
+use crate::{Decoder, Encoder, Error};
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+/// Bencode encoding parameters.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Bencode {
+    limits: Limits,
+}
+
+impl Bencode {
+    /// Creates parameters with the default limits.
+    pub const fn new() -> Self {
+        Self {
+            limits: Limits::DEFAULT,
+        }
+    }
+
+    /// Sets the limits checked when decoding.
+    pub fn with_limits(self, limits: Limits) -> Self {
+        Self { limits }
+    }
+
+    /// Returns the limits checked when decoding.
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+}
+
+/// Parameters for Bencode byte strings.
+///
+/// `[u8]` and `Vec<u8>` encode as a single byte string under these
+/// parameters, rather than as a list of integers.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ByteString(pub Bencode);
+
+/// Any Bencode value.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Value {
+    /// An integer, which decoding rejects beyond the range of `i128`.
+    Integer(i128),
+
+    /// A byte string.
+    Bytes(Vec<u8>),
+
+    /// A list of values.
+    List(Vec<Value>),
+
+    /// A dictionary from byte strings to values.
+    Dict(BTreeMap<Vec<u8>, Value>),
+}
+
+fn decimal(mut writer: impl Write, mut value: u128) -> Result<(), Error> {
+    let mut buf = [0u8; 39];
+    let mut start = buf.len();
+
+    loop {
+        start -= 1;
+        buf[start] = b'0' + (value % 10) as u8;
+        value /= 10;
+
+        if value == 0 {
+            return Ok(writer.write_all(&buf[start..])?);
+        }
+    }
+}
+
+fn integer(mut writer: impl Write, negative: bool, magnitude: u128) -> Result<(), Error> {
+    writer.write_all(if negative { b"i-" } else { b"i" })?;
+    decimal(&mut writer, magnitude)?;
+    Ok(writer.write_all(b"e")?)
+}
+
+fn string(mut writer: impl Write, bytes: &[u8]) -> Result<(), Error> {
+    decimal(&mut writer, bytes.len() as u128)?;
+    writer.write_all(b":")?;
+    Ok(writer.write_all(bytes)?)
+}
+
+/// Reads the digits starting with `first` up to `end`, rejecting leading zeros.
+fn digits(mut reader: impl Read, first: u8, end: u8) -> Result<u128, Error> {
+    let mut value = 0u128;
+    let mut count = 0;
+
+    let mut byte = first;
+    while byte != end {
+        if !byte.is_ascii_digit() {
+            return Err(Error::invalid("expected digit"));
+        }
+
+        if count == 1 && value == 0 {
+            return Err(Error::invalid("leading zero"));
+        }
+
+        value = value
+            .checked_mul(10)
+            .and_then(|value| value.checked_add(u128::from(byte - b'0')))
+            .ok_or(Error::invalid("integer out of range"))?;
+
+        count += 1;
+        byte = io::byte(&mut reader)?;
+    }
+
+    match count {
+        0 => Err(Error::invalid("expected digit")),
+        _ => Ok(value),
+    }
+}
+
+/// Reads an integer following the initial byte, as a sign and a magnitude.
+fn read_integer(mut reader: impl Read, initial: u8) -> Result<(bool, u128), Error> {
+    if initial != b'i' {
+        return Err(Error::invalid("expected integer"));
+    }
+
+    let (negative, first) = match byte(&mut reader)? {
+        b'-' => (true, byte(&mut reader)?),
+        first => (false, first),
+    };
+
+    match digits(reader, first, b'e')? {
+        0 if negative => Err(Error::invalid("negative zero")),
+        magnitude => Ok((negative, magnitude)),
+    }
+}
+
+/// Reads a byte string following the initial byte, without trusting its
+/// length with a large allocation.
+fn read_string(mut reader: impl Read, initial: u8, limits: Limits) -> Result<Vec<u8>, Error> {
+    if !initial.is_ascii_digit() {
+        return Err(Error::invalid("expected byte string"));
+    }
+
+    let len = digits(&mut reader, initial, b':')?;
+    let len = usize::try_from(len).map_err(|_| Error::limit("length exceeds usize"))?;
+    limits.check_len(len)?;
+    limits.check_alloc::<u8>(len)?;
+
+    Ok(io::read_vec(reader, len)?)
+}
+
+/// Reads the items of a list after its initial byte, passing each item's
+/// initial byte to `item`.
+fn read_list<R: Read>(
+    reader: &mut R,
+    limits: Limits,
+    mut item: impl FnMut(&mut R, u8) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let mut len = 0usize;
+
+    loop {
+        match byte(&mut *reader)? {
+            b'e' => return Ok(()),
+            initial => {
+                len += 1;
+                limits.check_len(len)?;
+                item(reader, initial)?;
+            }
+        }
+    }
+}
+
+/// Reads the entries of a dictionary after its initial byte, passing each
+/// key to `entry` to read the value.
+fn read_dict<R: Read>(
+    reader: &mut R,
+    limits: Limits,
+    mut entry: impl FnMut(&mut R, Vec<u8>) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let mut last: Option<Vec<u8>> = None;
+    let mut len = 0usize;
+
+    loop {
+        let key = match byte(&mut *reader)? {
+            b'e' => return Ok(()),
+            initial => read_string(&mut *reader, initial, limits)?,
+        };
+
+        if matches!(&last, Some(last) if *last >= key) {
+            return Err(Error::invalid("unsorted dictionary keys"));
+        }
+
+        len += 1;
+        limits.check_len(len)?;
+
+        last = Some(key.clone());
+        entry(reader, key)?;
+    }
+}
+
+fn read_value<R: Read>(reader: &mut R, initial: u8, limits: Limits) -> Result<Value, Error> {
+    Ok(match initial {
+        b'i' => Value::Integer(i128::decode(
+            Prepend::new(initial, reader),
+            Bencode { limits },
+        )?),
+
+        b'l' => {
+            let limits = limits.nested()?;
+            let mut items = Vec::new();
+            read_list(reader, limits, |reader, initial| {
+                items.push(read_value(reader, initial, limits)?);
+                Ok(())
+            })?;
+
+            Value::List(items)
+        }
+
+        b'd' => {
+            let limits = limits.nested()?;
+            let mut dict = BTreeMap::new();
+            read_dict(reader, limits, |reader, key| {
+                let initial = byte(&mut *reader)?;
+                dict.insert(key, read_value(reader, initial, limits)?);
+                Ok(())
+            })?;
+
+            Value::Dict(dict)
+        }
+
+        b'0'..=b'9' => Value::Bytes(read_string(reader, initial, limits)?),
+        _ => return Err(Error::invalid("unexpected type")),
+    })
+}
+
+fn write_value<W: Write>(writer: &mut W, value: &Value) -> Result<(), Error> {
+    match value {
+        Value::Integer(value) => integer(writer, *value < 0, value.unsigned_abs()),
+        Value::Bytes(bytes) => string(writer, bytes),
+        Value::List(items) => {
+            writer.write_all(b"l")?;
+
+            for item in items {
+                write_value(&mut *writer, item)?;
+            }
+
+            Ok(writer.write_all(b"e")?)
+        }
+        Value::Dict(dict) => {
+            writer.write_all(b"d")?;
+
+            for (key, value) in dict {
+                string(&mut *writer, key)?;
+                write_value(&mut *writer, value)?;
+            }
+
+            Ok(writer.write_all(b"e")?)
+        }
+    }
+}
+
+macro_rules! int {
+    ($($t:ident)+) => {
+        $(
+            impl Encoder<Bencode> for $t {
+                type Error = Error;
+
+                fn encode(&self, writer: impl Write, _: Bencode) -> Result<(), Error> {
+                    match u128::try_from(*self) {
+                        Ok(magnitude) => integer(writer, false, magnitude),
+                        Err(_) => {
+                            let value = i128::try_from(*self)
+                                .map_err(|_| Error::invalid("integer out of range"))?;
+                            integer(writer, true, value.unsigned_abs())
+                        }
+                    }
+                }
+            }
+
+            impl Decoder<Bencode> for $t {
+                type Error = Error;
+
+                fn decode(mut reader: impl Read, _: Bencode) -> Result<Self, Error> {
+                    let initial = byte(&mut reader)?;
+                    let value = match read_integer(reader, initial)? {
+                        (false, magnitude) => $t::try_from(magnitude).ok(),
+                        (true, magnitude) => match i128::try_from(magnitude) {
+                            Ok(magnitude) => $t::try_from(-magnitude).ok(),
+                            Err(_) if magnitude == 1 << 127 => $t::try_from(i128::MIN).ok(),
+                            Err(_) => None,
+                        },
+                    };
+
+                    value.ok_or(Error::invalid("integer out of range"))
+                }
+            }
+        )+
+    };
+}
+
+int!(u8 u16 u32 u64 u128 usize i8 i16 i32 i64 i128 isize);
+
+impl Encoder<ByteString> for [u8] {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, _: ByteString) -> Result<(), Error> {
+        string(writer, self)
+    }
+}
+
+impl Encoder<ByteString> for Vec<u8> {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, _: ByteString) -> Result<(), Error> {
+        string(writer, self)
+    }
+}
+
+impl Decoder<ByteString> for Vec<u8> {
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: ByteString) -> Result<Self, Error> {
+        let initial = byte(&mut reader)?;
+        read_string(reader, initial, params.0.limits)
+    }
+}
+
+impl Encoder<Bencode> for str {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, _: Bencode) -> Result<(), Error> {
+        string(writer, self.as_bytes())
+    }
+}
+
+impl Encoder<Bencode> for String {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, _: Bencode) -> Result<(), Error> {
+        string(writer, self.as_bytes())
+    }
+}
+
+impl Decoder<Bencode> for String {
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: Bencode) -> Result<Self, Error> {
+        let initial = byte(&mut reader)?;
+        let bytes = read_string(reader, initial, params.limits)?;
+        String::from_utf8(bytes).map_err(|_| Error::invalid("invalid UTF-8"))
+    }
+}
+
+impl<T: Encoder<Bencode>> Encoder<Bencode> for [T]
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, params: Bencode) -> Result<(), Error> {
+        writer.write_all(b"l")?;
+
+        for item in self {
+            item.encode(&mut writer, params).map_err(Into::into)?;
+        }
+
+        Ok(writer.write_all(b"e")?)
+    }
+}
+
+impl<T: Encoder<Bencode>> Encoder<Bencode> for Vec<T>
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: Bencode) -> Result<(), Error> {
+        self.as_slice().encode(writer, params)
+    }
+}
+
+impl<T: Decoder<Bencode>> Decoder<Bencode> for Vec<T>
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: Bencode) -> Result<Self, Error> {
+        if byte(&mut reader)? != b'l' {
+            return Err(Error::invalid("expected list"));
+        }
+
+        let params = Bencode::new().with_limits(params.limits.nested()?);
+        let mut items = Vec::new();
+        read_list(&mut reader, params.limits, |reader, initial| {
+            let item = T::decode(Prepend::new(initial, reader), params).map_err(Into::into)?;
+            items.push(item);
+            Ok(())
+        })?;
+
+        Ok(items)
+    }
+}
+
+macro_rules! dict {
+    ($($k:ty => $key:expr),+) => {
+        $(
+            impl<V: Encoder<Bencode>> Encoder<Bencode> for BTreeMap<$k, V>
+            where
+                V::Error: Into<Error>,
+            {
+                type Error = Error;
+
+                fn encode(&self, mut writer: impl Write, params: Bencode) -> Result<(), Error> {
+                    writer.write_all(b"d")?;
+
+                    for (key, value) in self {
+                        string(&mut writer, key.as_ref())?;
+                        value.encode(&mut writer, params).map_err(Into::into)?;
+                    }
+
+                    Ok(writer.write_all(b"e")?)
+                }
+            }
+
+            impl<V: Decoder<Bencode>> Decoder<Bencode> for BTreeMap<$k, V>
+            where
+                V::Error: Into<Error>,
+            {
+                type Error = Error;
+
+                fn decode(mut reader: impl Read, params: Bencode) -> Result<Self, Error> {
+                    if byte(&mut reader)? != b'd' {
+                        return Err(Error::invalid("expected dictionary"));
+                    }
+
+                    let params = Bencode::new().with_limits(params.limits.nested()?);
+                    let mut map = BTreeMap::new();
+                    read_dict(&mut reader, params.limits, |reader, key| {
+                        let value = V::decode(&mut *reader, params).map_err(Into::into)?;
+                        map.insert($key(key)?, value);
+                        Ok(())
+                    })?;
+
+                    Ok(map)
+                }
+            }
+        )+
+    };
+}
+
+dict! {
+    String => |key| String::from_utf8(key).map_err(|_| Error::invalid("invalid UTF-8")),
+    Vec<u8> => Ok::<_, Error>
+}
+
+impl Encoder<Bencode> for Value {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, mut writer: impl Write, _: Bencode) -> Result<(), Error> {
+        write_value(&mut writer, self)
+    }
+}
+
+impl Decoder<Bencode> for Value {
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: Bencode) -> Result<Self, Error> {
+        let initial = byte(&mut reader)?;
+        read_value(&mut reader, initial, params.limits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DecoderExt, EncoderExt};
+    use alloc::vec;
+
+    fn invalid<T: Decoder<Bencode, Error = Error> + core::fmt::Debug>(bytes: &[u8], reason: &str) {
+        match T::decode_exact(bytes, Bencode::new()) {
+            Err(Error::InvalidValue { reason: actual, .. }) => assert_eq!(actual, reason),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unsorted_keys() {
+        invalid::<Value>(b"d1:bi1e1:ai2ee", "unsorted dictionary keys");
+        invalid::<Value>(b"d1:ai1e1:ai2ee", "unsorted dictionary keys");
+        invalid::<Value>(b"d2:abi1e1:ai2ee", "unsorted dictionary keys");
+        invalid::<BTreeMap<String, u8>>(b"d1:bi1e1:ai2ee", "unsorted dictionary keys");
+
+        // Keys compare as raw bytes, so a prefix sorts first.
+        let buf = b"d1:ai1e2:abi2ee";
+        let value = Value::decode_exact(&buf[..], Bencode::new()).unwrap();
+        assert_eq!(value.encode_to_vec(Bencode::new()).unwrap(), buf);
+    }
+
+    #[test]
+    fn non_canonical_integers() {
+        invalid::<Value>(b"i03e", "leading zero");
+        invalid::<Value>(b"i-0e", "negative zero");
+        invalid::<Value>(b"01:a", "leading zero");
+    }
+
+    fn too_deep<T: Decoder<Bencode, Error = Error> + core::fmt::Debug>(
+        bytes: &[u8],
+        params: Bencode,
+    ) {
+        match T::decode_exact(bytes, params) {
+            Err(Error::LimitExceeded { limit, .. }) => assert_eq!(limit, "nesting depth"),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn deep_nesting() {
+        too_deep::<Value>(&vec![b'l'; 1_000_000], Bencode::new());
+
+        let mut buf = vec![b'l'; 128];
+        buf.extend(core::iter::repeat(b'e').take(128));
+        assert!(Value::decode_exact(&buf, Bencode::new()).is_ok());
+
+        let shallow = Bencode::new().with_limits(Limits::default().depth(1));
+        too_deep::<Value>(b"llee", shallow);
+        too_deep::<Vec<Vec<u8>>>(b"llee", shallow);
+        too_deep::<BTreeMap<String, Vec<u8>>>(b"d1:alee", shallow);
+        assert!(Vec::<u8>::decode_exact(b"le", shallow).is_ok());
+    }
+
+    #[test]
+    fn wide_integers() {
+        let buf = b"i-170141183460469231731687303715884105728e";
+        let value = Value::decode_exact(&buf[..], Bencode::new()).unwrap();
+        assert_eq!(value, Value::Integer(i128::MIN));
+        assert_eq!(value.encode_to_vec(Bencode::new()).unwrap(), buf);
+
+        let buf = b"i18446744073709551616e";
+        let value = Value::decode_exact(&buf[..], Bencode::new()).unwrap();
+        assert_eq!(value, Value::Integer(1 << 64));
+
+        invalid::<Value>(
+            b"i170141183460469231731687303715884105728e",
+            "integer out of range",
+        );
+    }
+}
@@ -0,0 +1,136 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Decoding values which borrow from the input.
+//!
+//! `BorrowDecoder` decodes from a byte slice and may return values which
+//! borrow from it, such as `&[u8]` and `&str` decoded with `ByteStr`
+//! parameters, avoiding allocation and copying. Every `Decoder` is also a
+//! `BorrowDecoder` under the `Owned` parameters.
+//!
+//! ```rust
+//! use codicon::*;
+//!
+//! let input = [5u8, b'h', b'e', b'l', b'l', b'o', 0x2a];
+//! let mut cursor = &input[..];
+//!
+//! let text = <&str>::borrow_decode(&mut cursor, ByteStr::new(Uleb128)).unwrap();
+//! let byte = u8::borrow_decode(&mut cursor, Owned(Le)).unwrap();
+//! assert_eq!((text, byte), ("hello", 0x2a));
+//! assert!(cursor.is_empty());
+//! ```
+
+use crate::container::ByteStr;
+use crate::{Decoder, Error};
+
+/// Trait used to express decoding relationships which borrow from the input.
+pub trait BorrowDecoder<'de, T>: Sized {
+    type Error;
+
+    /// Decodes from the input with the given parameters.
+    ///
+    /// The input is advanced past the decoded bytes.
+    fn borrow_decode(input: &mut &'de [u8], params: T) -> Result<Self, Self::Error>;
+}
+
+/// Parameters which decode an owned value using the parameters `P`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Owned<P>(pub P);
+
+impl<'de, D: Decoder<P>, P> BorrowDecoder<'de, Owned<P>> for D {
+    type Error = D::Error;
+
+    #[inline]
+    fn borrow_decode(input: &mut &'de [u8], params: Owned<P>) -> Result<Self, Self::Error> {
+        D::decode(input, params.0)
+    }
+}
+
+impl<'de, L> BorrowDecoder<'de, ByteStr<L>> for &'de [u8]
+where
+    usize: Decoder<L>,
+    <usize as Decoder<L>>::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn borrow_decode(input: &mut &'de [u8], params: ByteStr<L>) -> Result<Self, Error> {
+        let reader: &mut &[u8] = input;
+        let len = usize::decode(reader, params.len).map_err(Into::into)?;
+        params.limits.check_len(len)?;
+
+        if input.len() < len {
+            return Err(Error::eof());
+        }
+
+        let (bytes, rest) = input.split_at(len);
+        *input = rest;
+        Ok(bytes)
+    }
+}
+
+impl<'de, L> BorrowDecoder<'de, ByteStr<L>> for &'de str
+where
+    usize: Decoder<L>,
+    <usize as Decoder<L>>::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn borrow_decode(input: &mut &'de [u8], params: ByteStr<L>) -> Result<Self, Error> {
+        let bytes = <&[u8]>::borrow_decode(input, params)?;
+        core::str::from_utf8(bytes).map_err(|_| Error::invalid("invalid UTF-8"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Le, Limits, Uleb128};
+
+    #[test]
+    fn zero_copy() {
+        let input = [3u8, 1, 2, 3, 4];
+        let mut cursor = &input[..];
+
+        let bytes = <&[u8]>::borrow_decode(&mut cursor, ByteStr::new(Uleb128)).unwrap();
+        assert_eq!(bytes, [1, 2, 3]);
+        assert_eq!(bytes.as_ptr(), input[1..].as_ptr());
+        assert_eq!(cursor, [4]);
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let input = [2u8, 0xc3, 0x28];
+        let mut cursor = &input[..];
+
+        assert!(matches!(
+            <&str>::borrow_decode(&mut cursor, ByteStr::new(Uleb128)),
+            Err(Error::InvalidValue {
+                reason: "invalid UTF-8",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn short_input() {
+        let input = [4u8, b'a', b'b', b'c'];
+        let mut cursor = &input[..];
+        let error = <&str>::borrow_decode(&mut cursor, ByteStr::new(Uleb128)).unwrap_err();
+        assert!(error.is_eof());
+
+        let mut cursor = &[][..];
+        let error = <&[u8]>::borrow_decode(&mut cursor, ByteStr::new(Uleb128)).unwrap_err();
+        assert!(error.is_eof());
+
+        let mut cursor = &[1u8][..];
+        assert!(u16::borrow_decode(&mut cursor, Owned(Le))
+            .unwrap_err()
+            .is_eof());
+
+        let params = ByteStr::new(Uleb128).with_limits(Limits::NONE.len(2));
+        let mut cursor = &input[..];
+        assert!(matches!(
+            <&[u8]>::borrow_decode(&mut cursor, params),
+            Err(Error::LimitExceeded { .. })
+        ));
+    }
+}
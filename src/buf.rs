@@ -0,0 +1,205 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Integration with the `bytes` crate.
+//!
+//! Any value can be encoded into a `bytes::BufMut` with
+//! `EncoderExt::encode_to_buf()` and decoded from a `bytes::Buf` with
+//! `DecoderExt::decode_from_buf()`, without first adapting the buffer to
+//! `Read` or `Write`.
+//!
+//! `Bytes` and `BytesMut` encode and decode like `Vec<u8>` under `ByteStr`
+//! parameters. In addition, `BytesDecoder` decodes from a `Bytes` buffer and
+//! may return values which share its memory: decoding `Bytes` this way slices
+//! the input rather than copying it. Every `Decoder` is also a `BytesDecoder`
+//! under the `Owned` parameters.
+//!
+//! ```rust
+//! use codicon::*;
+//! use codicon::buf::BytesDecoder;
+//! use bytes::{Bytes, BytesMut};
+//!
+//! let mut buf = BytesMut::new();
+//! "hello".encode_to_buf(&mut buf, ByteStr::new(Uleb128)).unwrap();
+//! 0x2au8.encode_to_buf(&mut buf, Le).unwrap();
+//!
+//! let mut input = buf.freeze();
+//! let text = Bytes::decode_bytes(&mut input, ByteStr::new(Uleb128)).unwrap();
+//! let byte = u8::decode_bytes(&mut input, Owned(Le)).unwrap();
+//! assert_eq!((&text[..], byte), (&b"hello"[..], 0x2a));
+//! assert!(input.is_empty());
+//! ```
+
+use crate::borrow::Owned;
+use crate::container::ByteStr;
+use crate::io::{self, BaseRead, BaseWrite, Read, Write};
+use crate::{Decoder, Encoder, Error};
+
+use alloc::vec::Vec;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+/// Trait used to express decoding relationships which share the input's memory.
+pub trait BytesDecoder<T>: Sized {
+    type Error;
+
+    /// Decodes from the input with the given parameters.
+    ///
+    /// The input is advanced past the decoded bytes.
+    fn decode_bytes(input: &mut Bytes, params: T) -> Result<Self, Self::Error>;
+}
+
+impl<D: Decoder<P>, P> BytesDecoder<Owned<P>> for D {
+    type Error = D::Error;
+
+    #[inline]
+    fn decode_bytes(input: &mut Bytes, params: Owned<P>) -> Result<Self, Self::Error> {
+        D::decode(Reader(input), params.0)
+    }
+}
+
+impl<L> BytesDecoder<ByteStr<L>> for Bytes
+where
+    usize: Decoder<L>,
+    <usize as Decoder<L>>::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn decode_bytes(input: &mut Bytes, params: ByteStr<L>) -> Result<Self, Error> {
+        let len = usize::decode(Reader(&mut *input), params.len).map_err(Into::into)?;
+        params.limits.check_len(len)?;
+
+        if input.len() < len {
+            return Err(Error::eof());
+        }
+
+        Ok(input.split_to(len))
+    }
+}
+
+impl<L> Encoder<ByteStr<L>> for Bytes
+where
+    usize: Encoder<L>,
+    <usize as Encoder<L>>::Error: Into<Error>,
+{
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: ByteStr<L>) -> Result<(), Error> {
+        self.as_ref().encode(writer, params)
+    }
+}
+
+impl<L> Decoder<ByteStr<L>> for Bytes
+where
+    usize: Decoder<L>,
+    <usize as Decoder<L>>::Error: Into<Error>,
+{
+    type Error = Error;
+
+    #[inline]
+    fn decode(reader: impl Read, params: ByteStr<L>) -> Result<Self, Error> {
+        Vec::decode(reader, params).map(Bytes::from)
+    }
+}
+
+impl<L> Encoder<ByteStr<L>> for BytesMut
+where
+    usize: Encoder<L>,
+    <usize as Encoder<L>>::Error: Into<Error>,
+{
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: ByteStr<L>) -> Result<(), Error> {
+        self.as_ref().encode(writer, params)
+    }
+}
+
+impl<L> Decoder<ByteStr<L>> for BytesMut
+where
+    usize: Decoder<L>,
+    <usize as Decoder<L>>::Error: Into<Error>,
+{
+    type Error = Error;
+
+    #[inline]
+    fn decode(reader: impl Read, params: ByteStr<L>) -> Result<Self, Error> {
+        // Converting a uniquely owned `Bytes` reuses its allocation.
+        Vec::decode(reader, params).map(|bytes| Bytes::from(bytes).into())
+    }
+}
+
+/// Reads from a `Buf`, advancing it past the bytes read.
+pub(crate) struct Reader<B>(pub B);
+
+impl<B: Buf> BaseRead for Reader<B> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = core::cmp::min(buf.len(), self.0.remaining());
+        self.0.copy_to_slice(&mut buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Writes to a `BufMut`, until it has no more space.
+pub(crate) struct Writer<B>(pub B);
+
+impl<B: BufMut> BaseWrite for Writer<B> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = core::cmp::min(buf.len(), self.0.remaining_mut());
+        self.0.put_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DecoderExt, EncoderExt, Uleb128};
+
+    #[test]
+    fn zero_copy() {
+        let input = Bytes::from_static(&[3, 1, 2, 3, 4]);
+        let mut rest = input.clone();
+
+        let bytes = Bytes::decode_bytes(&mut rest, ByteStr::new(Uleb128)).unwrap();
+        assert_eq!(&bytes[..], [1, 2, 3]);
+        assert_eq!(bytes.as_ptr(), input[1..].as_ptr());
+        assert_eq!(&rest[..], [4]);
+    }
+
+    #[test]
+    fn short_input() {
+        let mut input = Bytes::from_static(&[3, 1, 2]);
+        let error = Bytes::decode_bytes(&mut input, ByteStr::new(Uleb128)).unwrap_err();
+        assert!(error.is_eof());
+
+        let mut input = Bytes::from_static(&[0x80]);
+        let error = Bytes::decode_bytes(&mut input, ByteStr::new(Uleb128)).unwrap_err();
+        assert!(error.is_eof());
+
+        let mut input = Bytes::from_static(&[1, 2]);
+        let error = u32::decode_bytes(&mut input, Owned(crate::Le)).unwrap_err();
+        assert!(error.is_eof());
+
+        let error = BytesMut::decode_exact(&[2, 1], ByteStr::new(Uleb128)).unwrap_err();
+        assert!(error.is_eof());
+    }
+
+    #[test]
+    fn round_trip() {
+        let params = ByteStr::new(Uleb128);
+
+        let bytes = BytesMut::from(&b"abc"[..]);
+        let buf = bytes.encode_to_vec(params).unwrap();
+        assert_eq!(buf, [3, b'a', b'b', b'c']);
+        assert_eq!(BytesMut::decode_exact(&buf, params).unwrap(), bytes);
+        assert_eq!(Bytes::decode_exact(&buf, params).unwrap(), bytes.freeze());
+
+        let mut out = BytesMut::new();
+        0x1234u16.encode_to_buf(&mut out, crate::Be).unwrap();
+        assert_eq!(&out[..], [0x12, 0x34]);
+    }
+}
@@ -0,0 +1,906 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! The Concise Binary Object Representation (CBOR, RFC 8949).
+//!
+//! Under the `Cbor` parameters, integers, floating point numbers, `bool`,
+//! text strings, `Vec` (as arrays), `BTreeMap` (as maps) and `Option` (as
+//! `null` or the value) encode as the corresponding CBOR data items. Arrays,
+//! tuples and the fields of types using the derive macros without explicit
+//! parameters encode as definite-length arrays; derived enums encode their
+//! tag before the array of the variant's fields. Byte
+//! strings use the `ByteString` parameters instead, since `Vec<u8>` is an
+//! array of integers under `Cbor`. `Tagged` values carry a CBOR tag.
+//!
+//! Integers are always encoded in their shortest form. Decoding accepts any
+//! well-formed encoding, including indefinite-length strings, arrays and maps,
+//! unless deterministic mode is enabled. Each `Vec`, `BTreeMap`, `Tagged`
+//! and present `Option` value takes a level of the depth limit of the
+//! parameters' `Limits`, which bounds the recursion of recursive types.
+//!
+//! In deterministic mode, encoding follows the core deterministic encoding
+//! requirements of RFC 8949 section 4.2.1: floating point numbers use their
+//! shortest exact form and map keys are sorted by their encodings. Decoding
+//! rejects any input which is not encoded that way, as well as
+//! indefinite-length items.
+//!
+//! ```rust
+//! use codicon::*;
+//! use codicon::cbor::{ByteString, Tagged};
+//! use std::collections::BTreeMap;
+//!
+//! let cbor = Cbor::new();
+//! assert_eq!(500u16.encode_to_vec(cbor).unwrap(), [0x19, 0x01, 0xf4]);
+//! assert_eq!((-1i8).encode_to_vec(cbor).unwrap(), [0x20]);
+//! assert_eq!("IETF".encode_to_vec(cbor).unwrap(), [0x64, b'I', b'E', b'T', b'F']);
+//! assert_eq!([1u8, 2][..].encode_to_vec(ByteString(cbor)).unwrap(), [0x42, 1, 2]);
+//! assert_eq!(vec![Some(1u8), None].encode_to_vec(cbor).unwrap(), [0x82, 0x01, 0xf6]);
+//! assert_eq!((1u8, [2u8, 3]).encode_to_vec(cbor).unwrap(), [0x82, 0x01, 0x82, 0x02, 0x03]);
+//!
+//! let tagged = Tagged { tag: 1, value: 1363896240u32 };
+//! let buf = tagged.encode_to_vec(cbor).unwrap();
+//! assert_eq!(buf, [0xc1, 0x1a, 0x51, 0x4b, 0x67, 0xb0]);
+//! assert_eq!(Tagged::<u32>::decode_exact(&buf, cbor).unwrap(), tagged);
+//!
+//! // Indefinite-length arrays are only accepted in the default mode.
+//! let buf = [0x9f, 0x01, 0x02, 0xff];
+//! assert_eq!(Vec::<u8>::decode_exact(&buf, cbor).unwrap(), [1, 2]);
+//! assert!(Vec::<u8>::decode_exact(&buf, cbor.deterministic(true)).is_err());
+//!
+//! // Deterministic mode sorts map keys by their encodings...
+//! let cbor = cbor.deterministic(true);
+//! let map: BTreeMap<i8, bool> = vec![(-1, true), (10, false)].into_iter().collect();
+//! let buf = map.encode_to_vec(cbor).unwrap();
+//! assert_eq!(buf, [0xa2, 0x0a, 0xf4, 0x20, 0xf5]);
+//! assert_eq!(BTreeMap::decode_exact(&buf, cbor).unwrap(), map);
+//!
+//! // ... and uses the shortest exact floating point encoding.
+//! assert_eq!(1.5f64.encode_to_vec(cbor).unwrap(), [0xf9, 0x3e, 0x00]);
+//! assert!(f64::decode_exact(&[0xfb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0], cbor).is_err());
+//! ```
+
+use crate::container::Concat;
+use crate::io::{self, byte, Prepend, Read, Record, Write};
+use crate::limits::Limits;
+use crate::{Decoder, Encoder, Error};
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+const UNSIGNED: u8 = 0;
+const NEGATIVE: u8 = 1;
+const BYTES: u8 = 2;
+const TEXT: u8 = 3;
+const ARRAY: u8 = 4;
+const MAP: u8 = 5;
+const TAG: u8 = 6;
+
+const FALSE: u8 = 0xf4;
+const TRUE: u8 = 0xf5;
+const NULL: u8 = 0xf6;
+const F16: u8 = 0xf9;
+const F32: u8 = 0xfa;
+const F64: u8 = 0xfb;
+const BREAK: u8 = 0xff;
+
+const INDEFINITE: u8 = 31;
+const NAN: u16 = 0x7e00;
+
+/// CBOR encoding parameters.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Cbor {
+    deterministic: bool,
+    limits: Limits,
+}
+
+impl Cbor {
+    /// Creates parameters for the default, non-deterministic mode.
+    pub const fn new() -> Self {
+        Self {
+            deterministic: false,
+            limits: Limits::DEFAULT,
+        }
+    }
+
+    /// Enables or disables deterministic mode.
+    pub const fn deterministic(self, deterministic: bool) -> Self {
+        Self {
+            deterministic,
+            ..self
+        }
+    }
+
+    /// Returns whether deterministic mode is enabled.
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    /// Sets the limits checked when decoding.
+    pub fn with_limits(self, limits: Limits) -> Self {
+        Self { limits, ..self }
+    }
+
+    /// Returns the limits checked when decoding.
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+}
+
+impl<T: ?Sized> Concat<T> for Cbor {
+    fn encode_fields(&self, writer: impl Write, fields: &[&'static str]) -> Result<(), Error> {
+        head(writer, ARRAY, fields.len() as u64)
+    }
+
+    fn decode_fields(&self, reader: impl Read, fields: &[&'static str]) -> Result<usize, Error> {
+        array(reader, fields.len(), *self)?;
+        Ok(fields.len())
+    }
+
+    fn encode_items(&self, writer: impl Write, items: usize) -> Result<(), Error> {
+        head(writer, ARRAY, items as u64)
+    }
+
+    fn decode_items(&self, reader: impl Read, items: usize) -> Result<(), Error> {
+        array(reader, items, *self)
+    }
+}
+
+/// Parameters for CBOR byte strings.
+///
+/// `[u8]` and `Vec<u8>` encode as a single CBOR byte string under these
+/// parameters, rather than as an array of integers.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ByteString(pub Cbor);
+
+/// A value with a CBOR tag.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Tagged<T> {
+    /// The tag number.
+    pub tag: u64,
+
+    /// The tagged value.
+    pub value: T,
+}
+
+fn head(mut writer: impl Write, major: u8, arg: u64) -> Result<(), Error> {
+    let major = major << 5;
+
+    match arg {
+        0..=23 => writer.write_all(&[major | arg as u8])?,
+        24..=0xff => writer.write_all(&[major | 24, arg as u8])?,
+        0x100..=0xffff => {
+            writer.write_all(&[major | 25])?;
+            writer.write_all(&(arg as u16).to_be_bytes())?;
+        }
+        0x1_0000..=0xffff_ffff => {
+            writer.write_all(&[major | 26])?;
+            writer.write_all(&(arg as u32).to_be_bytes())?;
+        }
+        _ => {
+            writer.write_all(&[major | 27])?;
+            writer.write_all(&arg.to_be_bytes())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the argument following an initial byte with definite length.
+fn arg(mut reader: impl Read, initial: u8, cbor: Cbor) -> Result<u64, Error> {
+    let (arg, min) = match initial & 0x1f {
+        info @ 0..=23 => return Ok(info.into()),
+        24 => (byte(&mut reader)?.into(), 24),
+        25 => {
+            let mut buf = [0u8; 2];
+            reader.read_exact(&mut buf)?;
+            (u16::from_be_bytes(buf).into(), 0x100)
+        }
+        26 => {
+            let mut buf = [0u8; 4];
+            reader.read_exact(&mut buf)?;
+            (u32::from_be_bytes(buf).into(), 0x1_0000)
+        }
+        27 => {
+            let mut buf = [0u8; 8];
+            reader.read_exact(&mut buf)?;
+            (u64::from_be_bytes(buf), 0x1_0000_0000)
+        }
+        INDEFINITE => return Err(Error::invalid("unexpected indefinite length")),
+        _ => return Err(Error::invalid("reserved additional information")),
+    };
+
+    match cbor.deterministic && arg < min {
+        true => Err(Error::invalid("non-minimal argument")),
+        false => Ok(arg),
+    }
+}
+
+/// Reads the length of a string, array or map, or `None` if indefinite.
+fn len(reader: impl Read, initial: u8, major: u8, cbor: Cbor) -> Result<Option<usize>, Error> {
+    if initial >> 5 != major {
+        return Err(Error::invalid("unexpected major type"));
+    }
+
+    if initial & 0x1f == INDEFINITE {
+        return match cbor.deterministic {
+            true => Err(Error::invalid("indefinite length")),
+            false => Ok(None),
+        };
+    }
+
+    let len = arg(reader, initial, cbor)?;
+    let len = usize::try_from(len).map_err(|_| Error::limit("length exceeds usize"))?;
+    cbor.limits.check_len(len)?;
+    Ok(Some(len))
+}
+
+/// Reads the head of a definite-length array of `items` items.
+fn array(mut reader: impl Read, items: usize, cbor: Cbor) -> Result<(), Error> {
+    let initial = byte(&mut reader)?;
+    match len(reader, initial, ARRAY, cbor)? {
+        Some(len) if len == items => Ok(()),
+        Some(_) => Err(Error::invalid("wrong number of items")),
+        None => Err(Error::invalid("indefinite length")),
+    }
+}
+
+/// Reads a byte or text string, appending its contents to `buf`.
+fn string(mut reader: impl Read, major: u8, cbor: Cbor, buf: &mut Vec<u8>) -> Result<(), Error> {
+    let initial = byte(&mut reader)?;
+    if initial & 0x1f != INDEFINITE || initial >> 5 != major {
+        return chunk(reader, initial, major, cbor, buf);
+    }
+
+    if cbor.deterministic {
+        return Err(Error::invalid("indefinite length"));
+    }
+
+    loop {
+        match byte(&mut reader)? {
+            BREAK => return Ok(()),
+            initial => chunk(&mut reader, initial, major, cbor, buf)?,
+        }
+    }
+}
+
+/// Reads a definite-length string, appending its contents to `buf`.
+fn chunk(
+    mut reader: impl Read,
+    initial: u8,
+    major: u8,
+    cbor: Cbor,
+    buf: &mut Vec<u8>,
+) -> Result<(), Error> {
+    let len = match len(&mut reader, initial, major, cbor)? {
+        Some(len) => len,
+        None => return Err(Error::invalid("nested indefinite length")),
+    };
+
+    let end = buf
+        .len()
+        .checked_add(len)
+        .ok_or(Error::limit("length exceeds usize"))?;
+    cbor.limits.check_len(end)?;
+    cbor.limits.check_alloc::<u8>(end)?;
+
+    Ok(io::read_onto(reader, buf, len)?)
+}
+
+/// Returns the bits of a half-precision float equal to `value`, if any.
+fn to_f16(value: f64) -> Option<u16> {
+    let bits = value.to_bits();
+    let sign = ((bits >> 48) & 0x8000) as u16;
+    let exp = ((bits >> 52) & 0x7ff) as i32;
+    let mant = bits & ((1 << 52) - 1);
+
+    match exp {
+        0 if mant == 0 => Some(sign),
+        0x7ff if mant == 0 => Some(sign | 0x7c00),
+        0x7ff => Some(NAN),
+
+        // Normal half-precision numbers.
+        1009..=1038 if mant.trailing_zeros() >= 42 => {
+            Some(sign | ((exp - 1008) as u16) << 10 | (mant >> 42) as u16)
+        }
+
+        // Subnormal half-precision numbers.
+        999..=1008 => {
+            let full = mant | 1 << 52;
+            let shift = 1051 - exp;
+            match full.trailing_zeros() >= shift as u32 {
+                true => Some(sign | (full >> shift) as u16),
+                false => None,
+            }
+        }
+
+        _ => None,
+    }
+}
+
+/// Returns the value of a half-precision float.
+fn from_f16(bits: u16) -> f64 {
+    let sign = u64::from(bits & 0x8000) << 48;
+    let exp = u64::from((bits >> 10) & 0x1f);
+    let mant = u64::from(bits & 0x3ff);
+
+    let magnitude = match exp {
+        0 => mant as f64 / (1u64 << 24) as f64,
+        0x1f if mant == 0 => f64::INFINITY,
+        0x1f => f64::NAN,
+        _ => f64::from_bits((exp + 1008) << 52 | mant << 42),
+    };
+
+    f64::from_bits(magnitude.to_bits() | sign)
+}
+
+/// Returns whether `value` is exactly representable as an `f32`.
+fn is_f32(value: f64) -> bool {
+    value.is_nan() || f64::from(value as f32) == value
+}
+
+fn float(mut writer: impl Write, value: f64, single: bool, cbor: Cbor) -> Result<(), Error> {
+    if cbor.deterministic {
+        if let Some(bits) = to_f16(value) {
+            writer.write_all(&[F16])?;
+            return Ok(writer.write_all(&bits.to_be_bytes())?);
+        }
+    }
+
+    if single || (cbor.deterministic && is_f32(value)) {
+        writer.write_all(&[F32])?;
+        return Ok(writer.write_all(&(value as f32).to_be_bytes())?);
+    }
+
+    writer.write_all(&[F64])?;
+    Ok(writer.write_all(&value.to_be_bytes())?)
+}
+
+/// Reads a float, returning its value and whether it was encoded in
+/// double precision.
+fn read_float(mut reader: impl Read, cbor: Cbor) -> Result<(f64, bool), Error> {
+    let initial = byte(&mut reader)?;
+    let (value, preferred) = match initial {
+        F16 => {
+            let mut buf = [0u8; 2];
+            reader.read_exact(&mut buf)?;
+            let bits = u16::from_be_bytes(buf);
+            (from_f16(bits), (bits & 0x7fff) <= 0x7c00 || bits == NAN)
+        }
+
+        F32 => {
+            let mut buf = [0u8; 4];
+            reader.read_exact(&mut buf)?;
+            let value = f32::from_be_bytes(buf).into();
+            (value, to_f16(value).is_none())
+        }
+
+        F64 => {
+            let mut buf = [0u8; 8];
+            reader.read_exact(&mut buf)?;
+            let value = f64::from_be_bytes(buf);
+            (value, to_f16(value).is_none() && !is_f32(value))
+        }
+
+        _ => return Err(Error::invalid("expected float")),
+    };
+
+    match cbor.deterministic && !preferred {
+        true => Err(Error::invalid("non-preferred float")),
+        false => Ok((value, initial == F64)),
+    }
+}
+
+macro_rules! int {
+    ($($t:ident)+) => {
+        $(
+            impl Encoder<Cbor> for $t {
+                type Error = Error;
+
+                fn encode(&self, writer: impl Write, _: Cbor) -> Result<(), Error> {
+                    let value = i128::try_from(*self)
+                        .map_err(|_| Error::invalid("integer out of range"))?;
+
+                    let (major, arg) = match value < 0 {
+                        true => (NEGATIVE, -1 - value),
+                        false => (UNSIGNED, value),
+                    };
+
+                    let arg = u64::try_from(arg).map_err(|_| Error::invalid("integer out of range"))?;
+                    head(writer, major, arg)
+                }
+            }
+
+            impl Decoder<Cbor> for $t {
+                type Error = Error;
+
+                fn decode(mut reader: impl Read, params: Cbor) -> Result<Self, Error> {
+                    let initial = byte(&mut reader)?;
+                    let value = match initial >> 5 {
+                        UNSIGNED => i128::from(arg(reader, initial, params)?),
+                        NEGATIVE => -1 - i128::from(arg(reader, initial, params)?),
+                        _ => return Err(Error::invalid("expected integer")),
+                    };
+
+                    $t::try_from(value).map_err(|_| Error::invalid("integer out of range"))
+                }
+            }
+        )+
+    };
+}
+
+int!(u8 u16 u32 u64 u128 usize i8 i16 i32 i64 i128 isize);
+
+impl Encoder<Cbor> for f32 {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: Cbor) -> Result<(), Error> {
+        float(writer, (*self).into(), true, params)
+    }
+}
+
+impl Decoder<Cbor> for f32 {
+    type Error = Error;
+
+    fn decode(reader: impl Read, params: Cbor) -> Result<Self, Error> {
+        match read_float(reader, params)? {
+            (value, true) if !is_f32(value) => Err(Error::invalid("inexact float")),
+            (value, _) => Ok(value as f32),
+        }
+    }
+}
+
+impl Encoder<Cbor> for f64 {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: Cbor) -> Result<(), Error> {
+        float(writer, *self, false, params)
+    }
+}
+
+impl Decoder<Cbor> for f64 {
+    type Error = Error;
+
+    #[inline]
+    fn decode(reader: impl Read, params: Cbor) -> Result<Self, Error> {
+        read_float(reader, params).map(|(value, _)| value)
+    }
+}
+
+impl Encoder<Cbor> for bool {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, mut writer: impl Write, _: Cbor) -> Result<(), Error> {
+        Ok(writer.write_all(&[if *self { TRUE } else { FALSE }])?)
+    }
+}
+
+impl Decoder<Cbor> for bool {
+    type Error = Error;
+
+    fn decode(reader: impl Read, _: Cbor) -> Result<Self, Error> {
+        match byte(reader)? {
+            FALSE => Ok(false),
+            TRUE => Ok(true),
+            _ => Err(Error::invalid("expected bool")),
+        }
+    }
+}
+
+impl Encoder<ByteString> for [u8] {
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, _: ByteString) -> Result<(), Error> {
+        head(&mut writer, BYTES, self.len() as u64)?;
+        Ok(writer.write_all(self)?)
+    }
+}
+
+impl Encoder<ByteString> for Vec<u8> {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: ByteString) -> Result<(), Error> {
+        self.as_slice().encode(writer, params)
+    }
+}
+
+impl Decoder<ByteString> for Vec<u8> {
+    type Error = Error;
+
+    fn decode(reader: impl Read, params: ByteString) -> Result<Self, Error> {
+        let mut bytes = Vec::new();
+        string(reader, BYTES, params.0, &mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+impl Encoder<Cbor> for str {
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, _: Cbor) -> Result<(), Error> {
+        head(&mut writer, TEXT, self.len() as u64)?;
+        Ok(writer.write_all(self.as_bytes())?)
+    }
+}
+
+impl Encoder<Cbor> for String {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: Cbor) -> Result<(), Error> {
+        self.as_str().encode(writer, params)
+    }
+}
+
+impl Decoder<Cbor> for String {
+    type Error = Error;
+
+    fn decode(reader: impl Read, params: Cbor) -> Result<Self, Error> {
+        let mut bytes = Vec::new();
+        string(reader, TEXT, params, &mut bytes)?;
+        String::from_utf8(bytes).map_err(|_| Error::invalid("invalid UTF-8"))
+    }
+}
+
+impl<T: Encoder<Cbor>> Encoder<Cbor> for [T]
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, params: Cbor) -> Result<(), Error> {
+        head(&mut writer, ARRAY, self.len() as u64)?;
+
+        for item in self {
+            item.encode(&mut writer, params).map_err(Into::into)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: Encoder<Cbor>> Encoder<Cbor> for Vec<T>
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: Cbor) -> Result<(), Error> {
+        self.as_slice().encode(writer, params)
+    }
+}
+
+impl<T: Decoder<Cbor>> Decoder<Cbor> for Vec<T>
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: Cbor) -> Result<Self, Error> {
+        let params = params.with_limits(params.limits.nested()?);
+        let initial = byte(&mut reader)?;
+
+        let mut items = Vec::new();
+        match len(&mut reader, initial, ARRAY, params)? {
+            Some(len) => {
+                params.limits.check_alloc::<T>(len)?;
+                items = io::with_capacity(len);
+
+                for _ in 0..len {
+                    items.push(T::decode(&mut reader, params).map_err(Into::into)?);
+                }
+            }
+
+            None => loop {
+                let initial = byte(&mut reader)?;
+                if initial == BREAK {
+                    break;
+                }
+
+                params.limits.check_len(items.len() + 1)?;
+                params.limits.check_alloc::<T>(items.len() + 1)?;
+                let reader = Prepend::new(initial, &mut reader);
+                items.push(T::decode(reader, params).map_err(Into::into)?);
+            },
+        }
+
+        Ok(items)
+    }
+}
+
+impl<K: Encoder<Cbor>, V: Encoder<Cbor>> Encoder<Cbor> for BTreeMap<K, V>
+where
+    K::Error: Into<Error>,
+    V::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, params: Cbor) -> Result<(), Error> {
+        head(&mut writer, MAP, self.len() as u64)?;
+
+        if !params.deterministic {
+            for (key, value) in self {
+                key.encode(&mut writer, params).map_err(Into::into)?;
+                value.encode(&mut writer, params).map_err(Into::into)?;
+            }
+
+            return Ok(());
+        }
+
+        // The order of the keys may differ from the order of their encodings.
+        let mut entries = Vec::with_capacity(self.len());
+        for (key, value) in self {
+            let mut bytes = Vec::new();
+            key.encode(&mut bytes, params).map_err(Into::into)?;
+            entries.push((bytes, value));
+        }
+
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        for (key, value) in entries {
+            writer.write_all(&key)?;
+            value.encode(&mut writer, params).map_err(Into::into)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<K: Decoder<Cbor> + Ord, V: Decoder<Cbor>> Decoder<Cbor> for BTreeMap<K, V>
+where
+    K::Error: Into<Error>,
+    V::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: Cbor) -> Result<Self, Error> {
+        let params = params.with_limits(params.limits.nested()?);
+        let initial = byte(&mut reader)?;
+        let len = len(&mut reader, initial, MAP, params)?;
+
+        let mut map = BTreeMap::new();
+        let mut last = Vec::new();
+
+        for i in 0.. {
+            if len == Some(i) {
+                break;
+            }
+
+            let initial = byte(&mut reader)?;
+            if len.is_none() {
+                if initial == BREAK {
+                    break;
+                }
+
+                params.limits.check_len(i + 1)?;
+            }
+
+            let mut record = Record {
+                reader: Prepend::new(initial, &mut reader),
+                bytes: Vec::new(),
+            };
+
+            let key = K::decode(&mut record, params).map_err(Into::into)?;
+            if params.deterministic {
+                if i > 0 && record.bytes <= last {
+                    return Err(Error::invalid("unsorted map keys"));
+                }
+
+                last = record.bytes;
+            }
+
+            let value = V::decode(&mut reader, params).map_err(Into::into)?;
+            if map.insert(key, value).is_some() {
+                return Err(Error::invalid("duplicate map key"));
+            }
+        }
+
+        Ok(map)
+    }
+}
+
+impl<T: Encoder<Cbor>> Encoder<Cbor> for Option<T>
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, params: Cbor) -> Result<(), Error> {
+        match self {
+            Some(value) => value.encode(writer, params).map_err(Into::into),
+            None => Ok(writer.write_all(&[NULL])?),
+        }
+    }
+}
+
+impl<T: Decoder<Cbor>> Decoder<Cbor> for Option<T>
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: Cbor) -> Result<Self, Error> {
+        match byte(&mut reader)? {
+            NULL => Ok(None),
+            initial => {
+                let params = params.with_limits(params.limits.nested()?);
+                T::decode(Prepend::new(initial, reader), params)
+                    .map(Some)
+                    .map_err(Into::into)
+            }
+        }
+    }
+}
+
+impl<T: Encoder<Cbor>> Encoder<Cbor> for Tagged<T>
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, params: Cbor) -> Result<(), Error> {
+        head(&mut writer, TAG, self.tag)?;
+        self.value.encode(writer, params).map_err(Into::into)
+    }
+}
+
+impl<T: Decoder<Cbor>> Decoder<Cbor> for Tagged<T>
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: Cbor) -> Result<Self, Error> {
+        let initial = byte(&mut reader)?;
+        if initial >> 5 != TAG {
+            return Err(Error::invalid("expected tag"));
+        }
+
+        let tag = arg(&mut reader, initial, params)?;
+        let params = params.with_limits(params.limits.nested()?);
+        let value = T::decode(reader, params).map_err(Into::into)?;
+        Ok(Self { tag, value })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DecoderExt, EncoderExt};
+    use alloc::boxed::Box;
+    use alloc::vec;
+
+    fn invalid<T: Decoder<Cbor, Error = Error> + core::fmt::Debug>(
+        buf: &[u8],
+        cbor: Cbor,
+        reason: &str,
+    ) {
+        match T::decode_exact(buf, cbor) {
+            Err(Error::InvalidValue { reason: actual, .. }) => assert_eq!(actual, reason),
+            result => panic!("{:02x?} decoded as {:?}", buf, result),
+        }
+    }
+
+    #[test]
+    fn nested_containers() {
+        let cbor = Cbor::new();
+
+        let value = vec![[1u8, 2], [3, 4]];
+        let buf = value.encode_to_vec(cbor).unwrap();
+        assert_eq!(buf, [0x82, 0x82, 0x01, 0x02, 0x82, 0x03, 0x04]);
+        assert_eq!(Vec::<[u8; 2]>::decode_exact(&buf, cbor).unwrap(), value);
+
+        let value = (1u8, vec![(2u8, String::from("a"))], [Some(true), None]);
+        let buf = value.encode_to_vec(cbor).unwrap();
+        assert_eq!(
+            buf,
+            [0x83, 0x01, 0x81, 0x82, 0x02, 0x61, b'a', 0x82, TRUE, NULL]
+        );
+
+        let decoded: (u8, Vec<(u8, String)>, [Option<bool>; 2]) =
+            DecoderExt::decode_exact(&buf, cbor).unwrap();
+        assert_eq!(decoded, value);
+
+        invalid::<[u8; 2]>(&[0x83, 0x01, 0x02, 0x03], cbor, "wrong number of items");
+        invalid::<(u8, u8)>(&[0x81, 0x01], cbor, "wrong number of items");
+        invalid::<(u8, u8)>(&[0x9f, 0x01, 0x02, BREAK], cbor, "indefinite length");
+        invalid::<[u8; 2]>(&[0x01, 0x02], cbor, "unexpected major type");
+    }
+
+    #[derive(Debug)]
+    struct Node(Vec<Node>);
+
+    impl Decoder<Cbor> for Node {
+        type Error = Error;
+
+        fn decode(mut reader: impl Read, params: Cbor) -> Result<Self, Error> {
+            Vec::decode(&mut reader as &mut dyn Read, params).map(Self)
+        }
+    }
+
+    #[derive(Debug)]
+    struct List(Option<Box<List>>);
+
+    impl Decoder<Cbor> for List {
+        type Error = Error;
+
+        fn decode(mut reader: impl Read, params: Cbor) -> Result<Self, Error> {
+            Option::decode(&mut reader as &mut dyn Read, params).map(Self)
+        }
+    }
+
+    fn too_deep<T: Decoder<Cbor, Error = Error> + core::fmt::Debug>(buf: &[u8], cbor: Cbor) {
+        match T::decode_exact(buf, cbor) {
+            Err(Error::LimitExceeded { limit, .. }) => assert_eq!(limit, "nesting depth"),
+            result => panic!(
+                "{:02x?} decoded as {:?}",
+                &buf[..core::cmp::min(buf.len(), 4)],
+                result
+            ),
+        }
+    }
+
+    #[test]
+    fn nesting_depth() {
+        let cbor = Cbor::new();
+
+        too_deep::<Node>(&vec![0x81; 1_000_000], cbor);
+        too_deep::<Node>(&vec![0x9f; 1_000_000], cbor);
+
+        let mut buf = vec![0x81; 127];
+        buf.push(0x80);
+        assert_eq!(Node::decode_exact(&buf, cbor).unwrap().0.len(), 1);
+        too_deep::<Node>(&buf, cbor.with_limits(Limits::default().depth(127)));
+
+        // A present value decodes the same byte again, consuming no input.
+        too_deep::<List>(&[0x01, 0, 0, 0], cbor);
+        assert!(List::decode_exact(&[NULL], cbor).unwrap().0.is_none());
+
+        let shallow = cbor.with_limits(Limits::default().depth(1));
+        too_deep::<BTreeMap<u8, Vec<u8>>>(&[0xa1, 0x01, 0x80], shallow);
+        too_deep::<Tagged<Vec<u8>>>(&[0xc1, 0x80], shallow);
+        assert!(Tagged::<u8>::decode_exact(&[0xc1, 0x01], shallow).is_ok());
+    }
+
+    #[test]
+    fn deterministic() {
+        let cbor = Cbor::new();
+        let strict = cbor.deterministic(true);
+
+        // A non-minimal argument.
+        assert_eq!(u8::decode_exact(&[0x18, 0x05], cbor).unwrap(), 5);
+        invalid::<u8>(&[0x18, 0x05], strict, "non-minimal argument");
+        invalid::<Vec<u8>>(&[0x98, 0x01, 0x00], strict, "non-minimal argument");
+
+        // A float with a shorter exact encoding.
+        assert_eq!(
+            f64::decode_exact(&[F32, 0x3f, 0xc0, 0, 0], cbor).unwrap(),
+            1.5
+        );
+        invalid::<f64>(&[F32, 0x3f, 0xc0, 0, 0], strict, "non-preferred float");
+
+        // Map keys out of the order of their encodings.
+        let buf = [0xa2, 0x20, TRUE, 0x0a, FALSE];
+        assert_eq!(
+            BTreeMap::<i8, bool>::decode_exact(&buf, cbor)
+                .unwrap()
+                .len(),
+            2
+        );
+        invalid::<BTreeMap<i8, bool>>(&buf, strict, "unsorted map keys");
+        invalid::<BTreeMap<i8, bool>>(&[0xa2, 0x01, TRUE, 0x01, FALSE], cbor, "duplicate map key");
+
+        // Indefinite-length items.
+        assert_eq!(
+            String::decode_exact(&[0x7f, 0x61, b'a', BREAK], cbor).unwrap(),
+            "a"
+        );
+        invalid::<String>(&[0x7f, 0x61, b'a', BREAK], strict, "indefinite length");
+        invalid::<Vec<u8>>(&[0x9f, BREAK], strict, "indefinite length");
+    }
+}
@@ -0,0 +1,236 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! A `tokio_util::codec` bridge.
+//!
+//! `CodiconCodec` implements `tokio_util::codec::Encoder` and
+//! `tokio_util::codec::Decoder` for any type implementing `Encoder` and
+//! `Decoder`, so that it can be used with `tokio_util::codec::Framed` and
+//! friends. As with `Incremental`, a value split across several reads is
+//! left in the buffer until it can be decoded completely, and no attempt is
+//! made to decode it again until as many bytes have arrived as the previous
+//! attempt asked for. Those bytes are reserved in the buffer up front.
+//!
+//! The buffer holds at most `CodiconCodec::MAX_BUFFERED` bytes by default;
+//! a value which needs more fails with `Error::LimitExceeded`. Use
+//! `CodiconCodec::with_limits()` to change the bound.
+//!
+//! ```rust
+//! use codicon::codec::CodiconCodec;
+//! use codicon::Be;
+//! use bytes::BytesMut;
+//! use tokio_util::codec::{Decoder as _, Encoder as _};
+//!
+//! let mut codec = CodiconCodec::<u32, _>::new(Be);
+//!
+//! let mut buf = BytesMut::new();
+//! codec.encode(0x12345678, &mut buf).unwrap();
+//! assert_eq!(&buf[..], [0x12, 0x34, 0x56, 0x78]);
+//!
+//! let mut partial = buf.split_to(3);
+//! assert!(codec.decode(&mut partial).unwrap().is_none());
+//! assert_eq!(partial.len(), 3);
+//!
+//! partial.unsplit(buf);
+//! assert_eq!(codec.decode(&mut partial).unwrap(), Some(0x12345678));
+//! assert!(partial.is_empty());
+//! ```
+
+use crate::limits::Limits;
+use crate::stream::Partial;
+use crate::{Decoder, Encoder, EncoderExt, Error};
+
+use bytes::{Buf, BytesMut};
+use core::marker::PhantomData;
+use tokio_util::codec;
+
+/// A `tokio_util` codec for values of type `T` with parameters `P`.
+#[derive(Debug)]
+pub struct CodiconCodec<T, P> {
+    params: P,
+    needed: usize,
+    limits: Limits,
+    value: PhantomData<fn() -> T>,
+}
+
+impl<T, P: Clone> Clone for CodiconCodec<T, P> {
+    fn clone(&self) -> Self {
+        Self {
+            params: self.params.clone(),
+            needed: self.needed,
+            limits: self.limits,
+            value: PhantomData,
+        }
+    }
+}
+
+impl<T, P: Default> Default for CodiconCodec<T, P> {
+    fn default() -> Self {
+        Self::new(P::default())
+    }
+}
+
+impl<T, P> CodiconCodec<T, P> {
+    /// The default bound on the bytes buffered for a single value.
+    pub const MAX_BUFFERED: usize = 8 * 1024 * 1024;
+
+    /// Creates a codec which encodes and decodes with the given parameters.
+    pub const fn new(params: P) -> Self {
+        Self {
+            params,
+            needed: 0,
+            limits: Limits::DEFAULT.bytes(Self::MAX_BUFFERED),
+            value: PhantomData,
+        }
+    }
+
+    /// Sets the limits on buffered input.
+    ///
+    /// A value needing more bytes than the byte limit fails with
+    /// `Error::LimitExceeded`. The parameters check their own limits.
+    pub fn with_limits(self, limits: Limits) -> Self {
+        Self { limits, ..self }
+    }
+
+    /// Returns the limits on buffered input.
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    /// Returns the parameters used for encoding and decoding.
+    pub fn params(&self) -> &P {
+        &self.params
+    }
+}
+
+impl<T: Decoder<P>, P: Clone> codec::Decoder for CodiconCodec<T, P>
+where
+    T::Error: Into<Error>,
+{
+    type Item = T;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<T>, Error> {
+        if src.len() < self.needed {
+            src.reserve(self.needed - src.len());
+            return Ok(None);
+        }
+
+        let mut reader = Partial {
+            buf: src,
+            wanted: 0,
+        };
+
+        let result = T::decode(&mut reader, self.params.clone()).map_err(Into::into);
+        let consumed = src.len() - reader.buf.len();
+        let wanted = reader.wanted;
+
+        match result {
+            Ok(value) => {
+                src.advance(consumed);
+                self.needed = 0;
+                Ok(Some(value))
+            }
+
+            Err(e) if e.is_eof() => {
+                let needed = src.len().saturating_add(core::cmp::max(wanted, 1));
+                self.limits
+                    .check_bytes(needed)
+                    .map_err(|e| e.at(src.len()))?;
+                src.reserve(needed - src.len());
+                self.needed = needed;
+                Ok(None)
+            }
+
+            Err(e) => Err(e.at(consumed)),
+        }
+    }
+}
+
+impl<T: Encoder<P>, P: Clone> codec::Encoder<T> for CodiconCodec<T, P>
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn encode(&mut self, item: T, dst: &mut BytesMut) -> Result<(), Error> {
+        let len = dst.len();
+        let result = item.encode_to_buf(&mut *dst, self.params.clone());
+
+        if result.is_err() {
+            dst.truncate(len);
+        }
+
+        result.map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Be, Prefixed, Uleb128};
+    use tokio_util::codec::Decoder as _;
+
+    #[test]
+    fn split_frame() {
+        let mut codec = CodiconCodec::<Vec<u8>, _>::new(Prefixed::new(Uleb128, Be));
+        let mut src = BytesMut::from(&[4, 1, 2][..]);
+
+        assert!(codec.decode(&mut src).unwrap().is_none());
+        assert!(src.capacity() >= 5);
+
+        src.extend_from_slice(&[3]);
+        assert!(codec.decode(&mut src).unwrap().is_none());
+
+        src.extend_from_slice(&[4, 5]);
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(vec![1, 2, 3, 4]));
+        assert_eq!(&src[..], [5]);
+    }
+
+    #[test]
+    fn several_frames() {
+        let mut codec = CodiconCodec::<u16, _>::new(Be);
+        let mut src = BytesMut::from(&[0x12, 0x34, 0x56, 0x78, 0x9a][..]);
+
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(0x1234));
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(0x5678));
+        assert!(codec.decode(&mut src).unwrap().is_none());
+        assert_eq!(&src[..], [0x9a]);
+    }
+
+    #[test]
+    fn oversize() {
+        let params = Prefixed::new(Uleb128, Be);
+        let limits = Limits::DEFAULT.bytes(4);
+        let mut codec = CodiconCodec::<Vec<u8>, _>::new(params).with_limits(limits);
+
+        let mut src = BytesMut::from(&[3, 1, 2, 3][..]);
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(vec![1, 2, 3]));
+
+        let mut src = BytesMut::from(&[9, 1, 2][..]);
+        assert!(codec.decode(&mut src).unwrap().is_none());
+
+        src.extend_from_slice(&[3, 4]);
+        match codec.decode(&mut src) {
+            Err(Error::LimitExceeded { limit, offset }) => {
+                assert_eq!(limit, "total bytes");
+                assert_eq!(offset, Some(5));
+            }
+            _ => panic!("limit not enforced"),
+        }
+        assert_eq!(&src[..], [9, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn invalid() {
+        let mut codec = CodiconCodec::<u8, _>::new(Uleb128);
+        let mut src = BytesMut::from(&[0x80, 0x02][..]);
+
+        assert!(matches!(
+            codec.decode(&mut src),
+            Err(Error::InvalidValue {
+                offset: Some(2),
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,695 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Encodings for containers of encodable types.
+//!
+//! Arrays, tuples and `Box` have no framing of their own: under parameters
+//! implementing `Concat`, they encode as the concatenation of their elements,
+//! each using the same parameters, after any header the parameters write for
+//! arrays and tuples.
+//!
+//! Variable-sized collections are encoded with `Prefixed` parameters, which
+//! write the number of elements before the elements themselves. Byte and text
+//! strings are encoded with `ByteStr` parameters, which write the number of
+//! bytes before the raw bytes. Similarly,
+//! `Option` is encoded with `Optional` parameters, which write a `bool`
+//! presence tag before the value (if any). Both take the parameters of the
+//! prefix or tag, followed by the parameters of the elements.
+//!
+//! ```rust
+//! use codicon::*;
+//!
+//! let prefixed = Prefixed::new(Len::<u16, _>::new(Be), Le);
+//! let buf = vec![1u32, 2].encode_to_vec(prefixed).unwrap();
+//! assert_eq!(buf, [0, 2, 1, 0, 0, 0, 2, 0, 0, 0]);
+//! assert_eq!(Vec::<u32>::decode_exact(&buf, prefixed).unwrap(), [1, 2]);
+//!
+//! let optional = Optional::new(Le, Uleb128);
+//! assert_eq!(Some(300u16).encode_to_vec(optional).unwrap(), [1, 0xac, 0x02]);
+//! assert_eq!(None::<u16>.encode_to_vec(optional).unwrap(), [0]);
+//!
+//! let bytestr = ByteStr::new(Uleb128);
+//! assert_eq!("hi".encode_to_vec(bytestr).unwrap(), [2, b'h', b'i']);
+//! assert_eq!(String::decode_exact(&[2, b'h', b'i'], bytestr).unwrap(), "hi");
+//!
+//! let buf = ([1u8, 2], (3u16, true)).encode_to_vec(Be).unwrap();
+//! assert_eq!(buf, [1, 2, 0, 3, 1]);
+//! ```
+
+use crate::io::{Counter, Read, Write};
+use crate::limits::Limits;
+use crate::{Be, Le, Ne, Sleb128, Uleb128, ZigZag};
+use crate::{ConstEncodedLen, Decoder, Encoder, Error};
+
+use core::convert::TryFrom;
+use core::marker::PhantomData;
+
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, string::String, vec::Vec};
+
+/// Marks parameters under which aggregates encode as their elements in order.
+///
+/// Arrays, tuples and `Box` implement `Encoder<P>` and `Decoder<P>` for
+/// parameters `P` implementing this trait, by encoding each of their
+/// elements with a clone of `P` and no additional framing.
+///
+/// Types using the derive macros without explicit parameters do likewise for
+/// parameters implementing `Concat<Self>`. Naming the type lets its crate
+/// rely on parameters it does not own, such as `Framed`, not being `Concat`
+/// for it. Parameters should implement this trait for every `T`.
+///
+/// Derived structs, and the fields of derived enum variants, additionally
+/// call the provided methods of this trait before their fields, as do arrays
+/// and tuples before their elements. By default they do nothing;
+/// self-describing formats override them to frame the fields, for example
+/// with a field count or the name of each field. Decoding may then find the
+/// fields in any order, or skip fields unknown to the type; a missing or
+/// repeated field fails with `Error::InvalidValue`. The `ConstEncodedLen` of
+/// arrays and tuples assumes that arrays and tuples are not framed.
+pub trait Concat<T: ?Sized = ()>: Clone {
+    /// Encodes whatever precedes the named fields of a derived type.
+    #[inline]
+    fn encode_fields(&self, writer: impl Write, fields: &[&'static str]) -> Result<(), Error> {
+        let _ = (writer, fields);
+        Ok(())
+    }
+
+    /// Encodes whatever precedes the named field of a derived type.
+    #[inline]
+    fn encode_field(&self, writer: impl Write, field: &'static str) -> Result<(), Error> {
+        let _ = (writer, field);
+        Ok(())
+    }
+
+    /// Decodes whatever precedes the named fields of a derived type,
+    /// returning the number of fields which follow.
+    #[inline]
+    fn decode_fields(&self, reader: impl Read, fields: &[&'static str]) -> Result<usize, Error> {
+        let _ = reader;
+        Ok(fields.len())
+    }
+
+    /// Decodes whatever precedes the next field of a derived type.
+    ///
+    /// `index` counts the fields decoded before it. Returns the position of
+    /// the field in `fields`, or `None` if the format skipped a field
+    /// unknown to the type. By default, the fields follow in order.
+    #[inline]
+    fn decode_field(
+        &self,
+        reader: impl Read,
+        fields: &[&'static str],
+        index: usize,
+    ) -> Result<Option<usize>, Error> {
+        let _ = (reader, fields);
+        Ok(Some(index))
+    }
+
+    /// Encodes whatever precedes the `len` elements of an array or tuple.
+    #[inline]
+    fn encode_items(&self, writer: impl Write, len: usize) -> Result<(), Error> {
+        let _ = (writer, len);
+        Ok(())
+    }
+
+    /// Decodes whatever precedes the `len` elements of an array or tuple.
+    #[inline]
+    fn decode_items(&self, reader: impl Read, len: usize) -> Result<(), Error> {
+        let _ = (reader, len);
+        Ok(())
+    }
+}
+
+impl<T: ?Sized> Concat<T> for Le {}
+impl<T: ?Sized> Concat<T> for Be {}
+impl<T: ?Sized> Concat<T> for Ne {}
+impl<T: ?Sized> Concat<T> for Uleb128 {}
+impl<T: ?Sized> Concat<T> for Sleb128 {}
+impl<T: ?Sized> Concat<T> for ZigZag {}
+impl<T: ?Sized, L: Clone, P: Clone> Concat<T> for Prefixed<L, P> {}
+impl<T: ?Sized, L: Clone> Concat<T> for ByteStr<L> {}
+impl<T: ?Sized, G: Clone, P: Clone> Concat<T> for Optional<G, P> {}
+
+/// Parameters which encode a `usize` as the integer type `I`.
+///
+/// The integer is encoded using the parameters `F`. Encoding fails with
+/// `Error::LimitExceeded` if the value does not fit in `I`.
+#[derive(Debug)]
+pub struct Len<I, F> {
+    params: F,
+    int: PhantomData<fn() -> I>,
+}
+
+impl<I, F> Len<I, F> {
+    /// Creates parameters encoding a `usize` as `I` with `params`.
+    pub const fn new(params: F) -> Self {
+        Self {
+            params,
+            int: PhantomData,
+        }
+    }
+}
+
+impl<I, F: Clone> Clone for Len<I, F> {
+    fn clone(&self) -> Self {
+        Self::new(self.params.clone())
+    }
+}
+
+impl<I, F: Copy> Copy for Len<I, F> {}
+
+impl<I, F: Default> Default for Len<I, F> {
+    fn default() -> Self {
+        Self::new(F::default())
+    }
+}
+
+impl<I, F> Encoder<Len<I, F>> for usize
+where
+    I: Encoder<F> + TryFrom<usize>,
+    <I as Encoder<F>>::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn encode(&self, writer: impl Write, params: Len<I, F>) -> Result<(), Error> {
+        let int = I::try_from(*self).map_err(|_| Error::limit("length exceeds prefix"))?;
+        int.encode(writer, params.params).map_err(Into::into)
+    }
+}
+
+impl<I, F> Decoder<Len<I, F>> for usize
+where
+    I: Decoder<F>,
+    I::Error: Into<Error>,
+    usize: TryFrom<I>,
+{
+    type Error = Error;
+
+    fn decode(reader: impl Read, params: Len<I, F>) -> Result<Self, Error> {
+        let int = I::decode(reader, params.params).map_err(Into::into)?;
+        usize::try_from(int).map_err(|_| Error::limit("length exceeds usize"))
+    }
+}
+
+/// Parameters for collections prefixed with their number of elements.
+///
+/// The length is encoded as a `usize` with the parameters `L` and each
+/// element is encoded with a clone of the parameters `P`. Decoding checks
+/// the length against the collection length and allocation size limits.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Prefixed<L, P> {
+    len: L,
+    params: P,
+    limits: Limits,
+}
+
+impl<L, P> Prefixed<L, P> {
+    /// Creates parameters with length parameters `len` and element parameters `params`.
+    pub const fn new(len: L, params: P) -> Self {
+        Self {
+            len,
+            params,
+            limits: Limits::DEFAULT,
+        }
+    }
+
+    /// Sets the limits checked when decoding.
+    pub fn with_limits(self, limits: Limits) -> Self {
+        Self { limits, ..self }
+    }
+
+    /// Returns the limits checked when decoding.
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+}
+
+/// Parameters for byte strings prefixed with their length in bytes.
+///
+/// The length is encoded as a `usize` with the parameters `L`. Text strings
+/// are encoded as UTF-8 and must be valid UTF-8 when decoded. Decoding checks
+/// the length against the collection length and allocation size limits.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ByteStr<L> {
+    pub(crate) len: L,
+    pub(crate) limits: Limits,
+}
+
+impl<L> ByteStr<L> {
+    /// Creates parameters with length parameters `len`.
+    pub const fn new(len: L) -> Self {
+        Self {
+            len,
+            limits: Limits::DEFAULT,
+        }
+    }
+
+    /// Sets the limits checked when decoding.
+    pub fn with_limits(self, limits: Limits) -> Self {
+        Self { limits, ..self }
+    }
+
+    /// Returns the limits checked when decoding.
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+}
+
+/// Parameters for optional values preceded by a presence tag.
+///
+/// The tag is encoded as a `bool` with the parameters `G` and the value, if
+/// present, is encoded with the parameters `P`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Optional<G, P> {
+    tag: G,
+    params: P,
+}
+
+impl<G, P> Optional<G, P> {
+    /// Creates parameters with tag parameters `tag` and value parameters `params`.
+    pub const fn new(tag: G, params: P) -> Self {
+        Self { tag, params }
+    }
+}
+
+/// Returns the length of the header `params` writes before `len` items.
+fn items_len<P: Concat>(params: &P, len: usize) -> Result<usize, Error> {
+    let mut counter = Counter::default();
+    params.encode_items(&mut counter, len)?;
+    Ok(counter.count())
+}
+
+/// Adds the encoded length of an item to a total.
+fn add_len<E: Into<Error>>(total: usize, len: Result<usize, E>) -> Result<usize, Error> {
+    total
+        .checked_add(len.map_err(Into::into)?)
+        .ok_or(Error::limit("length exceeds usize"))
+}
+
+impl<T: Encoder<P>, P: Concat, const N: usize> Encoder<P> for [T; N]
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, params: P) -> Result<(), Error> {
+        params.encode_items(&mut writer, N)?;
+
+        for item in self {
+            item.encode(&mut writer, params.clone())
+                .map_err(Into::into)?;
+        }
+
+        Ok(())
+    }
+
+    fn encoded_len(&self, params: P) -> Result<usize, Error> {
+        let mut len = items_len(&params, N)?;
+        for item in self {
+            len = add_len(len, item.encoded_len(params.clone()))?;
+        }
+
+        Ok(len)
+    }
+}
+
+impl<T: ConstEncodedLen<P>, P: Concat, const N: usize> ConstEncodedLen<P> for [T; N]
+where
+    T::Error: Into<Error>,
+{
+    const ENCODED_LEN: usize = T::ENCODED_LEN * N;
+}
+
+impl<T: Decoder<P>, P: Concat, const N: usize> Decoder<P> for [T; N]
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: P) -> Result<Self, Error> {
+        params.decode_items(&mut reader, N)?;
+
+        let mut error = None;
+        let items: [Option<T>; N] = core::array::from_fn(|_| match error {
+            Some(_) => None,
+            None => match T::decode(&mut reader, params.clone()) {
+                Ok(item) => Some(item),
+                Err(e) => {
+                    error = Some(e.into());
+                    None
+                }
+            },
+        });
+
+        match error {
+            Some(e) => Err(e),
+            None => Ok(items.map(|item| item.unwrap())),
+        }
+    }
+}
+
+macro_rules! tuple {
+    ($($t:ident)+) => {
+        impl<P: Concat, $($t: Encoder<P>),+> Encoder<P> for ($($t,)+)
+        where
+            $($t::Error: Into<Error>,)+
+        {
+            type Error = Error;
+
+            #[allow(non_snake_case)]
+            fn encode(&self, mut writer: impl Write, params: P) -> Result<(), Error> {
+                let ($($t,)+) = self;
+                params.encode_items(&mut writer, [$(stringify!($t)),+].len())?;
+                $($t.encode(&mut writer, params.clone()).map_err(Into::into)?;)+
+                Ok(())
+            }
+
+            #[allow(non_snake_case)]
+            fn encoded_len(&self, params: P) -> Result<usize, Error> {
+                let ($($t,)+) = self;
+                let len = items_len(&params, [$(stringify!($t)),+].len())?;
+                $(let len = add_len(len, $t.encoded_len(params.clone()))?;)+
+                Ok(len)
+            }
+        }
+
+        impl<P: Concat, $($t: ConstEncodedLen<P>),+> ConstEncodedLen<P> for ($($t,)+)
+        where
+            $($t::Error: Into<Error>,)+
+        {
+            const ENCODED_LEN: usize = 0 $(+ $t::ENCODED_LEN)+;
+        }
+
+        impl<P: Concat, $($t: Decoder<P>),+> Decoder<P> for ($($t,)+)
+        where
+            $($t::Error: Into<Error>,)+
+        {
+            type Error = Error;
+
+            fn decode(mut reader: impl Read, params: P) -> Result<Self, Error> {
+                params.decode_items(&mut reader, [$(stringify!($t)),+].len())?;
+                Ok(($($t::decode(&mut reader, params.clone()).map_err(Into::into)?,)+))
+            }
+        }
+    };
+}
+
+tuple!(A);
+tuple!(A B);
+tuple!(A B C);
+tuple!(A B C D);
+tuple!(A B C D E);
+tuple!(A B C D E F);
+tuple!(A B C D E F G);
+tuple!(A B C D E F G H);
+tuple!(A B C D E F G H I);
+tuple!(A B C D E F G H I J);
+tuple!(A B C D E F G H I J K);
+tuple!(A B C D E F G H I J K L);
+
+#[cfg(feature = "alloc")]
+impl<T: Encoder<P> + ?Sized, P: Concat> Encoder<P> for Box<T> {
+    type Error = T::Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: P) -> Result<(), Self::Error> {
+        (**self).encode(writer, params)
+    }
+
+    #[inline]
+    fn encoded_len(&self, params: P) -> Result<usize, Self::Error> {
+        (**self).encoded_len(params)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Decoder<P>, P: Concat> Decoder<P> for Box<T> {
+    type Error = T::Error;
+
+    #[inline]
+    fn decode(reader: impl Read, params: P) -> Result<Self, Self::Error> {
+        T::decode(reader, params).map(Box::new)
+    }
+}
+
+impl<T: Encoder<P>, L, P: Clone> Encoder<Prefixed<L, P>> for [T]
+where
+    usize: Encoder<L>,
+    <usize as Encoder<L>>::Error: Into<Error>,
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, params: Prefixed<L, P>) -> Result<(), Error> {
+        self.len()
+            .encode(&mut writer, params.len)
+            .map_err(Into::into)?;
+
+        for item in self {
+            item.encode(&mut writer, params.params.clone())
+                .map_err(Into::into)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Encoder<P>, L, P: Clone> Encoder<Prefixed<L, P>> for Vec<T>
+where
+    usize: Encoder<L>,
+    <usize as Encoder<L>>::Error: Into<Error>,
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: Prefixed<L, P>) -> Result<(), Error> {
+        self.as_slice().encode(writer, params)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Decoder<P>, L, P: Clone> Decoder<Prefixed<L, P>> for Vec<T>
+where
+    usize: Decoder<L>,
+    <usize as Decoder<L>>::Error: Into<Error>,
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: Prefixed<L, P>) -> Result<Self, Error> {
+        let len = usize::decode(&mut reader, params.len).map_err(Into::into)?;
+        params.limits.check_len(len)?;
+        params.limits.check_alloc::<T>(len)?;
+
+        let mut items = crate::io::with_capacity(len);
+
+        for _ in 0..len {
+            items.push(T::decode(&mut reader, params.params.clone()).map_err(Into::into)?);
+        }
+
+        Ok(items)
+    }
+}
+
+impl<T: Encoder<P>, G, P> Encoder<Optional<G, P>> for Option<T>
+where
+    bool: Encoder<G>,
+    <bool as Encoder<G>>::Error: Into<Error>,
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, params: Optional<G, P>) -> Result<(), Error> {
+        self.is_some()
+            .encode(&mut writer, params.tag)
+            .map_err(Into::into)?;
+
+        match self {
+            Some(value) => value.encode(writer, params.params).map_err(Into::into),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: Decoder<P>, G, P> Decoder<Optional<G, P>> for Option<T>
+where
+    bool: Decoder<G>,
+    <bool as Decoder<G>>::Error: Into<Error>,
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: Optional<G, P>) -> Result<Self, Error> {
+        match bool::decode(&mut reader, params.tag).map_err(Into::into)? {
+            true => T::decode(reader, params.params)
+                .map(Some)
+                .map_err(Into::into),
+            false => Ok(None),
+        }
+    }
+}
+
+impl<L> Encoder<ByteStr<L>> for [u8]
+where
+    usize: Encoder<L>,
+    <usize as Encoder<L>>::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, params: ByteStr<L>) -> Result<(), Error> {
+        self.len()
+            .encode(&mut writer, params.len)
+            .map_err(Into::into)?;
+        Ok(writer.write_all(self)?)
+    }
+}
+
+impl<L> Encoder<ByteStr<L>> for str
+where
+    usize: Encoder<L>,
+    <usize as Encoder<L>>::Error: Into<Error>,
+{
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: ByteStr<L>) -> Result<(), Error> {
+        self.as_bytes().encode(writer, params)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<L> Encoder<ByteStr<L>> for Vec<u8>
+where
+    usize: Encoder<L>,
+    <usize as Encoder<L>>::Error: Into<Error>,
+{
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: ByteStr<L>) -> Result<(), Error> {
+        self.as_slice().encode(writer, params)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<L> Decoder<ByteStr<L>> for Vec<u8>
+where
+    usize: Decoder<L>,
+    <usize as Decoder<L>>::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: ByteStr<L>) -> Result<Self, Error> {
+        let len = usize::decode(&mut reader, params.len).map_err(Into::into)?;
+        params.limits.check_len(len)?;
+        params.limits.check_alloc::<u8>(len)?;
+        Ok(crate::io::read_vec(reader, len)?)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<L> Encoder<ByteStr<L>> for String
+where
+    usize: Encoder<L>,
+    <usize as Encoder<L>>::Error: Into<Error>,
+{
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: ByteStr<L>) -> Result<(), Error> {
+        self.as_bytes().encode(writer, params)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<L> Decoder<ByteStr<L>> for String
+where
+    usize: Decoder<L>,
+    <usize as Decoder<L>>::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn decode(reader: impl Read, params: ByteStr<L>) -> Result<Self, Error> {
+        let bytes = Vec::decode(reader, params)?;
+        String::from_utf8(bytes).map_err(|_| Error::invalid("invalid UTF-8"))
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::DecoderExt;
+
+    fn exceeded<T: core::fmt::Debug>(result: Result<T, Error>, expected: &str) {
+        match result {
+            Err(Error::LimitExceeded { limit, .. }) => assert_eq!(limit, expected),
+            result => panic!("expected {} limit, got {:?}", expected, result),
+        }
+    }
+
+    #[test]
+    fn prefixed_limits() {
+        let prefixed = Prefixed::new(Uleb128, Le);
+        let buf = [3, 1, 0, 2, 0, 3, 0];
+        assert_eq!(Vec::<u16>::decode_exact(&buf, prefixed).unwrap(), [1, 2, 3]);
+
+        let limits = Limits::NONE.len(2);
+        exceeded(
+            Vec::<u16>::decode_exact(&buf, prefixed.with_limits(limits)),
+            "collection length",
+        );
+
+        let limits = Limits::NONE.alloc(5);
+        exceeded(
+            Vec::<u16>::decode_exact(&buf, prefixed.with_limits(limits)),
+            "allocation size",
+        );
+    }
+
+    #[test]
+    fn bytestr_limits() {
+        let bytestr = ByteStr::new(Uleb128);
+        let buf = [3, b'a', b'b', b'c'];
+        assert_eq!(String::decode_exact(&buf, bytestr).unwrap(), "abc");
+
+        let limits = Limits::NONE.len(2);
+        exceeded(
+            String::decode_exact(&buf, bytestr.with_limits(limits)),
+            "collection length",
+        );
+
+        let limits = Limits::NONE.alloc(2);
+        exceeded(
+            Vec::<u8>::decode_exact(&buf, bytestr.with_limits(limits)),
+            "allocation size",
+        );
+
+        let error = String::decode_exact(&[2, 0xc3, 0x28], bytestr).unwrap_err();
+        assert!(matches!(
+            error,
+            Error::InvalidValue {
+                reason: "invalid UTF-8",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn short_input() {
+        assert!(<[u16; 3]>::decode(&[1u8, 0, 2, 0, 3][..], Le)
+            .unwrap_err()
+            .is_eof());
+        assert!(<(u8, u32)>::decode(&[1u8, 2][..], Be).unwrap_err().is_eof());
+
+        let prefixed = Prefixed::new(Uleb128, Le).with_limits(Limits::NONE);
+        assert!(Vec::<u16>::decode(&[3u8, 1, 0, 2][..], prefixed)
+            .unwrap_err()
+            .is_eof());
+
+        // A huge length is not trusted for the allocation.
+        let bytestr = ByteStr::new(Uleb128).with_limits(Limits::NONE);
+        let buf = [0xff, 0xff, 0xff, 0xff, 0x0f, 1, 2];
+        assert!(Vec::<u8>::decode(&buf[..], bytestr).unwrap_err().is_eof());
+    }
+}
@@ -0,0 +1,1114 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! The ASN.1 Distinguished Encoding Rules (DER, X.690).
+//!
+//! Every value encodes as a tag, the length of its contents and the contents
+//! themselves. Under the `Der` parameters, `bool` encodes as a BOOLEAN, the
+//! integer types as INTEGERs, `str` and `String` as UTF8Strings and `Vec` as a
+//! SEQUENCE OF. The remaining ASN.1 types are represented by the types of this
+//! module, such as `OctetString`, `BitString` and `ObjectIdentifier`.
+//!
+//! Arrays, tuples and types using the derive macros encode as the
+//! concatenation of their elements, which forms the contents of a SEQUENCE
+//! when wrapped in `Sequence`. Likewise, `Explicit` wraps a value in an
+//! explicit context-specific tag. `Tlv` holds any element without
+//! interpreting its contents.
+//!
+//! Decoding is strict: anything which is not the unique DER encoding of a
+//! value, such as a length or integer which is not in its shortest form, an
+//! indefinite length or an unsorted SET OF, fails with `Error::InvalidValue`.
+//! Only tag numbers below 31 are supported. Each constructed element takes
+//! a level of the depth limit of the parameters' `Limits`.
+//!
+//! ```rust
+//! use codicon::*;
+//! use codicon::der::{ObjectIdentifier, OctetString, Sequence, SetOf};
+//!
+//! let der = Der::new();
+//! assert_eq!(128u32.encode_to_vec(der).unwrap(), [0x02, 0x02, 0x00, 0x80]);
+//! assert_eq!((-129i32).encode_to_vec(der).unwrap(), [0x02, 0x02, 0xff, 0x7f]);
+//! assert!(u32::decode_exact(&[0x02, 0x02, 0x00, 0x7f], der).is_err());
+//!
+//! let oid = ObjectIdentifier(vec![1, 2, 840, 113549]);
+//! let buf = oid.encode_to_vec(der).unwrap();
+//! assert_eq!(buf, [0x06, 0x06, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d]);
+//! assert_eq!(ObjectIdentifier::decode_exact(&buf, der).unwrap(), oid);
+//!
+//! // SEQUENCE { BOOLEAN, OCTET STRING }
+//! let value = Sequence((true, OctetString(vec![1, 2])));
+//! let buf = value.encode_to_vec(der).unwrap();
+//! assert_eq!(buf, [0x30, 0x07, 0x01, 0x01, 0xff, 0x04, 0x02, 0x01, 0x02]);
+//! assert_eq!(Sequence::decode_exact(&buf, der).unwrap(), value);
+//!
+//! // The elements of a SET OF are sorted by their encodings.
+//! let set = SetOf(vec![256u16, 1]);
+//! let buf = set.encode_to_vec(der).unwrap();
+//! assert_eq!(buf, [0x31, 0x07, 0x02, 0x01, 0x01, 0x02, 0x02, 0x01, 0x00]);
+//! assert!(SetOf::<u16>::decode_exact(&[0x31, 0x07, 0x02, 0x02, 0x01, 0x00, 0x02, 0x01, 0x01], der).is_err());
+//!
+//! // Lengths must be in their shortest form.
+//! assert!(bool::decode_exact(&[0x01, 0x81, 0x01, 0xff], der).is_err());
+//! ```
+
+use crate::container::Concat;
+use crate::framed::Frame;
+use crate::int;
+use crate::io::{self, byte, Read, Record, Write};
+use crate::limits::Limits;
+use crate::{Decoder, Encoder, Error};
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+const BOOLEAN: u8 = 0x01;
+const INTEGER: u8 = 0x02;
+const BIT_STRING: u8 = 0x03;
+const OCTET_STRING: u8 = 0x04;
+const NULL: u8 = 0x05;
+const OBJECT_IDENTIFIER: u8 = 0x06;
+const UTF8_STRING: u8 = 0x0c;
+const UTC_TIME: u8 = 0x17;
+const GENERALIZED_TIME: u8 = 0x18;
+const SEQUENCE: u8 = 0x30;
+const SET: u8 = 0x31;
+
+/// DER encoding parameters.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Der {
+    limits: Limits,
+}
+
+impl Der {
+    /// Creates parameters with the default limits.
+    pub const fn new() -> Self {
+        Self {
+            limits: Limits::DEFAULT,
+        }
+    }
+
+    /// Sets the limits checked when decoding.
+    pub fn with_limits(self, limits: Limits) -> Self {
+        Self { limits }
+    }
+
+    /// Returns the limits checked when decoding.
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+}
+
+impl<T: ?Sized> Concat<T> for Der {}
+
+/// An element with any tag and uninterpreted contents.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Tlv {
+    /// The identifier octet, including the class and constructed bits.
+    pub tag: u8,
+
+    /// The contents octets.
+    pub value: Vec<u8>,
+}
+
+/// The NULL value.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Null;
+
+/// An OCTET STRING.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct OctetString(pub Vec<u8>);
+
+/// A BIT STRING.
+///
+/// The bits are stored most significant first. The last `unused` bits of the
+/// last byte are not part of the string and must be zero.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BitString {
+    /// The bytes holding the bits.
+    pub bytes: Vec<u8>,
+
+    /// The number of unused bits in the last byte, at most seven.
+    pub unused: u8,
+}
+
+/// An OBJECT IDENTIFIER, as its arcs.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ObjectIdentifier(pub Vec<u64>);
+
+/// A SEQUENCE whose contents are the encoding of the inner value.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Sequence<T>(pub T);
+
+/// A SET OF, whose elements are encoded in the order of their encodings.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SetOf<T>(pub Vec<T>);
+
+/// A value with an explicit context-specific tag `[N]`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Explicit<T, const N: u8>(pub T);
+
+/// A date and time in UTC, with a precision of one second.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateTime {
+    /// The year.
+    pub year: u16,
+
+    /// The month, starting from one.
+    pub month: u8,
+
+    /// The day of the month, starting from one.
+    pub day: u8,
+
+    /// The hour.
+    pub hour: u8,
+
+    /// The minute.
+    pub minute: u8,
+
+    /// The second.
+    pub second: u8,
+}
+
+impl DateTime {
+    fn check(&self) -> Result<(), Error> {
+        let leap = match (self.year % 4, self.year % 100, self.year % 400) {
+            (_, _, 0) => true,
+            (_, 0, _) => false,
+            (0, _, _) => true,
+            _ => false,
+        };
+
+        let days = match self.month {
+            2 if leap => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        };
+
+        match (1..=12).contains(&self.month)
+            && (1..=days).contains(&self.day)
+            && self.hour < 24
+            && self.minute < 60
+            && self.second < 60
+        {
+            true => Ok(()),
+            false => Err(Error::invalid("invalid date or time")),
+        }
+    }
+}
+
+/// A UTCTime, for years from 1950 to 2049.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UtcTime(pub DateTime);
+
+/// A GeneralizedTime, without fractional seconds.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GeneralizedTime(pub DateTime);
+
+fn header(mut writer: impl Write, tag: u8, len: usize) -> Result<(), Error> {
+    if tag & 0x1f == 0x1f {
+        return Err(Error::invalid("unsupported tag"));
+    }
+
+    if len < 0x80 {
+        return Ok(writer.write_all(&[tag, len as u8])?);
+    }
+
+    let bytes = len.to_be_bytes();
+    let skip = (len.leading_zeros() / 8) as usize;
+    writer.write_all(&[tag, 0x80 | (bytes.len() - skip) as u8])?;
+    Ok(writer.write_all(&bytes[skip..])?)
+}
+
+/// Returns the length of the header of an element with `len` bytes of contents.
+fn header_len(len: usize) -> Result<usize, Error> {
+    let skip = (len.leading_zeros() / 8) as usize;
+    let n = match len < 0x80 {
+        true => 2,
+        false => 2 + core::mem::size_of::<usize>() - skip,
+    };
+
+    len.checked_add(n)
+        .ok_or(Error::limit("length exceeds usize"))
+}
+
+/// Reads a tag and the length of its contents.
+fn read_header(mut reader: impl Read) -> Result<(u8, usize), Error> {
+    let tag = byte(&mut reader)?;
+    if tag & 0x1f == 0x1f {
+        return Err(Error::invalid("unsupported tag"));
+    }
+
+    let len = match byte(&mut reader)? {
+        len @ 0..=0x7f => usize::from(len),
+        0x80 => return Err(Error::invalid("indefinite length")),
+        0xff => return Err(Error::invalid("reserved length")),
+        first => {
+            let mut buf = [0u8; core::mem::size_of::<usize>()];
+            let n = usize::from(first & 0x7f);
+            if n > buf.len() {
+                return Err(Error::limit("length exceeds usize"));
+            }
+
+            let start = buf.len() - n;
+            reader.read_exact(&mut buf[start..])?;
+            match usize::from_be_bytes(buf) {
+                len if len < 0x80 || buf[start] == 0 => {
+                    return Err(Error::invalid("non-minimal length"))
+                }
+                len => len,
+            }
+        }
+    };
+
+    Ok((tag, len))
+}
+
+/// Reads the header of an element which must have the given tag.
+fn expect(reader: impl Read, tag: u8) -> Result<usize, Error> {
+    match read_header(reader)? {
+        (actual, len) if actual == tag => Ok(len),
+        _ => Err(Error::invalid("unexpected tag")),
+    }
+}
+
+/// Returns the tag of an explicit context-specific tag number.
+fn context(n: u8) -> Result<u8, Error> {
+    match n {
+        0..=30 => Ok(0xa0 | n),
+        _ => Err(Error::invalid("unsupported tag")),
+    }
+}
+
+/// Reads `len` bytes of contents without trusting `len` with a large allocation.
+fn contents(reader: impl Read, len: usize, limits: Limits) -> Result<Vec<u8>, Error> {
+    limits.check_len(len)?;
+    limits.check_alloc::<u8>(len)?;
+    Ok(io::read_vec(reader, len)?)
+}
+
+/// Encodes a value as the contents of a constructed element.
+fn encode_constructed<T: Encoder<Der> + ?Sized>(
+    mut writer: impl Write,
+    tag: u8,
+    value: &T,
+    params: Der,
+) -> Result<(), Error>
+where
+    T::Error: Into<Error>,
+{
+    let len = value.encoded_len(params).map_err(Into::into)?;
+    header(&mut writer, tag, len)?;
+    value.encode(writer, params).map_err(Into::into)
+}
+
+/// Returns the length of a constructed element holding a value.
+fn constructed_len<T: Encoder<Der> + ?Sized>(value: &T, params: Der) -> Result<usize, Error>
+where
+    T::Error: Into<Error>,
+{
+    header_len(value.encoded_len(params).map_err(Into::into)?)
+}
+
+/// Returns the total length of the items of a sequence or set.
+fn items_len<T: Encoder<Der>>(items: &[T], params: Der) -> Result<usize, Error>
+where
+    T::Error: Into<Error>,
+{
+    let mut len = 0usize;
+    for item in items {
+        let n = item.encoded_len(params).map_err(Into::into)?;
+        len = len
+            .checked_add(n)
+            .ok_or(Error::limit("length exceeds usize"))?;
+    }
+
+    Ok(len)
+}
+
+/// Decodes the contents of a constructed element, which must be consumed
+/// exactly, with parameters for one level of nesting deeper.
+fn decode_constructed<R: Read, T>(
+    mut reader: R,
+    tag: u8,
+    params: Der,
+    decode: impl FnOnce(&mut Frame<&mut R>, Der) -> Result<T, Error>,
+) -> Result<T, Error> {
+    let len = expect(&mut reader, tag)?;
+    params.limits.check_bytes(len)?;
+    let params = params.with_limits(params.limits.nested()?);
+
+    let mut frame = Frame {
+        reader: &mut reader,
+        remaining: len,
+    };
+
+    let value = match decode(&mut frame, params) {
+        Err(e) if e.is_eof() && frame.remaining == 0 => {
+            return Err(Error::invalid("value overruns contents"))
+        }
+        result => result?,
+    };
+
+    match frame.remaining {
+        0 => Ok(value),
+        _ => Err(Error::invalid("value underruns contents")),
+    }
+}
+
+impl Encoder<Der> for Tlv {
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, _: Der) -> Result<(), Error> {
+        header(&mut writer, self.tag, self.value.len())?;
+        Ok(writer.write_all(&self.value)?)
+    }
+}
+
+impl Decoder<Der> for Tlv {
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: Der) -> Result<Self, Error> {
+        let (tag, len) = read_header(&mut reader)?;
+        let value = contents(reader, len, params.limits)?;
+        Ok(Self { tag, value })
+    }
+}
+
+impl Encoder<Der> for bool {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, mut writer: impl Write, _: Der) -> Result<(), Error> {
+        Ok(writer.write_all(&[BOOLEAN, 1, if *self { 0xff } else { 0x00 }])?)
+    }
+}
+
+impl Decoder<Der> for bool {
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, _: Der) -> Result<Self, Error> {
+        if expect(&mut reader, BOOLEAN)? != 1 {
+            return Err(Error::invalid("invalid bool length"));
+        }
+
+        match byte(reader)? {
+            0x00 => Ok(false),
+            0xff => Ok(true),
+            _ => Err(Error::invalid("invalid bool")),
+        }
+    }
+}
+
+impl Encoder<Der> for Null {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, mut writer: impl Write, _: Der) -> Result<(), Error> {
+        Ok(writer.write_all(&[NULL, 0])?)
+    }
+}
+
+impl Decoder<Der> for Null {
+    type Error = Error;
+
+    fn decode(reader: impl Read, _: Der) -> Result<Self, Error> {
+        match expect(reader, NULL)? {
+            0 => Ok(Null),
+            _ => Err(Error::invalid("invalid null length")),
+        }
+    }
+}
+
+macro_rules! int {
+    ($($t:ident)+) => {
+        $(
+            impl Encoder<Der> for $t {
+                type Error = Error;
+
+                fn encode(&self, mut writer: impl Write, _: Der) -> Result<(), Error> {
+                    let (buf, start) =
+                        int::encode(*self).ok_or(Error::invalid("integer out of range"))?;
+
+                    writer.write_all(&[INTEGER, (buf.len() - start) as u8])?;
+                    Ok(writer.write_all(&buf[start..])?)
+                }
+            }
+
+            impl Decoder<Der> for $t {
+                type Error = Error;
+
+                fn decode(mut reader: impl Read, _: Der) -> Result<Self, Error> {
+                    let len = expect(&mut reader, INTEGER)?;
+                    if len == 0 {
+                        return Err(Error::invalid("empty integer"));
+                    }
+
+                    let mut buf = [0u8; int::MAX_LEN];
+                    if len > buf.len() {
+                        return Err(Error::invalid("integer out of range"));
+                    }
+
+                    let bytes = &mut buf[..len];
+                    reader.read_exact(bytes)?;
+
+                    if int::redundant(bytes) {
+                        return Err(Error::invalid("non-minimal integer"));
+                    }
+
+                    int::decode(bytes).ok_or(Error::invalid("integer out of range"))
+                }
+            }
+        )+
+    };
+}
+
+int!(u8 u16 u32 u64 u128 usize i8 i16 i32 i64 i128 isize);
+
+impl Encoder<Der> for OctetString {
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, _: Der) -> Result<(), Error> {
+        header(&mut writer, OCTET_STRING, self.0.len())?;
+        Ok(writer.write_all(&self.0)?)
+    }
+}
+
+impl Decoder<Der> for OctetString {
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: Der) -> Result<Self, Error> {
+        let len = expect(&mut reader, OCTET_STRING)?;
+        contents(reader, len, params.limits).map(Self)
+    }
+}
+
+impl BitString {
+    fn check(&self) -> Result<(), Error> {
+        match (self.bytes.last(), self.unused) {
+            (_, 0) => Ok(()),
+            (None, _) | (_, 8..=u8::MAX) => Err(Error::invalid("too many unused bits")),
+            (Some(last), unused) if last & ((1 << unused) - 1) != 0 => {
+                Err(Error::invalid("nonzero unused bits"))
+            }
+            (Some(_), _) => Ok(()),
+        }
+    }
+}
+
+impl Encoder<Der> for BitString {
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, _: Der) -> Result<(), Error> {
+        self.check()?;
+
+        let len = self.bytes.len().checked_add(1);
+        header(
+            &mut writer,
+            BIT_STRING,
+            len.ok_or(Error::limit("length exceeds usize"))?,
+        )?;
+        writer.write_all(&[self.unused])?;
+        Ok(writer.write_all(&self.bytes)?)
+    }
+}
+
+impl Decoder<Der> for BitString {
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: Der) -> Result<Self, Error> {
+        let len = expect(&mut reader, BIT_STRING)?;
+        let len = len
+            .checked_sub(1)
+            .ok_or(Error::invalid("empty bit string"))?;
+
+        let unused = byte(&mut reader)?;
+        let bytes = contents(reader, len, params.limits)?;
+
+        let value = Self { bytes, unused };
+        value.check()?;
+        Ok(value)
+    }
+}
+
+impl Encoder<Der> for ObjectIdentifier {
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, _: Der) -> Result<(), Error> {
+        let first = match self.0.as_slice() {
+            [first @ 0..=1, second @ 0..=39, ..] => first * 40 + second,
+            [2, second, ..] => second
+                .checked_add(80)
+                .ok_or(Error::invalid("invalid object identifier"))?,
+            _ => return Err(Error::invalid("invalid object identifier")),
+        };
+
+        // Each subidentifier takes at most ten base-128 digits.
+        let mut contents = Vec::new();
+        for arc in core::iter::once(first).chain(self.0[2..].iter().copied()) {
+            let mut digits = [0u8; 10];
+            let mut n = 0;
+            let mut arc = arc;
+
+            loop {
+                digits[n] = (arc & 0x7f) as u8 | if n > 0 { 0x80 } else { 0 };
+                n += 1;
+                arc >>= 7;
+
+                if arc == 0 {
+                    break;
+                }
+            }
+
+            contents.extend(digits[..n].iter().rev());
+        }
+
+        header(&mut writer, OBJECT_IDENTIFIER, contents.len())?;
+        Ok(writer.write_all(&contents)?)
+    }
+}
+
+impl Decoder<Der> for ObjectIdentifier {
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: Der) -> Result<Self, Error> {
+        let len = expect(&mut reader, OBJECT_IDENTIFIER)?;
+        let contents = contents(reader, len, params.limits)?;
+
+        let mut arcs = Vec::new();
+        let mut arc = 0u64;
+        let mut start = true;
+
+        for byte in &contents {
+            if start && *byte == 0x80 {
+                return Err(Error::invalid("non-minimal subidentifier"));
+            }
+
+            if arc >> 57 != 0 {
+                return Err(Error::invalid("subidentifier out of range"));
+            }
+
+            arc = arc << 7 | u64::from(byte & 0x7f);
+            start = byte & 0x80 == 0;
+
+            if start {
+                match arcs.is_empty() {
+                    true if arc < 80 => arcs.extend([arc / 40, arc % 40].iter()),
+                    true => arcs.extend([2, arc - 80].iter()),
+                    false => arcs.push(arc),
+                }
+
+                arc = 0;
+            }
+        }
+
+        match start && !arcs.is_empty() {
+            true => Ok(Self(arcs)),
+            false => Err(Error::invalid("truncated object identifier")),
+        }
+    }
+}
+
+impl Encoder<Der> for str {
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, _: Der) -> Result<(), Error> {
+        header(&mut writer, UTF8_STRING, self.len())?;
+        Ok(writer.write_all(self.as_bytes())?)
+    }
+}
+
+impl Encoder<Der> for String {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: Der) -> Result<(), Error> {
+        self.as_str().encode(writer, params)
+    }
+}
+
+impl Decoder<Der> for String {
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: Der) -> Result<Self, Error> {
+        let len = expect(&mut reader, UTF8_STRING)?;
+        let bytes = contents(reader, len, params.limits)?;
+        String::from_utf8(bytes).map_err(|_| Error::invalid("invalid UTF-8"))
+    }
+}
+
+/// Writes `value` as `N` decimal digits.
+fn digits<const N: usize>(value: u16) -> [u8; N] {
+    let mut buf = [b'0'; N];
+    let mut value = value;
+
+    for digit in buf.iter_mut().rev() {
+        *digit += (value % 10) as u8;
+        value /= 10;
+    }
+
+    buf
+}
+
+/// Parses decimal digits.
+fn parse(digits: &[u8]) -> Result<u16, Error> {
+    digits.iter().try_fold(0u16, |value, digit| match digit {
+        b'0'..=b'9' => Ok(value * 10 + u16::from(digit - b'0')),
+        _ => Err(Error::invalid("invalid date or time")),
+    })
+}
+
+/// Encodes the time of day and the `Z` suffix following the year.
+fn encode_time(mut writer: impl Write, time: &DateTime) -> Result<(), Error> {
+    for value in [time.month, time.day, time.hour, time.minute, time.second].iter() {
+        writer.write_all(&digits::<2>((*value).into()))?;
+    }
+
+    Ok(writer.write_all(b"Z")?)
+}
+
+/// Decodes the month through to the `Z` suffix following the year.
+fn decode_time(year: u16, rest: &[u8]) -> Result<DateTime, Error> {
+    let field = |i: usize| parse(&rest[i * 2..i * 2 + 2]).map(|v| v as u8);
+    if rest.len() != 11 || rest[10] != b'Z' {
+        return Err(Error::invalid("invalid date or time"));
+    }
+
+    let time = DateTime {
+        year,
+        month: field(0)?,
+        day: field(1)?,
+        hour: field(2)?,
+        minute: field(3)?,
+        second: field(4)?,
+    };
+
+    time.check()?;
+    Ok(time)
+}
+
+impl Encoder<Der> for UtcTime {
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, _: Der) -> Result<(), Error> {
+        self.0.check()?;
+        if !(1950..2050).contains(&self.0.year) {
+            return Err(Error::invalid("year out of range"));
+        }
+
+        writer.write_all(&[UTC_TIME, 13])?;
+        writer.write_all(&digits::<2>(self.0.year % 100))?;
+        encode_time(writer, &self.0)
+    }
+}
+
+impl Decoder<Der> for UtcTime {
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, _: Der) -> Result<Self, Error> {
+        if expect(&mut reader, UTC_TIME)? != 13 {
+            return Err(Error::invalid("invalid date or time"));
+        }
+
+        let mut buf = [0u8; 13];
+        reader.read_exact(&mut buf)?;
+
+        let year = match parse(&buf[..2])? {
+            year @ 50..=99 => 1900 + year,
+            year => 2000 + year,
+        };
+
+        decode_time(year, &buf[2..]).map(Self)
+    }
+}
+
+impl Encoder<Der> for GeneralizedTime {
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, _: Der) -> Result<(), Error> {
+        self.0.check()?;
+        if self.0.year > 9999 {
+            return Err(Error::invalid("year out of range"));
+        }
+
+        writer.write_all(&[GENERALIZED_TIME, 15])?;
+        writer.write_all(&digits::<4>(self.0.year))?;
+        encode_time(writer, &self.0)
+    }
+}
+
+impl Decoder<Der> for GeneralizedTime {
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, _: Der) -> Result<Self, Error> {
+        if expect(&mut reader, GENERALIZED_TIME)? != 15 {
+            return Err(Error::invalid("invalid date or time"));
+        }
+
+        let mut buf = [0u8; 15];
+        reader.read_exact(&mut buf)?;
+        decode_time(parse(&buf[..4])?, &buf[4..]).map(Self)
+    }
+}
+
+impl<T: Encoder<Der>> Encoder<Der> for Sequence<T>
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: Der) -> Result<(), Error> {
+        encode_constructed(writer, SEQUENCE, &self.0, params)
+    }
+
+    #[inline]
+    fn encoded_len(&self, params: Der) -> Result<usize, Error> {
+        constructed_len(&self.0, params)
+    }
+}
+
+impl<T: Decoder<Der>> Decoder<Der> for Sequence<T>
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn decode(reader: impl Read, params: Der) -> Result<Self, Error> {
+        decode_constructed(reader, SEQUENCE, params, |reader, params| {
+            T::decode(reader, params).map(Self).map_err(Into::into)
+        })
+    }
+}
+
+impl<T: Encoder<Der>, const N: u8> Encoder<Der> for Explicit<T, N>
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: Der) -> Result<(), Error> {
+        encode_constructed(writer, context(N)?, &self.0, params)
+    }
+
+    #[inline]
+    fn encoded_len(&self, params: Der) -> Result<usize, Error> {
+        context(N)?;
+        constructed_len(&self.0, params)
+    }
+}
+
+impl<T: Decoder<Der>, const N: u8> Decoder<Der> for Explicit<T, N>
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn decode(reader: impl Read, params: Der) -> Result<Self, Error> {
+        decode_constructed(reader, context(N)?, params, |reader, params| {
+            T::decode(reader, params).map(Self).map_err(Into::into)
+        })
+    }
+}
+
+impl<T: Encoder<Der>> Encoder<Der> for [T]
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, params: Der) -> Result<(), Error> {
+        header(&mut writer, SEQUENCE, items_len(self, params)?)?;
+
+        for item in self {
+            item.encode(&mut writer, params).map_err(Into::into)?;
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn encoded_len(&self, params: Der) -> Result<usize, Error> {
+        header_len(items_len(self, params)?)
+    }
+}
+
+impl<T: Encoder<Der>> Encoder<Der> for Vec<T>
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: Der) -> Result<(), Error> {
+        self.as_slice().encode(writer, params)
+    }
+
+    #[inline]
+    fn encoded_len(&self, params: Der) -> Result<usize, Error> {
+        self.as_slice().encoded_len(params)
+    }
+}
+
+impl<T: Decoder<Der>> Decoder<Der> for Vec<T>
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn decode(reader: impl Read, params: Der) -> Result<Self, Error> {
+        decode_constructed(reader, SEQUENCE, params, |reader, params| {
+            let mut items = Vec::new();
+
+            while reader.remaining > 0 {
+                params.limits.check_len(items.len() + 1)?;
+                params.limits.check_alloc::<T>(items.len() + 1)?;
+                items.push(T::decode(&mut *reader, params).map_err(Into::into)?);
+            }
+
+            Ok(items)
+        })
+    }
+}
+
+impl<T: Encoder<Der>> Encoder<Der> for SetOf<T>
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, params: Der) -> Result<(), Error> {
+        let mut items = Vec::with_capacity(self.0.len());
+        for item in &self.0 {
+            let mut bytes = Vec::new();
+            item.encode(&mut bytes, params).map_err(Into::into)?;
+            items.push(bytes);
+        }
+
+        items.sort();
+
+        let len = items.iter().map(Vec::len).sum();
+        header(&mut writer, SET, len)?;
+
+        for item in items {
+            writer.write_all(&item)?;
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn encoded_len(&self, params: Der) -> Result<usize, Error> {
+        // Sorting the items doesn't change their total length.
+        header_len(items_len(&self.0, params)?)
+    }
+}
+
+impl<T: Decoder<Der>> Decoder<Der> for SetOf<T>
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn decode(reader: impl Read, params: Der) -> Result<Self, Error> {
+        decode_constructed(reader, SET, params, |reader, params| {
+            let mut items = Vec::new();
+            let mut last = Vec::new();
+
+            while reader.remaining > 0 {
+                params.limits.check_len(items.len() + 1)?;
+                params.limits.check_alloc::<T>(items.len() + 1)?;
+
+                let mut record = Record {
+                    reader: &mut *reader,
+                    bytes: Vec::new(),
+                };
+
+                items.push(T::decode(&mut record, params).map_err(Into::into)?);
+                if record.bytes < last {
+                    return Err(Error::invalid("unsorted set"));
+                }
+
+                last = record.bytes;
+            }
+
+            Ok(Self(items))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DecoderExt, EncoderExt};
+    use alloc::vec;
+
+    #[test]
+    fn non_minimal_integer() {
+        let der = Der::new();
+
+        for bytes in [&[0x02, 0x02, 0x00, 0x7f][..], &[0x02, 0x02, 0xff, 0x80]] {
+            let error = i32::decode_exact(bytes, der).unwrap_err();
+            assert!(matches!(
+                error,
+                Error::InvalidValue {
+                    reason: "non-minimal integer",
+                    ..
+                }
+            ));
+        }
+
+        let error = i32::decode_exact(&[0x02, 0x00], der).unwrap_err();
+        assert!(matches!(
+            error,
+            Error::InvalidValue {
+                reason: "empty integer",
+                ..
+            }
+        ));
+
+        assert_eq!(
+            i32::decode_exact(&[0x02, 0x02, 0x00, 0x80], der).unwrap(),
+            0x80
+        );
+        assert_eq!(i32::decode_exact(&[0x02, 0x01, 0x80], der).unwrap(), -0x80);
+    }
+
+    #[test]
+    fn non_minimal_length() {
+        let der = Der::new();
+
+        let cases: [&[u8]; 2] = [&[0x04, 0x81, 0x01, 0x00], &[0x04, 0x82, 0x00, 0x80]];
+        for bytes in cases {
+            let error = OctetString::decode(bytes, der).unwrap_err();
+            assert!(matches!(
+                error,
+                Error::InvalidValue {
+                    reason: "non-minimal length",
+                    ..
+                }
+            ));
+        }
+
+        let error = OctetString::decode(&[0x04, 0x80, 0x00, 0x00][..], der).unwrap_err();
+        assert!(matches!(
+            error,
+            Error::InvalidValue {
+                reason: "indefinite length",
+                ..
+            }
+        ));
+
+        let buf = OctetString(vec![0; 0x80]).encode_to_vec(der).unwrap();
+        assert_eq!(buf[..3], [0x04, 0x81, 0x80]);
+    }
+
+    #[test]
+    fn encoded_len() {
+        let der = Der::new();
+
+        let value = Sequence((
+            Explicit::<_, 0>(vec![vec![1u8; 0x40]; 4]),
+            SetOf(vec![OctetString(vec![2; 0x100]), OctetString(vec![1])]),
+        ));
+
+        let buf = value.encode_to_vec(der).unwrap();
+        assert_eq!(value.encoded_len(der).unwrap(), buf.len());
+        assert_eq!(
+            value.0 .0.encoded_len(der).unwrap(),
+            4 + 4 + 4 * (3 + 0x40 * 3)
+        );
+        assert!(Explicit::<u8, 31>(0).encoded_len(der).is_err());
+    }
+
+    #[derive(Debug)]
+    struct Node(Vec<Node>);
+
+    impl Decoder<Der> for Node {
+        type Error = Error;
+
+        fn decode(mut reader: impl Read, params: Der) -> Result<Self, Error> {
+            Vec::decode(&mut reader as &mut dyn Read, params).map(Self)
+        }
+    }
+
+    fn too_deep<T: core::fmt::Debug>(result: Result<T, Error>) {
+        match result {
+            Err(Error::LimitExceeded { limit, .. }) => assert_eq!(limit, "nesting depth"),
+            result => panic!("unexpected {:?}", result),
+        }
+    }
+
+    /// Returns `depth` nested empty SEQUENCEs.
+    fn nest(depth: usize) -> Vec<u8> {
+        let mut buf = Vec::new();
+        for _ in 0..depth {
+            let mut outer = Vec::new();
+            header(&mut outer, SEQUENCE, buf.len()).unwrap();
+            outer.extend(buf);
+            buf = outer;
+        }
+
+        buf
+    }
+
+    #[test]
+    fn nesting_depth() {
+        let der = Der::new();
+
+        // Lengths are only checked against the remaining contents once read.
+        let buf = [0x30, 0x84, 0x7f, 0xff, 0xff, 0xff].repeat(200_000);
+        too_deep(Node::decode_exact(&buf, der));
+
+        assert_eq!(Node::decode_exact(&nest(128), der).unwrap().0.len(), 1);
+        too_deep(Node::decode_exact(&nest(129), der));
+
+        let shallow = der.with_limits(Limits::default().depth(1));
+        assert!(Sequence::<u8>::decode_exact(&[0x30, 0x03, 0x02, 0x01, 0x01], shallow).is_ok());
+        too_deep(Sequence::<Vec<u8>>::decode_exact(
+            &[0x30, 0x02, 0x30, 0x00],
+            shallow,
+        ));
+        too_deep(Explicit::<Vec<u8>, 0>::decode_exact(
+            &[0xa0, 0x02, 0x30, 0x00],
+            shallow,
+        ));
+        too_deep(SetOf::<Vec<u8>>::decode_exact(
+            &[0x31, 0x02, 0x30, 0x00],
+            shallow,
+        ));
+    }
+
+    static ENCODES: core::sync::atomic::AtomicUsize = core::sync::atomic::AtomicUsize::new(0);
+
+    /// A NULL which counts how often it is encoded.
+    struct Counted;
+
+    impl Encoder<Der> for Counted {
+        type Error = Error;
+
+        fn encode(&self, writer: impl Write, params: Der) -> Result<(), Error> {
+            ENCODES.fetch_add(1, core::sync::atomic::Ordering::Relaxed);
+            Null.encode(writer, params)
+        }
+
+        fn encoded_len(&self, _: Der) -> Result<usize, Error> {
+            Ok(2)
+        }
+    }
+
+    /// Wraps a value in a SEQUENCE holding a tuple, once per token.
+    macro_rules! nest {
+        ($value:expr;) => { $value };
+        ($value:expr; $_:tt $($rest:tt)*) => { nest!(Sequence(($value, [0u8])); $($rest)*) };
+    }
+
+    #[test]
+    fn linear_encoded_len() {
+        let value = nest!(Counted; 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20);
+        let buf = value.encode_to_vec(Der::new()).unwrap();
+        assert_eq!(value.encoded_len(Der::new()).unwrap(), buf.len());
+        assert_eq!(buf.len(), 2 + 20 * 5);
+        assert_eq!(ENCODES.load(core::sync::atomic::Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,185 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Fixed-width endian encodings for primitive types.
+//!
+//! Integers and floats are encoded as their in-memory representation in the
+//! chosen byte order. A `bool` is encoded as a single byte which must be
+//! either `0` or `1`.
+//!
+//! ```rust
+//! use codicon::*;
+//!
+//! let mut buf = Vec::new();
+//! 0x1234u16.encode(&mut buf, Le).unwrap();
+//! 0x1234u16.encode(&mut buf, Be).unwrap();
+//! true.encode(&mut buf, Ne).unwrap();
+//! assert_eq!(buf, [0x34, 0x12, 0x12, 0x34, 0x01]);
+//!
+//! let mut reader = buf.as_slice();
+//! assert_eq!(u16::decode(&mut reader, Le).unwrap(), 0x1234);
+//! assert_eq!(i16::decode(&mut reader, Be).unwrap(), 0x1234);
+//! assert_eq!(bool::decode(&mut reader, Ne).unwrap(), true);
+//! assert!(bool::decode(&mut [2u8].as_ref(), Ne).is_err());
+//! ```
+
+use crate::io::{Read, Write};
+use crate::{ConstEncodedLen, Decoder, Encoder, Error};
+
+/// Little-endian encoding parameters.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Le;
+
+/// Big-endian encoding parameters.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Be;
+
+/// Native-endian encoding parameters.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Ne;
+
+macro_rules! endian {
+    ($params:ident, $to:ident, $from:ident, $($t:ty)+) => {
+        $(
+            impl Encoder<$params> for $t {
+                type Error = Error;
+
+                #[inline]
+                fn encode(&self, mut writer: impl Write, _: $params) -> Result<(), Error> {
+                    Ok(writer.write_all(&self.$to())?)
+                }
+
+                #[inline]
+                fn encoded_len(&self, _: $params) -> Result<usize, Error> {
+                    Ok(<Self as ConstEncodedLen<$params>>::ENCODED_LEN)
+                }
+            }
+
+            impl ConstEncodedLen<$params> for $t {
+                const ENCODED_LEN: usize = core::mem::size_of::<$t>();
+            }
+
+            impl Decoder<$params> for $t {
+                type Error = Error;
+
+                #[inline]
+                fn decode(mut reader: impl Read, _: $params) -> Result<Self, Error> {
+                    let mut bytes = [0u8; core::mem::size_of::<$t>()];
+                    reader.read_exact(&mut bytes)?;
+                    Ok(<$t>::$from(bytes))
+                }
+            }
+        )+
+
+        impl Encoder<$params> for bool {
+            type Error = Error;
+
+            #[inline]
+            fn encode(&self, writer: impl Write, params: $params) -> Result<(), Error> {
+                u8::from(*self).encode(writer, params)
+            }
+
+            #[inline]
+            fn encoded_len(&self, _: $params) -> Result<usize, Error> {
+                Ok(<Self as ConstEncodedLen<$params>>::ENCODED_LEN)
+            }
+        }
+
+        impl ConstEncodedLen<$params> for bool {
+            const ENCODED_LEN: usize = 1;
+        }
+
+        impl Decoder<$params> for bool {
+            type Error = Error;
+
+            #[inline]
+            fn decode(reader: impl Read, params: $params) -> Result<Self, Error> {
+                match u8::decode(reader, params)? {
+                    0 => Ok(false),
+                    1 => Ok(true),
+                    _ => Err(Error::invalid("invalid bool")),
+                }
+            }
+        }
+    };
+}
+
+endian!(Le, to_le_bytes, from_le_bytes, u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64);
+endian!(Be, to_be_bytes, from_be_bytes, u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64);
+endian!(Ne, to_ne_bytes, from_ne_bytes, u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encodes `value` into `buf`, returning the bytes written.
+    fn encode<T: Encoder<P, Error = Error>, P>(value: T, params: P, buf: &mut [u8]) -> &[u8] {
+        let total = buf.len();
+        let mut writer = &mut buf[..];
+        value.encode(&mut writer, params).unwrap();
+        let len = total - writer.len();
+        &buf[..len]
+    }
+
+    macro_rules! round_trip {
+        ($($t:ident)+) => {
+            $(
+                for value in [$t::MIN, $t::MAX] {
+                    let mut buf = [0u8; 16];
+                    let le = encode(value, Le, &mut buf);
+                    assert_eq!(le, value.to_le_bytes());
+                    assert_eq!($t::decode(le, Le).unwrap(), value);
+
+                    let mut buf = [0u8; 16];
+                    let be = encode(value, Be, &mut buf);
+                    assert_eq!(be, value.to_be_bytes());
+                    assert_eq!($t::decode(be, Be).unwrap(), value);
+
+                    let mut buf = [0u8; 16];
+                    let ne = encode(value, Ne, &mut buf);
+                    assert_eq!($t::decode(ne, Ne).unwrap(), value);
+                }
+            )+
+        };
+    }
+
+    #[test]
+    fn limits() {
+        round_trip!(u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64);
+    }
+
+    #[test]
+    fn nan_bits() {
+        let nan = f64::from_bits(0x7ff8_0000_0000_0001);
+        let mut buf = [0u8; 8];
+        let be = encode(nan, Be, &mut buf);
+        assert_eq!(be, [0x7f, 0xf8, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(f64::decode(be, Be).unwrap().to_bits(), nan.to_bits());
+
+        let nan = f32::from_bits(0xffc0_0001);
+        let mut buf = [0u8; 4];
+        let le = encode(nan, Le, &mut buf);
+        assert_eq!(f32::decode(le, Le).unwrap().to_bits(), nan.to_bits());
+    }
+
+    #[test]
+    fn bools() {
+        assert!(!bool::decode(&[0u8][..], Le).unwrap());
+        assert!(bool::decode(&[1u8][..], Be).unwrap());
+
+        for byte in [2u8, 0xff] {
+            assert!(matches!(
+                bool::decode(&[byte][..], Ne),
+                Err(Error::InvalidValue {
+                    reason: "invalid bool",
+                    ..
+                })
+            ));
+        }
+    }
+
+    #[test]
+    fn short_input() {
+        assert!(u32::decode(&[1u8, 2, 3][..], Le).unwrap_err().is_eof());
+        assert!(bool::decode(&[][..], Be).unwrap_err().is_eof());
+    }
+}
@@ -0,0 +1,289 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! The common error type.
+
+use crate::io;
+
+#[cfg(feature = "alloc")]
+use alloc::{borrow::Cow, string::String};
+use core::fmt::{Display, Formatter};
+use core::ops::Deref;
+
+/// A common error type for encoders and decoders.
+///
+/// Every variant carries the byte offset in the stream at which the error
+/// occurred, if it is known. Code which knows the offset (for example, by
+/// reading through an `io::Tracked` reader) can attach it with `Error::at()`.
+///
+/// I/O errors convert into this type, with `io::ErrorKind::UnexpectedEof`
+/// becoming `Error::UnexpectedEof`. This type also converts back into an
+/// `io::Error`, so encoders and decoders using either error type compose.
+///
+/// ```rust
+/// use codicon::*;
+///
+/// let error = Error::invalid("bad magic").at(4);
+/// assert_eq!(error.offset(), Some(4));
+/// assert_eq!(error.to_string(), "invalid value at offset 4: bad magic");
+///
+/// let error: Error = u32::decode(&mut [0u8; 2].as_ref(), Le).unwrap_err();
+/// assert!(error.is_eof());
+/// ```
+#[non_exhaustive]
+#[derive(Debug)]
+pub enum Error {
+    /// The input ended before the value was complete.
+    UnexpectedEof {
+        /// The offset at which more input was required.
+        offset: Option<usize>,
+    },
+
+    /// The input contained, or the value to encode was, an invalid value.
+    InvalidValue {
+        /// The offset of the invalid value.
+        offset: Option<usize>,
+
+        /// Why the value was invalid.
+        reason: &'static str,
+    },
+
+    /// A limit, such as a maximum length, was exceeded.
+    LimitExceeded {
+        /// The offset at which the limit was exceeded.
+        offset: Option<usize>,
+
+        /// The limit which was exceeded.
+        limit: &'static str,
+    },
+
+    /// The underlying reader or writer failed.
+    Io {
+        /// The offset at which the failure occurred.
+        offset: Option<usize>,
+
+        /// The underlying error.
+        error: io::Error,
+    },
+
+    /// An error specific to an encoder or decoder.
+    Custom {
+        /// The offset at which the error occurred.
+        offset: Option<usize>,
+
+        /// A description of the error.
+        message: Message,
+    },
+}
+
+/// The description of an `Error::Custom`.
+///
+/// With the `alloc` feature, the description may be an owned `String`, such
+/// as one formatted by serde. Otherwise, it is a `&'static str`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Message(Text);
+
+#[cfg(feature = "alloc")]
+type Text = Cow<'static, str>;
+
+#[cfg(not(feature = "alloc"))]
+type Text = &'static str;
+
+impl Message {
+    /// Returns the description as a string slice.
+    pub fn as_str(&self) -> &str {
+        #[cfg(feature = "alloc")]
+        return &self.0;
+
+        #[cfg(not(feature = "alloc"))]
+        return self.0;
+    }
+
+    /// Returns the description if it is a `&'static str`.
+    #[cfg(not(feature = "std"))]
+    fn as_static(&self) -> Option<&'static str> {
+        #[cfg(feature = "alloc")]
+        return match self.0 {
+            Cow::Borrowed(text) => Some(text),
+            Cow::Owned(_) => None,
+        };
+
+        #[cfg(not(feature = "alloc"))]
+        return Some(self.0);
+    }
+}
+
+impl From<&'static str> for Message {
+    fn from(text: &'static str) -> Self {
+        #[cfg(feature = "alloc")]
+        return Self(Cow::Borrowed(text));
+
+        #[cfg(not(feature = "alloc"))]
+        return Self(text);
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<String> for Message {
+    fn from(text: String) -> Self {
+        Self(Cow::Owned(text))
+    }
+}
+
+impl Deref for Message {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Display for Message {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Error {
+    /// Creates an `Error::UnexpectedEof` with an unknown offset.
+    pub fn eof() -> Self {
+        Self::UnexpectedEof { offset: None }
+    }
+
+    /// Creates an `Error::InvalidValue` with an unknown offset.
+    pub fn invalid(reason: &'static str) -> Self {
+        Self::InvalidValue {
+            offset: None,
+            reason,
+        }
+    }
+
+    /// Creates an `Error::LimitExceeded` with an unknown offset.
+    pub fn limit(limit: &'static str) -> Self {
+        Self::LimitExceeded {
+            offset: None,
+            limit,
+        }
+    }
+
+    /// Creates an `Error::Custom` with an unknown offset.
+    ///
+    /// The message is a `&'static str` or, with the `alloc` feature, a
+    /// `String`.
+    pub fn custom(message: impl Into<Message>) -> Self {
+        Self::Custom {
+            offset: None,
+            message: message.into(),
+        }
+    }
+
+    /// Returns the offset at which the error occurred, if known.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            Self::UnexpectedEof { offset }
+            | Self::InvalidValue { offset, .. }
+            | Self::LimitExceeded { offset, .. }
+            | Self::Io { offset, .. }
+            | Self::Custom { offset, .. } => *offset,
+        }
+    }
+
+    /// Sets the offset at which the error occurred, unless already known.
+    pub fn at(mut self, at: usize) -> Self {
+        match &mut self {
+            Self::UnexpectedEof { offset }
+            | Self::InvalidValue { offset, .. }
+            | Self::LimitExceeded { offset, .. }
+            | Self::Io { offset, .. }
+            | Self::Custom { offset, .. } => {
+                offset.get_or_insert(at);
+            }
+        }
+
+        self
+    }
+
+    /// Returns whether the input ended before the value was complete.
+    pub fn is_eof(&self) -> bool {
+        matches!(self, Self::UnexpectedEof { .. })
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        #[cfg(feature = "std")]
+        if matches!(error.get_ref(), Some(e) if e.is::<Error>()) {
+            let inner = error.into_inner().and_then(|e| e.downcast().ok());
+            return *inner.unwrap();
+        }
+
+        match error.kind() {
+            io::ErrorKind::UnexpectedEof => Self::eof(),
+            _ => Self::Io {
+                offset: None,
+                error,
+            },
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(error: Error) -> Self {
+        let kind = match error {
+            Error::Io { error, .. } => return error,
+            Error::UnexpectedEof { .. } => io::ErrorKind::UnexpectedEof,
+            Error::InvalidValue { .. } | Error::LimitExceeded { .. } => io::ErrorKind::InvalidData,
+            Error::Custom { .. } => io::ErrorKind::Other,
+        };
+
+        #[cfg(feature = "std")]
+        return io::Error::new(kind, error);
+
+        #[cfg(not(feature = "std"))]
+        return io::Error::new(
+            kind,
+            match error {
+                Error::InvalidValue { reason, .. } => reason,
+                Error::LimitExceeded { limit, .. } => limit,
+                Error::Custom { message, .. } => message.as_static().unwrap_or("error"),
+                _ => "unexpected end of input",
+            },
+        );
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            Self::UnexpectedEof { .. } => "unexpected end of input",
+            Self::InvalidValue { .. } => "invalid value",
+            Self::LimitExceeded { .. } => "limit exceeded",
+            Self::Io { .. } => "I/O error",
+            Self::Custom { .. } => "error",
+        })?;
+
+        if let Some(offset) = self.offset() {
+            write!(f, " at offset {}", offset)?;
+        }
+
+        match self {
+            Self::UnexpectedEof { .. } => Ok(()),
+            Self::InvalidValue { reason, .. } => write!(f, ": {}", reason),
+            Self::LimitExceeded { limit, .. } => write!(f, ": {}", limit),
+            Self::Io { error, .. } => write!(f, ": {}", error),
+            Self::Custom { message, .. } => write!(f, ": {}", message),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(all(feature = "serde", not(feature = "std")))]
+impl serde::de::StdError for Error {}
@@ -0,0 +1,116 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::io::Tracked;
+use crate::{Decoder, Encoder, Error};
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "bytes")]
+use crate::buf::{Reader, Writer};
+#[cfg(feature = "bytes")]
+use bytes::{Buf, BufMut};
+
+/// Convenience methods for every `Encoder`.
+///
+/// ```rust
+/// use codicon::*;
+///
+/// assert_eq!(0x1234u16.encode_to_vec(Be).unwrap(), [0x12, 0x34]);
+///
+/// let mut buf = [0u8; 4];
+/// assert_eq!(0x1234u16.encode_into_slice(&mut buf, Le).unwrap(), 2);
+/// assert_eq!(buf, [0x34, 0x12, 0x00, 0x00]);
+/// assert!(0x1234u32.encode_into_slice(&mut buf[..2], Le).is_err());
+/// ```
+pub trait EncoderExt<T>: Encoder<T> {
+    /// Encodes into a new vector.
+    #[cfg(feature = "alloc")]
+    fn encode_to_vec(&self, params: T) -> Result<Vec<u8>, Self::Error> {
+        let mut buf = Vec::new();
+        self.encode(&mut buf, params)?;
+        Ok(buf)
+    }
+
+    /// Encodes into the start of a slice, returning the number of bytes written.
+    ///
+    /// Fails if the slice is too small to hold the encoded value.
+    fn encode_into_slice(&self, buf: &mut [u8], params: T) -> Result<usize, Self::Error> {
+        let len = buf.len();
+        let mut writer = buf;
+        self.encode(&mut writer, params)?;
+        Ok(len - writer.len())
+    }
+
+    /// Encodes into a `bytes::BufMut`.
+    ///
+    /// Fails if the buffer runs out of space.
+    #[cfg(feature = "bytes")]
+    fn encode_to_buf(&self, buf: impl BufMut, params: T) -> Result<(), Self::Error> {
+        self.encode(Writer(buf), params)
+    }
+}
+
+impl<T, E: Encoder<T> + ?Sized> EncoderExt<T> for E {}
+
+/// Convenience methods for every `Decoder`.
+///
+/// Errors returned by these methods carry the offset within the slice at
+/// which decoding stopped, unless the decoder already provided one.
+///
+/// ```rust
+/// use codicon::*;
+///
+/// let buf = [0x34, 0x12, 0xff];
+/// assert_eq!(u16::decode_from_slice(&buf, Le).unwrap(), (0x1234, 2));
+/// assert_eq!(u16::decode_exact(&buf[..2], Le).unwrap(), 0x1234);
+///
+/// let error = u16::decode_exact(&buf, Le).unwrap_err();
+/// assert_eq!(error.offset(), Some(2));
+/// ```
+pub trait DecoderExt<T>: Decoder<T> {
+    /// Decodes from the start of a slice.
+    ///
+    /// Returns the value and the number of bytes consumed.
+    fn decode_from_slice(buf: &[u8], params: T) -> Result<(Self, usize), Error>
+    where
+        Self::Error: Into<Error>,
+    {
+        let mut reader = Tracked::new(buf);
+        let result = Self::decode(&mut reader, params);
+        let consumed = reader.offset();
+
+        match result {
+            Ok(value) => Ok((value, consumed)),
+            Err(e) => Err(e.into().at(consumed)),
+        }
+    }
+
+    /// Decodes from a slice which must contain exactly one encoded value.
+    ///
+    /// Fails with `Error::InvalidValue` if any bytes remain after decoding.
+    fn decode_exact(buf: &[u8], params: T) -> Result<Self, Error>
+    where
+        Self::Error: Into<Error>,
+    {
+        match Self::decode_from_slice(buf, params)? {
+            (value, consumed) if consumed == buf.len() => Ok(value),
+            (_, consumed) => Err(Error::invalid("trailing bytes").at(consumed)),
+        }
+    }
+
+    /// Decodes from a `bytes::Buf`, advancing it past the decoded bytes.
+    ///
+    /// If decoding fails, the bytes read before the failure remain consumed.
+    #[cfg(feature = "bytes")]
+    fn decode_from_buf(buf: impl Buf, params: T) -> Result<Self, Error>
+    where
+        Self::Error: Into<Error>,
+    {
+        let mut reader = Tracked::new(Reader(buf));
+        let result = Self::decode(&mut reader, params);
+        result.map_err(|e| e.into().at(reader.offset()))
+    }
+}
+
+impl<T, D: Decoder<T>> DecoderExt<T> for D {}
@@ -0,0 +1,233 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Length-delimited framing.
+//!
+//! `Framed` parameters encode any value as its encoded length followed by
+//! the encoding itself. Decoding reads exactly the framed bytes: the value
+//! must consume the whole frame and nothing beyond it, otherwise decoding
+//! fails with `Error::InvalidValue`.
+//!
+//! ```rust
+//! use codicon::*;
+//!
+//! let framed = Framed::new(Uleb128, Len::<u16, _>::new(Be));
+//! let buf = 300u32.encode_to_vec(framed).unwrap();
+//! assert_eq!(buf, [0x00, 0x02, 0xac, 0x02]);
+//! assert_eq!(u32::decode_exact(&buf, framed).unwrap(), 300);
+//!
+//! // The frame claims three bytes but the value only uses two.
+//! let error = u32::decode_exact(&[0x00, 0x03, 0xac, 0x02, 0x00], framed).unwrap_err();
+//! assert!(matches!(error, Error::InvalidValue { .. }));
+//!
+//! // The frame claims one byte but the value needs two.
+//! let error = u32::decode_exact(&[0x00, 0x01, 0xac, 0x02], framed).unwrap_err();
+//! assert!(matches!(error, Error::InvalidValue { .. }));
+//! ```
+
+use crate::io::{self, BaseRead, Read, Tracked, Write};
+use crate::limits::Limits;
+use crate::{Decoder, Encoder, Error};
+
+/// Parameters for values prefixed with their encoded length in bytes.
+///
+/// The value is encoded with the parameters `P` and its length is encoded
+/// as a `usize` with the parameters `L`. Decoding checks the length against
+/// the total bytes limit.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Framed<P, L> {
+    params: P,
+    len: L,
+    limits: Limits,
+}
+
+impl<P, L> Framed<P, L> {
+    /// Creates parameters with value parameters `params` and length parameters `len`.
+    pub const fn new(params: P, len: L) -> Self {
+        Self {
+            params,
+            len,
+            limits: Limits::DEFAULT,
+        }
+    }
+
+    /// Sets the limits checked when decoding.
+    pub fn with_limits(self, limits: Limits) -> Self {
+        Self { limits, ..self }
+    }
+
+    /// Returns the limits checked when decoding.
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+}
+
+// The bounds are expressed on the parameters, through `EncodeFrame` and
+// `DecodeFrame`, rather than as `T: Encoder<P>` with `T::Error: Into<Error>`.
+// Otherwise, resolving any `T: Encoder<_>` with unknown parameters recurses
+// through these impls without end.
+impl<T: ?Sized, P: EncodeFrame<T> + Clone, L: EncodeFrame<usize>> Encoder<Framed<P, L>> for T {
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, params: Framed<P, L>) -> Result<(), Error> {
+        let len = params.params.clone().encoded_len(self)?;
+        params.len.encode(&len, &mut writer)?;
+
+        let mut writer = Tracked::new(writer);
+        params.params.encode(self, &mut writer)?;
+
+        match writer.offset() == len {
+            true => Ok(()),
+            false => Err(Error::invalid("encoded length changed")),
+        }
+    }
+}
+
+impl<T, P: DecodeFrame<T>, L: DecodeFrame<usize>> Decoder<Framed<P, L>> for T {
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: Framed<P, L>) -> Result<Self, Error> {
+        let len = params.len.decode(&mut reader)?;
+        params.limits.check_bytes(len)?;
+
+        let mut frame = Frame {
+            reader,
+            remaining: len,
+        };
+
+        let value = match params.params.decode(&mut frame) {
+            Err(e) if e.is_eof() && frame.remaining == 0 => {
+                return Err(Error::invalid("value overruns frame"))
+            }
+            result => result?,
+        };
+
+        match frame.remaining {
+            0 => Ok(value),
+            _ => Err(Error::invalid("value underruns frame")),
+        }
+    }
+}
+
+mod sealed {
+    use super::*;
+
+    /// Parameters with which `T` encodes and whose errors convert into `Error`.
+    pub trait EncodeFrame<T: ?Sized> {
+        fn encoded_len(self, value: &T) -> Result<usize, Error>;
+        fn encode(self, value: &T, writer: impl Write) -> Result<(), Error>;
+    }
+
+    impl<T: Encoder<P> + ?Sized, P> EncodeFrame<T> for P
+    where
+        T::Error: Into<Error>,
+    {
+        fn encoded_len(self, value: &T) -> Result<usize, Error> {
+            value.encoded_len(self).map_err(Into::into)
+        }
+
+        fn encode(self, value: &T, writer: impl Write) -> Result<(), Error> {
+            value.encode(writer, self).map_err(Into::into)
+        }
+    }
+
+    /// Parameters with which `T` decodes and whose errors convert into `Error`.
+    pub trait DecodeFrame<T> {
+        fn decode(self, reader: impl Read) -> Result<T, Error>;
+    }
+
+    impl<T: Decoder<P>, P> DecodeFrame<T> for P
+    where
+        T::Error: Into<Error>,
+    {
+        fn decode(self, reader: impl Read) -> Result<T, Error> {
+            T::decode(reader, self).map_err(Into::into)
+        }
+    }
+}
+
+pub(crate) use sealed::{DecodeFrame, EncodeFrame};
+
+/// A reader which ends after the remaining bytes of a frame.
+pub(crate) struct Frame<R> {
+    pub(crate) reader: R,
+    pub(crate) remaining: usize,
+}
+
+impl<R: Read> BaseRead for Frame<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let max = core::cmp::min(buf.len(), self.remaining);
+        let n = self.reader.read(&mut buf[..max])?;
+        self.remaining -= n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Framed;
+    use crate::{Be, Decoder, DecoderExt, Encoder, Error, Le, Len, Limits, Uleb128};
+
+    fn reason<T: core::fmt::Debug>(result: Result<T, Error>) -> &'static str {
+        match result {
+            Err(Error::InvalidValue { reason, .. }) => reason,
+            result => panic!("expected invalid value, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let framed = Framed::new(Le, Uleb128);
+        let mut buf = [0u8; 8];
+        let mut writer = &mut buf[..];
+        0x0102_0304u32.encode(&mut writer, framed).unwrap();
+        assert_eq!(writer.len(), 3);
+        assert_eq!(buf[..5], [4, 4, 3, 2, 1]);
+
+        let buf = [4u8, 4, 3, 2, 1, 0xff];
+        let (value, len) = u32::decode_from_slice(&buf, framed).unwrap();
+        assert_eq!((value, len), (0x0102_0304, 5));
+    }
+
+    #[test]
+    fn underrun() {
+        let framed = Framed::new(Uleb128, Len::<u8, _>::new(Be));
+        let result = u32::decode(&[3u8, 0xac, 0x02, 0x00][..], framed);
+        assert_eq!(reason(result), "value underruns frame");
+
+        let framed = Framed::new(Le, Uleb128);
+        let result = u16::decode(&[3u8, 1, 2, 3][..], framed);
+        assert_eq!(reason(result), "value underruns frame");
+    }
+
+    #[test]
+    fn overrun() {
+        let framed = Framed::new(Uleb128, Len::<u8, _>::new(Be));
+        let result = u32::decode(&[1u8, 0xac, 0x02][..], framed);
+        assert_eq!(reason(result), "value overruns frame");
+
+        let framed = Framed::new(Le, Uleb128);
+        let result = u32::decode(&[0u8, 1, 2, 3, 4][..], framed);
+        assert_eq!(reason(result), "value overruns frame");
+    }
+
+    #[test]
+    fn short_input() {
+        // The input ends inside the frame: this is not the value's fault.
+        let framed = Framed::new(Le, Uleb128);
+        assert!(u32::decode(&[4u8, 1, 2][..], framed).unwrap_err().is_eof());
+        assert!(u32::decode(&[][..], framed).unwrap_err().is_eof());
+    }
+
+    #[test]
+    fn limits() {
+        let framed = Framed::new(Le, Uleb128).with_limits(Limits::NONE.bytes(3));
+        assert!(matches!(
+            u32::decode(&[4u8, 1, 2, 3, 4][..], framed),
+            Err(Error::LimitExceeded {
+                limit: "total bytes",
+                ..
+            })
+        ));
+        assert_eq!(u16::decode(&[2u8, 1, 0][..], framed).unwrap(), 1);
+    }
+}
@@ -0,0 +1,365 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Asynchronous encoding and decoding.
+//!
+//! The `AsyncEncoder` and `AsyncDecoder` traits mirror `Encoder` and
+//! `Decoder` over `futures_io::AsyncWrite` and `futures_io::AsyncRead`.
+//! Types which only implement the synchronous traits can still be used
+//! asynchronously with the `Buffered` parameters, which encode into memory
+//! first and then flush the bytes to the writer, or read bytes into memory
+//! as the decoder asks for them.
+//!
+//! This module requires Rust 1.75.
+//!
+//! ```rust
+//! use codicon::*;
+//! use codicon::future::*;
+//! use std::future::Future;
+//! use std::sync::Arc;
+//! use std::task::{Context, Poll, Wake, Waker};
+//!
+//! struct Noop;
+//!
+//! impl Wake for Noop {
+//!     fn wake(self: Arc<Self>) {}
+//! }
+//!
+//! struct Foo;
+//!
+//! impl Encoder<Foo> for u8 {
+//!     type Error = std::io::Error;
+//!
+//!     fn encode(&self, mut writer: impl Write, params: Foo) -> std::io::Result<()> {
+//!         writer.write_all(std::slice::from_ref(self))
+//!     }
+//! }
+//!
+//! let waker = Waker::from(Arc::new(Noop));
+//! let mut cx = Context::from_waker(&waker);
+//!
+//! let mut buf = Vec::new();
+//! let mut fut = Box::pin(7u8.encode_async(&mut buf, Buffered(Foo)));
+//! assert!(matches!(fut.as_mut().poll(&mut cx), Poll::Ready(Ok(()))));
+//! drop(fut);
+//! assert_eq!(buf, [7u8]);
+//!
+//! let mut reader = &[0x12, 0x34, 0x56][..];
+//! let mut fut = Box::pin(u16::decode_async(&mut reader, Buffered(Be)));
+//! assert!(matches!(fut.as_mut().poll(&mut cx), Poll::Ready(Ok(0x1234))));
+//! drop(fut);
+//! assert_eq!(reader, [0x56]);
+//! ```
+//!
+//! With the `tokio` feature, the `Tokio` wrapper adapts `tokio::io` readers
+//! and writers for use with these traits.
+
+use crate::io;
+use crate::stream::Partial;
+use crate::{Decoder, Encoder};
+
+use core::future::{poll_fn, Future};
+use core::pin::Pin;
+#[cfg(feature = "tokio")]
+use core::task::{Context, Poll};
+
+use futures_io::{AsyncRead, AsyncWrite};
+
+/// Trait used to express asynchronous encoding relationships.
+pub trait AsyncEncoder<T> {
+    type Error;
+
+    /// Encodes to the asynchronous writer with the given parameters.
+    fn encode_async<W: AsyncWrite + Unpin>(
+        &self,
+        writer: W,
+        params: T,
+    ) -> impl Future<Output = Result<(), Self::Error>>;
+}
+
+/// Trait used to express asynchronous decoding relationships.
+pub trait AsyncDecoder<T>: Sized {
+    type Error;
+
+    /// Decodes from the asynchronous reader with the given parameters.
+    fn decode_async<R: AsyncRead + Unpin>(
+        reader: R,
+        params: T,
+    ) -> impl Future<Output = Result<Self, Self::Error>>;
+}
+
+/// Writes all of `buf` into the asynchronous writer.
+pub async fn write_all<W: AsyncWrite + Unpin + ?Sized>(
+    writer: &mut W,
+    mut buf: &[u8],
+) -> io::Result<()> {
+    while !buf.is_empty() {
+        match poll_fn(|cx| Pin::new(&mut *writer).poll_write(cx, buf)).await {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(n) => buf = &buf[n..],
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Flushes the asynchronous writer.
+pub async fn flush<W: AsyncWrite + Unpin + ?Sized>(writer: &mut W) -> io::Result<()> {
+    poll_fn(|cx| Pin::new(&mut *writer).poll_flush(cx)).await
+}
+
+/// Reads exactly enough bytes from the asynchronous reader to fill `buf`.
+pub async fn read_exact<R: AsyncRead + Unpin + ?Sized>(
+    reader: &mut R,
+    mut buf: &mut [u8],
+) -> io::Result<()> {
+    while !buf.is_empty() {
+        match poll_fn(|cx| Pin::new(&mut *reader).poll_read(cx, buf)).await {
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => buf = &mut buf[n..],
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Parameters which encode or decode with `P` in memory.
+///
+/// Encoding writes the whole value into memory and then writes it to the
+/// asynchronous writer. Decoding runs the decoder over the bytes read so
+/// far; whenever it asks for more, exactly the bytes it asked for are read
+/// and decoding starts again from the beginning. As with `Incremental`, no
+/// attempt is made until all of those bytes have arrived, however many reads
+/// that takes. No bytes past the end of the value are read, but a value the
+/// decoder reads in `n` pieces is still decoded `n` times, so prefer framed
+/// or length-prefixed values where the cost matters.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Buffered<P>(pub P);
+
+impl<T: Encoder<P>, P> AsyncEncoder<Buffered<P>> for T
+where
+    T::Error: From<io::Error>,
+{
+    type Error = T::Error;
+
+    fn encode_async<W: AsyncWrite + Unpin>(
+        &self,
+        mut writer: W,
+        params: Buffered<P>,
+    ) -> impl Future<Output = Result<(), Self::Error>> {
+        let mut buf = Vec::new();
+        let encoded = self.encode(&mut buf, params.0);
+
+        async move {
+            encoded?;
+            write_all(&mut writer, &buf).await?;
+            flush(&mut writer).await?;
+            Ok(())
+        }
+    }
+}
+
+impl<T: Decoder<P>, P: Clone> AsyncDecoder<Buffered<P>> for T
+where
+    T::Error: From<io::Error>,
+{
+    type Error = T::Error;
+
+    async fn decode_async<R: AsyncRead + Unpin>(
+        mut reader: R,
+        params: Buffered<P>,
+    ) -> Result<Self, Self::Error> {
+        let mut buf = Vec::new();
+        let mut needed = 0;
+        let mut eof = false;
+
+        loop {
+            if buf.len() >= needed || eof {
+                let mut partial = Partial {
+                    buf: &buf,
+                    wanted: 0,
+                };
+
+                // Even a successful decode may have stopped at the end of the
+                // bytes read so far, so only the real end of input is final.
+                let result = T::decode(&mut partial, params.0.clone());
+                if partial.wanted == 0 || eof {
+                    return result;
+                }
+
+                needed = buf.len() + partial.wanted;
+            }
+
+            let len = buf.len();
+            buf.resize(needed, 0);
+            loop {
+                match poll_fn(|cx| Pin::new(&mut reader).poll_read(cx, &mut buf[len..])).await {
+                    Ok(n) => {
+                        eof = n == 0;
+                        buf.truncate(len + n);
+                        break;
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+    }
+}
+
+/// Adapts a `tokio::io` reader or writer to `futures_io`.
+#[cfg(feature = "tokio")]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Tokio<T>(pub T);
+
+#[cfg(feature = "tokio")]
+impl<T: tokio::io::AsyncRead + Unpin> AsyncRead for Tokio<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut buf = tokio::io::ReadBuf::new(buf);
+        match Pin::new(&mut self.0).poll_read(cx, &mut buf) {
+            Poll::Ready(Ok(())) => Poll::Ready(Ok(buf.filled().len())),
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T: tokio::io::AsyncWrite + Unpin> AsyncWrite for Tokio<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use super::*;
+    use crate::{Be, Error, Prefixed, Read, Uleb128};
+
+    use core::cell::Cell;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+
+    struct Noop;
+
+    impl Wake for Noop {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    fn block_on<F: Future>(fut: F) -> F::Output {
+        let waker = Waker::from(Arc::new(Noop));
+        let mut cx = Context::from_waker(&waker);
+        let mut fut = core::pin::pin!(fut);
+
+        loop {
+            if let Poll::Ready(output) = fut.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    /// Parameters counting the decoding attempts of a `u64`.
+    #[derive(Clone)]
+    struct Counting<'a>(&'a Cell<usize>);
+
+    impl Decoder<Counting<'_>> for u64 {
+        type Error = Error;
+
+        fn decode(reader: impl Read, params: Counting<'_>) -> Result<Self, Error> {
+            params.0.set(params.0.get() + 1);
+            u64::decode(reader, Be)
+        }
+    }
+
+    /// A `tokio` reader which is pending before every read and then
+    /// returns at most one byte.
+    struct Trickle<'a> {
+        buf: &'a [u8],
+        ready: bool,
+    }
+
+    impl<'a> Trickle<'a> {
+        fn new(buf: &'a [u8]) -> Self {
+            Self { buf, ready: false }
+        }
+    }
+
+    impl tokio::io::AsyncRead for Trickle<'_> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut tokio::io::ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            if !core::mem::replace(&mut self.ready, false) {
+                self.ready = true;
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+
+            if let Some((byte, rest)) = self.buf.split_first() {
+                buf.put_slice(&[*byte]);
+                self.buf = rest;
+            }
+
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn short_reads() {
+        let attempts = Cell::new(0);
+        let bytes = [1, 2, 3, 4, 5, 6, 7, 8, 9];
+        let mut reader = Tokio(Trickle::new(&bytes));
+
+        let value = block_on(u64::decode_async(
+            &mut reader,
+            Buffered(Counting(&attempts)),
+        ));
+        assert_eq!(value.unwrap(), 0x0102030405060708);
+        assert_eq!(attempts.get(), 2);
+        assert_eq!(reader.0.buf, [9]);
+
+        let bytes = [3, 0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc];
+        let mut reader = Tokio(Trickle::new(&bytes));
+        let params = Buffered(Prefixed::new(Uleb128, Be));
+
+        let value = block_on(Vec::<u16>::decode_async(&mut reader, params));
+        assert_eq!(value.unwrap(), [0x1234, 0x5678, 0x9abc]);
+        assert!(reader.0.buf.is_empty());
+    }
+
+    #[test]
+    fn eof_mid_value() {
+        let bytes = [1, 2, 3];
+        let mut reader = Tokio(Trickle::new(&bytes));
+
+        let result = block_on(u64::decode_async(&mut reader, Buffered(Be)));
+        assert!(result.unwrap_err().is_eof());
+
+        let bytes = [3, 0x12, 0x34, 0x56];
+        let mut reader = Tokio(&bytes[..]);
+        let params = Buffered(Prefixed::new(Uleb128, Be));
+
+        let result = block_on(Vec::<u16>::decode_async(&mut reader, params));
+        assert!(result.unwrap_err().is_eof());
+    }
+}
@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Minimal big-endian two's complement integers, as used by DER and SSH.
+
+use core::convert::TryFrom;
+
+/// The most bytes in the minimal two's complement of a 128-bit integer.
+pub(crate) const MAX_LEN: usize = 17;
+
+/// Whether the first of `bytes` only repeats the sign of the next.
+pub(crate) fn redundant(bytes: &[u8]) -> bool {
+    match bytes {
+        [0x00, next, ..] => *next < 0x80,
+        [0xff, next, ..] => *next >= 0x80,
+        _ => false,
+    }
+}
+
+/// Returns the two's complement of `value`, sign-extended to `MAX_LEN`
+/// bytes, and the start of its minimal form, which keeps at least one byte.
+pub(crate) fn encode<T: Copy>(value: T) -> Option<([u8; MAX_LEN], usize)>
+where
+    u128: TryFrom<T>,
+    i128: TryFrom<T>,
+{
+    let mut buf = [0u8; MAX_LEN];
+    match u128::try_from(value) {
+        Ok(value) => buf[1..].copy_from_slice(&value.to_be_bytes()),
+        Err(_) => {
+            let value = i128::try_from(value).ok()?;
+            buf[0] = 0xff;
+            buf[1..].copy_from_slice(&value.to_be_bytes());
+        }
+    }
+
+    let mut start = 0;
+    while redundant(&buf[start..]) {
+        start += 1;
+    }
+
+    Some((buf, start))
+}
+
+/// Decodes the two's complement in `bytes`, which is zero if empty.
+///
+/// Returns `None` if the value does not fit in `T`.
+pub(crate) fn decode<T: TryFrom<u128> + TryFrom<i128>>(bytes: &[u8]) -> Option<T> {
+    let mut buf = [0u8; MAX_LEN];
+    let start = MAX_LEN.checked_sub(bytes.len())?;
+    buf[start..].copy_from_slice(bytes);
+
+    let negative = matches!(bytes.first(), Some(0x80..=0xff));
+    if negative {
+        buf[..start].iter_mut().for_each(|b| *b = 0xff);
+    }
+
+    let tail = <[u8; 16]>::try_from(&buf[1..]).ok()?;
+    match (negative, buf[0]) {
+        (false, 0x00) => T::try_from(u128::from_be_bytes(tail)).ok(),
+        (true, 0xff) if start > 0 => T::try_from(i128::from_be_bytes(tail)).ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minimal<T: Copy>(value: T) -> alloc::vec::Vec<u8>
+    where
+        u128: TryFrom<T>,
+        i128: TryFrom<T>,
+    {
+        let (buf, start) = encode(value).unwrap();
+        buf[start..].to_vec()
+    }
+
+    #[test]
+    fn round_trip() {
+        assert_eq!(minimal(0u8), [0x00]);
+        assert_eq!(minimal(0x80u8), [0x00, 0x80]);
+        assert_eq!(minimal(-1i8), [0xff]);
+        assert_eq!(minimal(-129i16), [0xff, 0x7f]);
+        assert_eq!(minimal(u128::MAX).len(), MAX_LEN);
+        assert_eq!(minimal(i128::MIN).len(), 16);
+
+        assert_eq!(decode::<u128>(&minimal(u128::MAX)), Some(u128::MAX));
+        assert_eq!(decode::<i128>(&minimal(i128::MIN)), Some(i128::MIN));
+        assert_eq!(decode::<i16>(&[0xff, 0x7f]), Some(-129));
+        assert_eq!(decode::<u32>(&[]), Some(0));
+    }
+
+    #[test]
+    fn out_of_range() {
+        assert_eq!(decode::<u8>(&[0x01, 0x00]), None);
+        assert_eq!(decode::<u8>(&[0xff]), None);
+        assert_eq!(decode::<i128>(&minimal(u128::MAX)), None);
+        assert_eq!(decode::<u128>(&[0x01; MAX_LEN]), None);
+        assert_eq!(decode::<u128>(&[0x00; MAX_LEN + 1]), None);
+    }
+}
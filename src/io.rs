@@ -0,0 +1,420 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Minimal I/O traits used by the encoding and decoding traits.
+//!
+//! When the `std` feature is enabled, `Read` and `Write` are implemented for
+//! every `std::io::Read` and `std::io::Write` type and `Error`, `ErrorKind`
+//! and `Result` are the `std::io` types. Without it, this module provides
+//! its own small equivalents, implemented for `&[u8]`, `&mut [u8]` and (with
+//! the `alloc` feature) `Vec<u8>`.
+
+#[cfg(feature = "std")]
+pub use std::io::{Error, ErrorKind, Result};
+
+// The traits implemented by this crate's own readers and writers.
+#[cfg(feature = "std")]
+pub(crate) use std::io::{Read as BaseRead, Write as BaseWrite};
+
+#[cfg(not(feature = "std"))]
+pub(crate) use self::{Read as BaseRead, Write as BaseWrite};
+
+/// A source of bytes.
+#[cfg(feature = "std")]
+pub trait Read: std::io::Read {}
+
+#[cfg(feature = "std")]
+impl<T: std::io::Read + ?Sized> Read for T {}
+
+/// A sink for bytes.
+#[cfg(feature = "std")]
+pub trait Write: std::io::Write {}
+
+#[cfg(feature = "std")]
+impl<T: std::io::Write + ?Sized> Write for T {}
+
+/// A list specifying general categories of I/O error.
+#[cfg(not(feature = "std"))]
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// The data was not valid for the operation.
+    InvalidData,
+
+    /// A parameter was incorrect.
+    InvalidInput,
+
+    /// The operation was interrupted and can be retried.
+    Interrupted,
+
+    /// The operation needs to block to complete.
+    WouldBlock,
+
+    /// A write returned `Ok(0)`.
+    WriteZero,
+
+    /// A read reached "end of file" prematurely.
+    UnexpectedEof,
+
+    /// Any other error.
+    Other,
+}
+
+/// The error type for I/O operations.
+#[cfg(not(feature = "std"))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Error {
+    kind: ErrorKind,
+    msg: &'static str,
+}
+
+#[cfg(not(feature = "std"))]
+impl Error {
+    /// Creates a new error from a kind and a message.
+    pub fn new(kind: ErrorKind, msg: &'static str) -> Self {
+        Self { kind, msg }
+    }
+
+    /// Returns the kind of this error.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Self { kind, msg: "" }
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.msg {
+            "" => write!(f, "{:?}", self.kind),
+            msg => f.write_str(msg),
+        }
+    }
+}
+
+/// A specialized `Result` type for I/O operations.
+#[cfg(not(feature = "std"))]
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// A source of bytes.
+#[cfg(not(feature = "std"))]
+pub trait Read {
+    /// Pulls some bytes from this source into `buf`, returning how many.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+    /// Reads exactly enough bytes to fill `buf`.
+    fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.read(buf) {
+                Ok(0) => break,
+                Ok(n) => buf = &mut buf[n..],
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        if buf.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "failed to fill whole buffer",
+            ))
+        }
+    }
+}
+
+/// A sink for bytes.
+#[cfg(not(feature = "std"))]
+pub trait Write {
+    /// Writes some bytes from `buf` into this sink, returning how many.
+    fn write(&mut self, buf: &[u8]) -> Result<usize>;
+
+    /// Flushes any buffered bytes.
+    fn flush(&mut self) -> Result<()>;
+
+    /// Writes all of `buf` into this sink.
+    fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.write(buf) {
+                Ok(0) => {
+                    return Err(Error::new(
+                        ErrorKind::WriteZero,
+                        "failed to write whole buffer",
+                    ))
+                }
+                Ok(n) => buf = &buf[n..],
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<R: Read + ?Sized> Read for &mut R {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        (**self).read(buf)
+    }
+
+    #[inline]
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        (**self).read_exact(buf)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Read for &[u8] {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let n = core::cmp::min(buf.len(), self.len());
+        let (head, tail) = self.split_at(n);
+        buf[..n].copy_from_slice(head);
+        *self = tail;
+        Ok(n)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<W: Write + ?Sized> Write for &mut W {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        (**self).write(buf)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> Result<()> {
+        (**self).flush()
+    }
+
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        (**self).write_all(buf)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Write for &mut [u8] {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let n = core::cmp::min(buf.len(), self.len());
+        let (head, tail) = core::mem::take(self).split_at_mut(n);
+        head.copy_from_slice(&buf[..n]);
+        *self = tail;
+        Ok(n)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+impl Write for alloc::vec::Vec<u8> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// A reader or writer which tracks the current offset in the stream.
+///
+/// ```rust
+/// use codicon::*;
+/// use codicon::io::Tracked;
+///
+/// let mut reader = Tracked::new([1u8, 0, 0, 0, 2].as_ref());
+/// assert_eq!(u32::decode(&mut reader, Le).unwrap(), 1);
+/// assert_eq!(reader.offset(), 4);
+///
+/// let error: Error = u32::decode(&mut reader, Le).unwrap_err();
+/// assert_eq!(error.at(reader.offset()).offset(), Some(5));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Tracked<T> {
+    inner: T,
+    offset: usize,
+}
+
+impl<T> Tracked<T> {
+    /// Wraps a reader or writer, starting at offset zero.
+    pub fn new(inner: T) -> Self {
+        Self { inner, offset: 0 }
+    }
+
+    /// Returns the number of bytes read or written so far.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Unwraps the inner reader or writer.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<R: Read> BaseRead for Tracked<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.offset += n;
+        Ok(n)
+    }
+}
+
+impl<W: Write> BaseWrite for Tracked<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.offset += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A writer which discards all bytes, counting how many were written.
+///
+/// ```rust
+/// use codicon::*;
+/// use codicon::io::Counter;
+///
+/// let mut counter = Counter::default();
+/// 300u32.encode(&mut counter, Uleb128).unwrap();
+/// assert_eq!(counter.count(), 2);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Counter(usize);
+
+impl Counter {
+    /// Returns the number of bytes written so far.
+    pub fn count(&self) -> usize {
+        self.0
+    }
+}
+
+impl BaseWrite for Counter {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// A reader which yields one byte before the rest of its inner reader.
+#[cfg(feature = "alloc")]
+pub(crate) struct Prepend<R> {
+    byte: Option<u8>,
+    reader: R,
+}
+
+#[cfg(feature = "alloc")]
+impl<R> Prepend<R> {
+    pub(crate) fn new(byte: u8, reader: R) -> Self {
+        Self {
+            byte: Some(byte),
+            reader,
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<R: Read> BaseRead for Prepend<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        match (self.byte, buf.first_mut()) {
+            (Some(byte), Some(first)) => {
+                *first = byte;
+                self.byte = None;
+                Ok(1)
+            }
+            _ => self.reader.read(buf),
+        }
+    }
+}
+
+/// A reader which keeps a copy of the bytes read.
+#[cfg(feature = "alloc")]
+pub(crate) struct Record<R> {
+    pub(crate) reader: R,
+    pub(crate) bytes: alloc::vec::Vec<u8>,
+}
+
+#[cfg(feature = "alloc")]
+impl<R: Read> BaseRead for Record<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let n = self.reader.read(buf)?;
+        self.bytes.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Reads a single byte.
+#[inline]
+pub(crate) fn byte(mut reader: impl Read) -> Result<u8> {
+    let mut byte = 0u8;
+    reader.read_exact(core::slice::from_mut(&mut byte))?;
+    Ok(byte)
+}
+
+/// The most bytes allocated ahead of reading them.
+#[cfg(feature = "alloc")]
+const CHUNK: usize = 4096;
+
+/// Reads `len` bytes onto the end of `buf`.
+///
+/// Don't trust `len` with a large allocation: the buffer grows as the bytes
+/// arrive, so a length larger than the input fails with an error before
+/// allocating much more than the input. Callers check their limits first.
+#[cfg(feature = "alloc")]
+pub(crate) fn read_onto(
+    mut reader: impl Read,
+    buf: &mut alloc::vec::Vec<u8>,
+    len: usize,
+) -> Result<()> {
+    let end = buf.len().saturating_add(len);
+    while buf.len() < end {
+        let start = buf.len();
+        buf.resize(start + core::cmp::min(end - start, CHUNK), 0);
+        reader.read_exact(&mut buf[start..])?;
+    }
+
+    Ok(())
+}
+
+/// Reads `len` bytes into a new vector, as by `read_onto()`.
+#[cfg(feature = "alloc")]
+pub(crate) fn read_vec(reader: impl Read, len: usize) -> Result<alloc::vec::Vec<u8>> {
+    let mut bytes = alloc::vec::Vec::with_capacity(core::cmp::min(len, CHUNK));
+    read_onto(reader, &mut bytes, len)?;
+    Ok(bytes)
+}
+
+/// Creates a vector for `len` items which are yet to be read.
+///
+/// Don't trust `len` with a large allocation before reading the items: the
+/// vector starts with room for at most `CHUNK` bytes of them.
+#[cfg(feature = "alloc")]
+pub(crate) fn with_capacity<T>(len: usize) -> alloc::vec::Vec<T> {
+    let cap = CHUNK / core::cmp::max(core::mem::size_of::<T>(), 1);
+    alloc::vec::Vec::with_capacity(core::cmp::min(len, cap))
+}
@@ -0,0 +1,256 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Variable-length integer encodings.
+//!
+//! `Uleb128` encodes unsigned integers and `Sleb128` encodes signed integers
+//! in the LEB128 format: seven bits per byte, least significant group first,
+//! with the high bit of each byte set if more bytes follow. `ZigZag` maps
+//! signed integers to unsigned ones so that values of small magnitude encode
+//! to few bytes (as in Protocol Buffers) and then encodes them as `Uleb128`.
+//!
+//! Decoding fails with `Error::InvalidValue` if the input has more bytes than
+//! the target type can require or encodes a value which does not fit in it.
+//!
+//! ```rust
+//! use codicon::*;
+//!
+//! let mut buf = Vec::new();
+//! 624485u32.encode(&mut buf, Uleb128).unwrap();
+//! (-123456i64).encode(&mut buf, Sleb128).unwrap();
+//! (-2i32).encode(&mut buf, ZigZag).unwrap();
+//! assert_eq!(buf, [0xe5, 0x8e, 0x26, 0xc0, 0xbb, 0x78, 0x03]);
+//!
+//! let mut reader = buf.as_slice();
+//! assert_eq!(u32::decode(&mut reader, Uleb128).unwrap(), 624485);
+//! assert_eq!(i64::decode(&mut reader, Sleb128).unwrap(), -123456);
+//! assert_eq!(i32::decode(&mut reader, ZigZag).unwrap(), -2);
+//!
+//! let too_big = [0x80, 0x02];
+//! assert!(matches!(u8::decode(&mut too_big.as_ref(), Uleb128), Err(Error::InvalidValue { .. })));
+//! ```
+
+use crate::io::{byte, Read, Write};
+use crate::{Decoder, Encoder, Error};
+
+/// Unsigned LEB128 encoding parameters.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Uleb128;
+
+/// Signed LEB128 encoding parameters.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Sleb128;
+
+/// Zigzag-mapped unsigned LEB128 encoding parameters.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ZigZag;
+
+macro_rules! leb128 {
+    ($($u:ident $i:ident)+) => {
+        $(
+            impl Encoder<Uleb128> for $u {
+                type Error = Error;
+
+                fn encode(&self, mut writer: impl Write, _: Uleb128) -> Result<(), Error> {
+                    let mut value = *self;
+
+                    loop {
+                        let byte = (value & 0x7f) as u8;
+                        value >>= 7;
+
+                        if value == 0 {
+                            return Ok(writer.write_all(&[byte])?);
+                        }
+
+                        writer.write_all(&[byte | 0x80])?;
+                    }
+                }
+            }
+
+            impl Decoder<Uleb128> for $u {
+                type Error = Error;
+
+                fn decode(mut reader: impl Read, _: Uleb128) -> Result<Self, Error> {
+                    let mut value: $u = 0;
+                    let mut shift = 0;
+
+                    loop {
+                        let byte = byte(&mut reader)?;
+                        let low = byte & 0x7f;
+
+                        if shift >= $u::BITS || ($u::BITS - shift < 7 && low >> ($u::BITS - shift) != 0) {
+                            return Err(Error::invalid("LEB128 overflow"));
+                        }
+
+                        value |= (low as $u) << shift;
+                        shift += 7;
+
+                        if byte & 0x80 == 0 {
+                            return Ok(value);
+                        }
+                    }
+                }
+            }
+
+            impl Encoder<Sleb128> for $i {
+                type Error = Error;
+
+                fn encode(&self, mut writer: impl Write, _: Sleb128) -> Result<(), Error> {
+                    let mut value = *self;
+
+                    loop {
+                        let byte = (value & 0x7f) as u8;
+                        value >>= 7;
+
+                        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+                            return Ok(writer.write_all(&[byte])?);
+                        }
+
+                        writer.write_all(&[byte | 0x80])?;
+                    }
+                }
+            }
+
+            impl Decoder<Sleb128> for $i {
+                type Error = Error;
+
+                fn decode(mut reader: impl Read, _: Sleb128) -> Result<Self, Error> {
+                    let mut value: $u = 0;
+                    let mut shift = 0;
+
+                    loop {
+                        let byte = byte(&mut reader)?;
+                        let low = byte & 0x7f;
+
+                        if shift >= $u::BITS {
+                            return Err(Error::invalid("LEB128 overflow"));
+                        }
+
+                        // The bits beyond the target type must all match its sign bit.
+                        let remaining = $u::BITS - shift;
+                        if remaining < 7 {
+                            let extra = low >> (remaining - 1);
+                            if extra != 0 && extra != 0x7f >> (remaining - 1) {
+                                return Err(Error::invalid("LEB128 overflow"));
+                            }
+                        }
+
+                        value |= (low as $u) << shift;
+                        shift += 7;
+
+                        if byte & 0x80 == 0 {
+                            if shift < $u::BITS && low & 0x40 != 0 {
+                                value |= !0 << shift;
+                            }
+
+                            return Ok(value as $i);
+                        }
+                    }
+                }
+            }
+
+            impl Encoder<ZigZag> for $i {
+                type Error = Error;
+
+                #[inline]
+                fn encode(&self, writer: impl Write, _: ZigZag) -> Result<(), Error> {
+                    let value = ((*self << 1) ^ (*self >> ($i::BITS - 1))) as $u;
+                    value.encode(writer, Uleb128)
+                }
+            }
+
+            impl Decoder<ZigZag> for $i {
+                type Error = Error;
+
+                #[inline]
+                fn decode(reader: impl Read, _: ZigZag) -> Result<Self, Error> {
+                    let value = $u::decode(reader, Uleb128)?;
+                    Ok((value >> 1) as $i ^ -((value & 1) as $i))
+                }
+            }
+        )+
+    };
+}
+
+leb128!(u8 i8 u16 i16 u32 i32 u64 i64 u128 i128 usize isize);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DecoderExt;
+
+    fn overflow<T: Decoder<P, Error = Error> + core::fmt::Debug, P>(bytes: &[u8], params: P) {
+        match T::decode_exact(bytes, params) {
+            Err(Error::InvalidValue { offset, reason }) => {
+                assert_eq!(reason, "LEB128 overflow");
+                assert_eq!(offset, Some(bytes.len()));
+            }
+            result => panic!("{:02x?} decoded as {:?}", bytes, result),
+        }
+    }
+
+    #[test]
+    fn u64_tenth_byte() {
+        let mut max = [0xff; 10];
+        max[9] = 0x01;
+        assert_eq!(u64::decode_exact(&max, Uleb128).unwrap(), u64::MAX);
+
+        let mut bytes = max;
+        bytes[9] = 0x02;
+        overflow::<u64, _>(&bytes, Uleb128);
+
+        overflow::<u64, _>(&[&[0x80; 10][..], &[0x00]].concat(), Uleb128);
+    }
+
+    #[test]
+    fn i64_tenth_byte() {
+        let mut min = [0x80; 10];
+        min[9] = 0x7f;
+        assert_eq!(i64::decode_exact(&min, Sleb128).unwrap(), i64::MIN);
+
+        let mut max = [0xff; 10];
+        max[9] = 0x00;
+        assert_eq!(i64::decode_exact(&max, Sleb128).unwrap(), i64::MAX);
+
+        let mut bytes = min;
+        bytes[9] = 0x01;
+        overflow::<i64, _>(&bytes, Sleb128);
+
+        bytes[9] = 0x7e;
+        overflow::<i64, _>(&bytes, Sleb128);
+    }
+
+    #[test]
+    fn u128_nineteenth_byte() {
+        let mut max = [0xff; 19];
+        max[18] = 0x03;
+        assert_eq!(u128::decode_exact(&max, Uleb128).unwrap(), u128::MAX);
+
+        let mut bytes = max;
+        bytes[18] = 0x04;
+        overflow::<u128, _>(&bytes, Uleb128);
+
+        bytes[18] = 0x83;
+        overflow::<u128, _>(&[&bytes[..], &[0x00]].concat(), Uleb128);
+    }
+
+    #[test]
+    fn i128_nineteenth_byte() {
+        let mut min = [0x80; 19];
+        min[18] = 0x7e;
+        assert_eq!(i128::decode_exact(&min, Sleb128).unwrap(), i128::MIN);
+
+        let mut bytes = min;
+        bytes[18] = 0x02;
+        overflow::<i128, _>(&bytes, Sleb128);
+    }
+
+    #[test]
+    fn zigzag() {
+        let mut max = [0xff; 10];
+        max[9] = 0x01;
+        assert_eq!(i64::decode_exact(&max, ZigZag).unwrap(), i64::MIN);
+
+        max[9] = 0x02;
+        overflow::<i64, _>(&max, ZigZag);
+    }
+}
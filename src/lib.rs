@@ -3,7 +3,7 @@
 //! Traits for encoding and decoding.
 //!
 //! This crate provides generic traits for encoding and decoding to a
-//! `std::io::Read` or `std::io::Write` type, respectively.
+//! `Read` or `Write` type, respectively.
 //!
 //! We often need to express that a type can be encoded or decoded. We
 //! also need a way to express the type of the encoding or decoding as
@@ -59,8 +59,166 @@
 //! let buf = [7u8; 1];
 //! assert_eq!(u8::decode(&mut buf.as_ref(), Foo).unwrap(), 7u8);
 //! ```
+//!
+//! This crate also provides parameters for some common encodings of the
+//! primitive types. For example, the `Le`, `Be` and `Ne` parameters encode
+//! integers, floats and `bool` with a fixed width in the given byte order,
+//! while the `Uleb128`, `Sleb128` and `ZigZag` parameters encode integers
+//! with a variable width:
+//!
+//! ```rust
+//! use codicon::*;
+//!
+//! assert_eq!(0x1234u16.encode_to_vec(Be).unwrap(), [0x12, 0x34]);
+//! assert_eq!(0x1234u16.encode_to_vec(Uleb128).unwrap(), [0xb4, 0x24]);
+//! assert_eq!(u16::decode_exact(&[0xb4, 0x24], Uleb128).unwrap(), 0x1234);
+//! ```
+//!
+//! Arrays, tuples, `Box`, `Vec` and `Option` of encodable types are also
+//! encodable, as are byte and text strings; see the `container` module for
+//! details. Byte and text strings can also be decoded without copying using
+//! the `BorrowDecoder` trait. Any value can be prefixed with the length of
+//! its encoding using `Framed`. When decoding untrusted input, use `Limits` to
+//! bound the resources a decoder may consume. To decode from non-blocking
+//! sources or encode to non-blocking sinks without losing partial progress,
+//! use `Incremental` and `EncodeBuffer`.
+//!
+//! Complete data formats are provided too, with the `alloc` feature: the
+//! `Cbor` parameters encode values as CBOR (RFC 8949), optionally in its
+//! deterministic form, the `MsgPack` parameters as MessagePack, the `Der`
+//! parameters as ASN.1 DER, the `Protobuf` parameters as Protocol Buffers,
+//! the `Ssh` parameters as the SSH data types (RFC 4251), the `Tls`
+//! parameters as the TLS presentation language, the `Xdr` parameters as XDR
+//! (RFC 4506) and the `Bencode` parameters as Bencode.
+//!
+//! These built-in encodings report failures using the common `Error` type,
+//! which distinguishes the end of the input, invalid values, exceeded limits
+//! and I/O errors. Your own encodings may use it too.
+//!
+//! # Features
+//!
+//! The `std` feature (enabled by default) makes every `std::io::Read` and
+//! `std::io::Write` type usable with this crate. Without it, this crate is
+//! `no_std` and provides its own minimal `Read` and `Write` traits, which are
+//! implemented for `&[u8]` and `&mut [u8]`. The `alloc` feature additionally
+//! implements `Write` for `Vec<u8>`.
+//!
+//! The `futures` feature adds the `AsyncEncoder` and `AsyncDecoder` traits
+//! over `futures_io`, and the `tokio` feature adapts `tokio::io` types to them.
+//! Both require Rust 1.75, while the rest of the crate supports Rust 1.63.
+//! The `bytes` feature encodes into `bytes::BufMut` and decodes from
+//! `bytes::Buf` directly, and decodes byte strings from `bytes::Bytes`
+//! without copying. The `tokio-util` feature adds `CodiconCodec`, which implements the
+//! `tokio_util::codec` traits for any encodable and decodable type.
+//!
+//! The `serde` feature encodes and decodes serde types using any serde data
+//! format under the `Serde` parameters, and provides a serde data format built
+//! on this crate's encodings.
+//!
+//! The `derive` feature provides `#[derive(Encoder, Decoder)]` macros, which
+//! encode and decode the fields of a type in order.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+mod ext;
+
+#[cfg(feature = "alloc")]
+mod int;
+
+pub mod borrow;
+pub mod container;
+pub mod endian;
+pub mod error;
+pub mod framed;
+pub mod io;
+pub mod leb128;
+pub mod limits;
+
+#[cfg(feature = "alloc")]
+pub mod bencode;
+
+#[cfg(feature = "alloc")]
+pub mod cbor;
+
+#[cfg(feature = "alloc")]
+pub mod der;
+
+#[cfg(feature = "alloc")]
+pub mod msgpack;
 
-pub use std::io::{Read, Write};
+#[cfg(feature = "alloc")]
+pub mod protobuf;
+
+#[cfg(feature = "alloc")]
+pub mod ssh;
+
+#[cfg(feature = "alloc")]
+pub mod stream;
+
+#[cfg(feature = "alloc")]
+pub mod tls;
+
+#[cfg(feature = "alloc")]
+pub mod xdr;
+
+#[cfg(feature = "futures")]
+#[clippy::msrv = "1.75"]
+pub mod future;
+
+#[cfg(feature = "tokio-util")]
+pub mod codec;
+
+#[cfg(feature = "bytes")]
+pub mod buf;
+
+#[cfg(feature = "serde")]
+pub mod serdes;
+
+pub use borrow::{BorrowDecoder, Owned};
+pub use container::{ByteStr, Concat, Len, Optional, Prefixed};
+pub use endian::{Be, Le, Ne};
+pub use error::Error;
+pub use ext::{DecoderExt, EncoderExt};
+pub use framed::Framed;
+pub use io::{Read, Write};
+pub use leb128::{Sleb128, Uleb128, ZigZag};
+pub use limits::Limits;
+
+#[cfg(feature = "alloc")]
+pub use bencode::Bencode;
+
+#[cfg(feature = "alloc")]
+pub use cbor::Cbor;
+
+#[cfg(feature = "alloc")]
+pub use der::Der;
+
+#[cfg(feature = "alloc")]
+pub use msgpack::MsgPack;
+
+#[cfg(feature = "alloc")]
+pub use protobuf::Protobuf;
+
+#[cfg(feature = "alloc")]
+pub use ssh::Ssh;
+
+#[cfg(feature = "alloc")]
+pub use stream::{EncodeBuffer, Incremental};
+
+#[cfg(feature = "alloc")]
+pub use tls::Tls;
+
+#[cfg(feature = "alloc")]
+pub use xdr::Xdr;
+
+#[cfg(feature = "serde")]
+pub use serdes::Serde;
+
+#[cfg(feature = "derive")]
+pub use codicon_derive::{Decoder, Encoder};
 
 /// Trait used to express encoding relationships.
 pub trait Encoder<T> {
@@ -68,6 +226,30 @@ pub trait Encoder<T> {
 
     /// Encodes to the writer with the given parameters.
     fn encode(&self, writer: impl Write, params: T) -> Result<(), Self::Error>;
+
+    /// Returns the number of bytes `encode()` writes with the given parameters.
+    ///
+    /// By default, this encodes to an `io::Counter`. Implementations should
+    /// override it if the length can be computed more cheaply.
+    fn encoded_len(&self, params: T) -> Result<usize, Self::Error> {
+        let mut counter = io::Counter::default();
+        self.encode(&mut counter, params)?;
+        Ok(counter.count())
+    }
+}
+
+/// Trait used to express encodings which always have the same length.
+///
+/// ```rust
+/// use codicon::*;
+///
+/// const LEN: usize = <([u16; 3], bool) as ConstEncodedLen<Le>>::ENCODED_LEN;
+/// assert_eq!(LEN, 7);
+/// assert_eq!(([1u16, 2, 3], true).encoded_len(Le).unwrap(), LEN);
+/// ```
+pub trait ConstEncodedLen<T>: Encoder<T> {
+    /// The number of bytes every encoding writes.
+    const ENCODED_LEN: usize;
 }
 
 /// Trait used to express decoding relationships.
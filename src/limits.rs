@@ -0,0 +1,239 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Resource limits for decoding untrusted input.
+//!
+//! A malicious length prefix can otherwise make a decoder allocate far more
+//! memory than the input could ever fill. `Limits` bounds:
+//!
+//!   * the total number of bytes read, enforced by wrapping the reader with
+//!     `Limits::reader()`;
+//!   * the size in bytes of any single allocation;
+//!   * the number of elements in any collection;
+//!   * the nesting depth of self-describing formats, whose nesting is not
+//!     fixed by the type being decoded.
+//!
+//! Parameters which decode collections, such as `Prefixed` and `ByteStr`,
+//! carry `Limits` and check them before allocating. Exceeding a limit fails
+//! with `Error::LimitExceeded`.
+//!
+//! Each level of nesting takes stack space while decoding, so a deeply
+//! nested input could overflow the stack before any allocation limit is
+//! reached. `Limits::DEFAULT`, which `Limits::default()` and the parameters
+//! of every format start from, therefore limits the nesting depth to 128
+//! and nothing else. `Limits::NONE` lifts even that limit, for trusted
+//! input only.
+//!
+//! ```rust
+//! use codicon::*;
+//!
+//! let limits = Limits::default().len(2);
+//! let params = Prefixed::new(Uleb128, Le).with_limits(limits);
+//!
+//! let buf = [3u8, 1, 2, 3];
+//! let error = Vec::<u8>::decode_exact(&buf, params).unwrap_err();
+//! assert!(matches!(error, Error::LimitExceeded { .. }));
+//!
+//! let limits = Limits::default().bytes(3);
+//! let error: Error = u32::decode(limits.reader(&buf[..]), Le).unwrap_err();
+//! assert!(matches!(error, Error::LimitExceeded { .. }));
+//! ```
+
+use crate::io::{self, BaseRead, Read};
+use crate::Error;
+
+/// Resource limits for decoding.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Limits {
+    bytes: usize,
+    alloc: usize,
+    len: usize,
+    depth: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Limits {
+    /// Limits which only bound the nesting depth, to 128 levels.
+    pub const DEFAULT: Self = Self {
+        depth: 128,
+        ..Self::NONE
+    };
+
+    /// Limits which allow everything, including unbounded nesting.
+    pub const NONE: Self = Self {
+        bytes: usize::MAX,
+        alloc: usize::MAX,
+        len: usize::MAX,
+        depth: usize::MAX,
+    };
+
+    /// Limits the total number of bytes read.
+    pub const fn bytes(self, bytes: usize) -> Self {
+        Self { bytes, ..self }
+    }
+
+    /// Limits the size in bytes of any single allocation.
+    pub const fn alloc(self, alloc: usize) -> Self {
+        Self { alloc, ..self }
+    }
+
+    /// Limits the number of elements in any collection.
+    pub const fn len(self, len: usize) -> Self {
+        Self { len, ..self }
+    }
+
+    /// Limits the nesting depth of self-describing formats.
+    pub const fn depth(self, depth: usize) -> Self {
+        Self { depth, ..self }
+    }
+
+    /// Fails unless reading `bytes` bytes in total is allowed.
+    pub fn check_bytes(&self, bytes: usize) -> Result<(), Error> {
+        match bytes <= self.bytes {
+            true => Ok(()),
+            false => Err(Error::limit("total bytes")),
+        }
+    }
+
+    /// Returns how many more bytes may be read after `bytes` bytes.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn remaining_bytes(&self, bytes: usize) -> usize {
+        self.bytes.saturating_sub(bytes)
+    }
+
+    /// Fails unless a collection of `len` elements is allowed.
+    pub fn check_len(&self, len: usize) -> Result<(), Error> {
+        match len <= self.len {
+            true => Ok(()),
+            false => Err(Error::limit("collection length")),
+        }
+    }
+
+    /// Fails unless `count` values of type `T` may be allocated at once.
+    pub fn check_alloc<T>(&self, count: usize) -> Result<(), Error> {
+        match count.checked_mul(core::mem::size_of::<T>()) {
+            Some(size) if size <= self.alloc => Ok(()),
+            _ => Err(Error::limit("allocation size")),
+        }
+    }
+
+    /// Returns the limits for a nested value, failing if too deeply nested.
+    pub fn nested(self) -> Result<Self, Error> {
+        match self.depth.checked_sub(1) {
+            Some(depth) => Ok(Self { depth, ..self }),
+            None => Err(Error::limit("nesting depth")),
+        }
+    }
+
+    /// Wraps a reader so that reading more than the byte limit fails.
+    pub fn reader<R: Read>(&self, reader: R) -> Limited<R> {
+        Limited {
+            reader,
+            remaining: self.bytes,
+        }
+    }
+}
+
+/// A reader which fails once more than a limited number of bytes are read.
+///
+/// Created by `Limits::reader()`.
+#[derive(Clone, Debug)]
+pub struct Limited<R> {
+    reader: R,
+    remaining: usize,
+}
+
+impl<R> Limited<R> {
+    /// Returns the number of bytes which may still be read.
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    /// Unwraps the inner reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read> BaseRead for Limited<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.remaining == 0 {
+            return Err(Error::limit("total bytes").into());
+        }
+
+        let max = core::cmp::min(buf.len(), self.remaining);
+        let n = self.reader.read(&mut buf[..max])?;
+        self.remaining -= n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exceeded<T: core::fmt::Debug>(result: Result<T, Error>, expected: &str) {
+        match result {
+            Err(Error::LimitExceeded { limit, .. }) => assert_eq!(limit, expected),
+            result => panic!("expected {} limit, got {:?}", expected, result),
+        }
+    }
+
+    #[test]
+    fn checks() {
+        let limits = Limits::NONE.bytes(4).len(2).alloc(8);
+
+        assert!(limits.check_bytes(4).is_ok());
+        exceeded(limits.check_bytes(5), "total bytes");
+
+        assert!(limits.check_len(2).is_ok());
+        exceeded(limits.check_len(3), "collection length");
+
+        assert!(limits.check_alloc::<u32>(2).is_ok());
+        exceeded(limits.check_alloc::<u32>(3), "allocation size");
+        exceeded(
+            Limits::NONE.check_alloc::<u64>(usize::MAX),
+            "allocation size",
+        );
+        assert!(Limits::NONE.alloc(0).check_alloc::<()>(usize::MAX).is_ok());
+    }
+
+    #[test]
+    fn nested() {
+        let limits = Limits::NONE.depth(2);
+        let limits = limits.nested().unwrap().nested().unwrap();
+        exceeded(limits.nested(), "nesting depth");
+        assert!(Limits::NONE.nested().is_ok());
+
+        let mut limits = Limits::default();
+        assert_eq!(limits, Limits::DEFAULT);
+        for _ in 0..128 {
+            limits = limits.nested().unwrap();
+        }
+        exceeded(limits.nested(), "nesting depth");
+    }
+
+    #[test]
+    fn reader() {
+        let mut reader = Limits::NONE.bytes(3).reader(&[1u8, 2, 3, 4][..]);
+
+        let mut buf = [0u8; 2];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(reader.remaining(), 1);
+
+        let error = reader.read_exact(&mut buf).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        #[cfg(feature = "std")]
+        exceeded(Err::<(), _>(error.into()), "total bytes");
+        assert_eq!(reader.remaining(), 0);
+        assert_eq!(reader.into_inner(), [4]);
+    }
+}
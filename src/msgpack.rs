@@ -0,0 +1,848 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! The MessagePack format.
+//!
+//! Under the `MsgPack` parameters, integers, floating point numbers, `bool`,
+//! strings, `Vec` (as arrays), `BTreeMap` (as maps) and `Option` (as `nil`
+//! or the value) encode as the corresponding MessagePack types. Integers
+//! always use their smallest representation. Byte strings use the `Bin`
+//! parameters instead, since `Vec<u8>` is an array of integers under
+//! `MsgPack`. Arrays and tuples encode as arrays too. `Ext` values are
+//! application-defined extension types.
+//!
+//! Each `Vec`, `BTreeMap`, `Ext` and present `Option` value takes a level
+//! of the depth limit of the parameters' `Limits`, which bounds the
+//! recursion of recursive types.
+//!
+//! Types using the derive macros encode their fields as an array by default,
+//! or as a map from field names to values with `Structs::Map`. Decoding an
+//! array expects the fields in declaration order; decoding a map accepts
+//! them in any order and skips the values of unknown keys.
+//!
+//! ```rust
+//! use codicon::*;
+//! use codicon::msgpack::{Bin, Ext};
+//!
+//! let msgpack = MsgPack::new();
+//! assert_eq!(7u64.encode_to_vec(msgpack).unwrap(), [0x07]);
+//! assert_eq!(300u64.encode_to_vec(msgpack).unwrap(), [0xcd, 0x01, 0x2c]);
+//! assert_eq!((-33i64).encode_to_vec(msgpack).unwrap(), [0xd0, 0xdf]);
+//! assert_eq!("hi".encode_to_vec(msgpack).unwrap(), [0xa2, b'h', b'i']);
+//! assert_eq!([1u8, 2][..].encode_to_vec(Bin(msgpack)).unwrap(), [0xc4, 2, 1, 2]);
+//! assert_eq!(vec![Some(1u8), None].encode_to_vec(msgpack).unwrap(), [0x92, 0x01, 0xc0]);
+//! assert_eq!((1u8, [2u8, 3]).encode_to_vec(msgpack).unwrap(), [0x92, 0x01, 0x92, 0x02, 0x03]);
+//!
+//! let ext = Ext { kind: 5, data: vec![1, 2] };
+//! assert_eq!(ext.encode_to_vec(msgpack).unwrap(), [0xd5, 5, 1, 2]);
+//! ```
+//!
+//! With the `derive` feature:
+//!
+//! ```rust
+//! # #[cfg(feature = "derive")]
+//! # fn main() {
+//! use codicon::*;
+//! use codicon::msgpack::Structs;
+//!
+//! #[derive(Encoder, Decoder, Debug, PartialEq)]
+//! struct Point {
+//!     x: u8,
+//!     y: i8,
+//! }
+//!
+//! let msgpack = MsgPack::new();
+//! let point = Point { x: 1, y: -1 };
+//! let buf = point.encode_to_vec(msgpack).unwrap();
+//! assert_eq!(buf, [0x92, 0x01, 0xff]);
+//! assert_eq!(Point::decode_exact(&buf, msgpack).unwrap(), point);
+//!
+//! let msgpack = msgpack.structs(Structs::Map);
+//! let buf = point.encode_to_vec(msgpack).unwrap();
+//! assert_eq!(buf, [0x82, 0xa1, b'x', 0x01, 0xa1, b'y', 0xff]);
+//! assert_eq!(Point::decode_exact(&buf, msgpack).unwrap(), point);
+//! # }
+//! # #[cfg(not(feature = "derive"))]
+//! # fn main() {}
+//! ```
+
+use crate::container::Concat;
+use crate::io::{self, byte, Prepend, Read, Write};
+use crate::limits::Limits;
+use crate::{Decoder, Encoder, Error};
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+const NIL: u8 = 0xc0;
+const FALSE: u8 = 0xc2;
+const TRUE: u8 = 0xc3;
+const FLOAT32: u8 = 0xca;
+const FLOAT64: u8 = 0xcb;
+
+/// How types using the derive macros encode their fields.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Structs {
+    /// An array of the field values.
+    #[default]
+    Array,
+
+    /// A map from the field names to the field values.
+    Map,
+}
+
+/// MessagePack encoding parameters.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MsgPack {
+    structs: Structs,
+    limits: Limits,
+}
+
+impl MsgPack {
+    /// Creates parameters which encode derived types as arrays.
+    pub const fn new() -> Self {
+        Self {
+            structs: Structs::Array,
+            limits: Limits::DEFAULT,
+        }
+    }
+
+    /// Sets how types using the derive macros encode their fields.
+    pub const fn structs(self, structs: Structs) -> Self {
+        Self { structs, ..self }
+    }
+
+    /// Sets the limits checked when decoding.
+    pub fn with_limits(self, limits: Limits) -> Self {
+        Self { limits, ..self }
+    }
+
+    /// Returns the limits checked when decoding.
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+}
+
+impl<T: ?Sized> Concat<T> for MsgPack {
+    fn encode_fields(&self, writer: impl Write, fields: &[&'static str]) -> Result<(), Error> {
+        match self.structs {
+            Structs::Array => header(writer, Kind::Array, fields.len()),
+            Structs::Map => header(writer, Kind::Map, fields.len()),
+        }
+    }
+
+    fn encode_field(&self, writer: impl Write, field: &'static str) -> Result<(), Error> {
+        match self.structs {
+            Structs::Array => Ok(()),
+            Structs::Map => field.encode(writer, *self),
+        }
+    }
+
+    fn decode_fields(
+        &self,
+        mut reader: impl Read,
+        fields: &[&'static str],
+    ) -> Result<usize, Error> {
+        let initial = byte(&mut reader)?;
+        match self.structs {
+            Structs::Map => len(reader, initial, Kind::Map),
+            Structs::Array => match len(reader, initial, Kind::Array)? == fields.len() {
+                true => Ok(fields.len()),
+                false => Err(Error::invalid("wrong number of fields")),
+            },
+        }
+    }
+
+    fn decode_field(
+        &self,
+        mut reader: impl Read,
+        fields: &[&'static str],
+        index: usize,
+    ) -> Result<Option<usize>, Error> {
+        if self.structs == Structs::Array {
+            return Ok(Some(index));
+        }
+
+        // Only read keys as long as some field name into memory.
+        let initial = byte(&mut reader)?;
+        let len = len(&mut reader, initial, Kind::Str)?;
+        let position = match fields.iter().any(|field| field.len() == len) {
+            true => {
+                let key = bytes(&mut reader, len, Limits::NONE)?;
+                fields.iter().position(|field| field.as_bytes() == key)
+            }
+
+            false => {
+                discard(&mut reader, len)?;
+                None
+            }
+        };
+
+        if position.is_none() {
+            skip(&mut reader as &mut dyn Read, self.limits)?;
+        }
+
+        Ok(position)
+    }
+
+    fn encode_items(&self, writer: impl Write, items: usize) -> Result<(), Error> {
+        header(writer, Kind::Array, items)
+    }
+
+    fn decode_items(&self, mut reader: impl Read, items: usize) -> Result<(), Error> {
+        let initial = byte(&mut reader)?;
+        match len(reader, initial, Kind::Array)? == items {
+            true => Ok(()),
+            false => Err(Error::invalid("wrong number of items")),
+        }
+    }
+}
+
+/// Parameters for MessagePack `bin` values.
+///
+/// `[u8]` and `Vec<u8>` encode as a single `bin` value under these
+/// parameters, rather than as an array of integers.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Bin(pub MsgPack);
+
+/// A MessagePack extension value.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Ext {
+    /// The application-defined type of the value.
+    pub kind: i8,
+
+    /// The encoded value.
+    pub data: Vec<u8>,
+}
+
+/// The families of types which are prefixed by a length.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Kind {
+    Str,
+    Bin,
+    Array,
+    Map,
+    Ext,
+}
+
+fn header(mut writer: impl Write, kind: Kind, len: usize) -> Result<(), Error> {
+    // The fixed-size type, if any, and the 8, 16 and 32-bit length types.
+    let (fix, codes) = match kind {
+        Kind::Str => (Some((0xa0, 31)), [Some(0xd9), Some(0xda), Some(0xdb)]),
+        Kind::Bin => (None, [Some(0xc4), Some(0xc5), Some(0xc6)]),
+        Kind::Array => (Some((0x90, 15)), [None, Some(0xdc), Some(0xdd)]),
+        Kind::Map => (Some((0x80, 15)), [None, Some(0xde), Some(0xdf)]),
+        Kind::Ext => (None, [Some(0xc7), Some(0xc8), Some(0xc9)]),
+    };
+
+    match (fix, codes, len) {
+        (Some((fix, max)), _, len) if len <= max => Ok(writer.write_all(&[fix | len as u8])?),
+        (_, [Some(code), ..], 0..=0xff) => Ok(writer.write_all(&[code, len as u8])?),
+        (_, [_, Some(code), _], 0..=0xffff) => {
+            writer.write_all(&[code])?;
+            Ok(writer.write_all(&(len as u16).to_be_bytes())?)
+        }
+        (_, [_, _, Some(code)], len) => {
+            let len = u32::try_from(len).map_err(|_| Error::limit("length exceeds u32"))?;
+            writer.write_all(&[code])?;
+            Ok(writer.write_all(&len.to_be_bytes())?)
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Reads the length following the initial byte of a value of the given kind.
+fn len(mut reader: impl Read, initial: u8, kind: Kind) -> Result<usize, Error> {
+    let width = match (kind, initial) {
+        (Kind::Str, 0xa0..=0xbf) => return Ok(usize::from(initial & 0x1f)),
+        (Kind::Array, 0x90..=0x9f) => return Ok(usize::from(initial & 0x0f)),
+        (Kind::Map, 0x80..=0x8f) => return Ok(usize::from(initial & 0x0f)),
+        (Kind::Str, 0xd9) | (Kind::Bin, 0xc4) | (Kind::Ext, 0xc7) => 1,
+        (Kind::Str, 0xda) | (Kind::Bin, 0xc5) | (Kind::Ext, 0xc8) => 2,
+        (Kind::Array, 0xdc) | (Kind::Map, 0xde) => 2,
+        (Kind::Str, 0xdb) | (Kind::Bin, 0xc6) | (Kind::Ext, 0xc9) => 4,
+        (Kind::Array, 0xdd) | (Kind::Map, 0xdf) => 4,
+        _ => return Err(Error::invalid("unexpected type")),
+    };
+
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf[4 - width..])?;
+    usize::try_from(u32::from_be_bytes(buf)).map_err(|_| Error::limit("length exceeds usize"))
+}
+
+/// Reads `len` bytes without trusting `len` with a large allocation.
+fn bytes(reader: impl Read, len: usize, limits: Limits) -> Result<Vec<u8>, Error> {
+    limits.check_len(len)?;
+    limits.check_alloc::<u8>(len)?;
+    Ok(io::read_vec(reader, len)?)
+}
+
+/// Reads and discards `len` bytes.
+fn discard(mut reader: impl Read, mut len: usize) -> Result<(), Error> {
+    let mut buf = [0u8; 64];
+    while len > 0 {
+        let n = core::cmp::min(len, buf.len());
+        reader.read_exact(&mut buf[..n])?;
+        len -= n;
+    }
+
+    Ok(())
+}
+
+/// Reads and discards a value, such as that of a field unknown to a derived
+/// type. Arrays and maps take a level of the depth limit.
+fn skip(reader: &mut dyn Read, limits: Limits) -> Result<(), Error> {
+    let initial = byte(&mut *reader)?;
+    let (len, items) = match initial {
+        0x00..=0x7f | 0xe0..=0xff | NIL | FALSE | TRUE => (0, 0),
+        0xcc | 0xd0 => (1, 0),
+        0xcd | 0xd1 => (2, 0),
+        0xce | 0xd2 | FLOAT32 => (4, 0),
+        0xcf | 0xd3 | FLOAT64 => (8, 0),
+        0xd4..=0xd8 => (1 + (1 << (initial - 0xd4)), 0),
+        0xa0..=0xbf | 0xd9..=0xdb => (len(&mut *reader, initial, Kind::Str)?, 0),
+        0xc4..=0xc6 => (len(&mut *reader, initial, Kind::Bin)?, 0),
+        0xc7..=0xc9 => (len(&mut *reader, initial, Kind::Ext)?.saturating_add(1), 0),
+        0x90..=0x9f | 0xdc | 0xdd => (0, len(&mut *reader, initial, Kind::Array)?),
+        0x80..=0x8f | 0xde | 0xdf => {
+            let entries = len(&mut *reader, initial, Kind::Map)?;
+            (0, entries.saturating_mul(2))
+        }
+        _ => return Err(Error::invalid("unexpected type")),
+    };
+
+    discard(&mut *reader, len)?;
+
+    if let 0x80..=0x9f | 0xdc..=0xdf = initial {
+        let limits = limits.nested()?;
+        for _ in 0..items {
+            skip(reader, limits)?;
+        }
+    }
+
+    Ok(())
+}
+
+macro_rules! int {
+    ($($t:ident)+) => {
+        $(
+            impl Encoder<MsgPack> for $t {
+                type Error = Error;
+
+                fn encode(&self, mut writer: impl Write, _: MsgPack) -> Result<(), Error> {
+                    let value = i128::try_from(*self)
+                        .map_err(|_| Error::invalid("integer out of range"))?;
+
+                    match value {
+                        0..=0x7f => writer.write_all(&[value as u8])?,
+                        -32..=-1 => writer.write_all(&[value as i8 as u8])?,
+                        0x80..=0xff => writer.write_all(&[0xcc, value as u8])?,
+                        0x100..=0xffff => {
+                            writer.write_all(&[0xcd])?;
+                            writer.write_all(&(value as u16).to_be_bytes())?;
+                        }
+                        0x1_0000..=0xffff_ffff => {
+                            writer.write_all(&[0xce])?;
+                            writer.write_all(&(value as u32).to_be_bytes())?;
+                        }
+                        0x1_0000_0000..=0xffff_ffff_ffff_ffff => {
+                            writer.write_all(&[0xcf])?;
+                            writer.write_all(&(value as u64).to_be_bytes())?;
+                        }
+                        -0x80..=-33 => writer.write_all(&[0xd0, value as i8 as u8])?,
+                        -0x8000..=-0x81 => {
+                            writer.write_all(&[0xd1])?;
+                            writer.write_all(&(value as i16).to_be_bytes())?;
+                        }
+                        -0x8000_0000..=-0x8001 => {
+                            writer.write_all(&[0xd2])?;
+                            writer.write_all(&(value as i32).to_be_bytes())?;
+                        }
+                        -0x8000_0000_0000_0000..=-0x8000_0001 => {
+                            writer.write_all(&[0xd3])?;
+                            writer.write_all(&(value as i64).to_be_bytes())?;
+                        }
+                        _ => return Err(Error::invalid("integer out of range")),
+                    }
+
+                    Ok(())
+                }
+            }
+
+            impl Decoder<MsgPack> for $t {
+                type Error = Error;
+
+                fn decode(mut reader: impl Read, _: MsgPack) -> Result<Self, Error> {
+                    let initial = byte(&mut reader)?;
+                    let width = match initial {
+                        0x00..=0x7f => 0,
+                        0xe0..=0xff => 0,
+                        0xcc | 0xd0 => 1,
+                        0xcd | 0xd1 => 2,
+                        0xce | 0xd2 => 4,
+                        0xcf | 0xd3 => 8,
+                        _ => return Err(Error::invalid("expected integer")),
+                    };
+
+                    let mut buf = [0u8; 8];
+                    reader.read_exact(&mut buf[8 - width..])?;
+                    let unsigned = u64::from_be_bytes(buf);
+
+                    let value = match initial {
+                        0x00..=0x7f => i128::from(initial),
+                        0xe0..=0xff => i128::from(initial as i8),
+                        0xcc..=0xcf => i128::from(unsigned),
+                        _ => {
+                            // Sign-extend from the encoded width.
+                            let shift = 64 - 8 * width as u32;
+                            i128::from((unsigned << shift) as i64 >> shift)
+                        }
+                    };
+
+                    $t::try_from(value).map_err(|_| Error::invalid("integer out of range"))
+                }
+            }
+        )+
+    };
+}
+
+int!(u8 u16 u32 u64 u128 usize i8 i16 i32 i64 i128 isize);
+
+impl Encoder<MsgPack> for f32 {
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, _: MsgPack) -> Result<(), Error> {
+        writer.write_all(&[FLOAT32])?;
+        Ok(writer.write_all(&self.to_be_bytes())?)
+    }
+}
+
+impl Decoder<MsgPack> for f32 {
+    type Error = Error;
+
+    fn decode(reader: impl Read, params: MsgPack) -> Result<Self, Error> {
+        let value = f64::decode(reader, params)?;
+        match value.is_nan() || f64::from(value as f32) == value {
+            true => Ok(value as f32),
+            false => Err(Error::invalid("inexact float")),
+        }
+    }
+}
+
+impl Encoder<MsgPack> for f64 {
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, _: MsgPack) -> Result<(), Error> {
+        writer.write_all(&[FLOAT64])?;
+        Ok(writer.write_all(&self.to_be_bytes())?)
+    }
+}
+
+impl Decoder<MsgPack> for f64 {
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, _: MsgPack) -> Result<Self, Error> {
+        match byte(&mut reader)? {
+            FLOAT32 => {
+                let mut buf = [0u8; 4];
+                reader.read_exact(&mut buf)?;
+                Ok(f32::from_be_bytes(buf).into())
+            }
+
+            FLOAT64 => {
+                let mut buf = [0u8; 8];
+                reader.read_exact(&mut buf)?;
+                Ok(f64::from_be_bytes(buf))
+            }
+
+            _ => Err(Error::invalid("expected float")),
+        }
+    }
+}
+
+impl Encoder<MsgPack> for bool {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, mut writer: impl Write, _: MsgPack) -> Result<(), Error> {
+        Ok(writer.write_all(&[if *self { TRUE } else { FALSE }])?)
+    }
+}
+
+impl Decoder<MsgPack> for bool {
+    type Error = Error;
+
+    fn decode(reader: impl Read, _: MsgPack) -> Result<Self, Error> {
+        match byte(reader)? {
+            FALSE => Ok(false),
+            TRUE => Ok(true),
+            _ => Err(Error::invalid("expected bool")),
+        }
+    }
+}
+
+impl Encoder<Bin> for [u8] {
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, _: Bin) -> Result<(), Error> {
+        header(&mut writer, Kind::Bin, self.len())?;
+        Ok(writer.write_all(self)?)
+    }
+}
+
+impl Encoder<Bin> for Vec<u8> {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: Bin) -> Result<(), Error> {
+        self.as_slice().encode(writer, params)
+    }
+}
+
+impl Decoder<Bin> for Vec<u8> {
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: Bin) -> Result<Self, Error> {
+        let initial = byte(&mut reader)?;
+        let len = len(&mut reader, initial, Kind::Bin)?;
+        bytes(reader, len, params.0.limits)
+    }
+}
+
+impl Encoder<MsgPack> for str {
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, _: MsgPack) -> Result<(), Error> {
+        header(&mut writer, Kind::Str, self.len())?;
+        Ok(writer.write_all(self.as_bytes())?)
+    }
+}
+
+impl Encoder<MsgPack> for String {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: MsgPack) -> Result<(), Error> {
+        self.as_str().encode(writer, params)
+    }
+}
+
+impl Decoder<MsgPack> for String {
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: MsgPack) -> Result<Self, Error> {
+        let initial = byte(&mut reader)?;
+        let len = len(&mut reader, initial, Kind::Str)?;
+        let bytes = bytes(reader, len, params.limits)?;
+        String::from_utf8(bytes).map_err(|_| Error::invalid("invalid UTF-8"))
+    }
+}
+
+impl Encoder<MsgPack> for Ext {
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, _: MsgPack) -> Result<(), Error> {
+        match self.data.len() {
+            1 => writer.write_all(&[0xd4])?,
+            2 => writer.write_all(&[0xd5])?,
+            4 => writer.write_all(&[0xd6])?,
+            8 => writer.write_all(&[0xd7])?,
+            16 => writer.write_all(&[0xd8])?,
+            len => header(&mut writer, Kind::Ext, len)?,
+        }
+
+        writer.write_all(&[self.kind as u8])?;
+        Ok(writer.write_all(&self.data)?)
+    }
+}
+
+impl Decoder<MsgPack> for Ext {
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: MsgPack) -> Result<Self, Error> {
+        let len = match byte(&mut reader)? {
+            0xd4 => 1,
+            0xd5 => 2,
+            0xd6 => 4,
+            0xd7 => 8,
+            0xd8 => 16,
+            initial => len(&mut reader, initial, Kind::Ext)?,
+        };
+
+        params.limits.nested()?;
+        let kind = byte(&mut reader)? as i8;
+        let data = bytes(reader, len, params.limits)?;
+        Ok(Self { kind, data })
+    }
+}
+
+impl<T: Encoder<MsgPack>> Encoder<MsgPack> for [T]
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, params: MsgPack) -> Result<(), Error> {
+        header(&mut writer, Kind::Array, self.len())?;
+
+        for item in self {
+            item.encode(&mut writer, params).map_err(Into::into)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: Encoder<MsgPack>> Encoder<MsgPack> for Vec<T>
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: MsgPack) -> Result<(), Error> {
+        self.as_slice().encode(writer, params)
+    }
+}
+
+impl<T: Decoder<MsgPack>> Decoder<MsgPack> for Vec<T>
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: MsgPack) -> Result<Self, Error> {
+        let params = params.with_limits(params.limits.nested()?);
+        let initial = byte(&mut reader)?;
+        let len = len(&mut reader, initial, Kind::Array)?;
+        params.limits.check_len(len)?;
+        params.limits.check_alloc::<T>(len)?;
+
+        let mut items = io::with_capacity(len);
+
+        for _ in 0..len {
+            items.push(T::decode(&mut reader, params).map_err(Into::into)?);
+        }
+
+        Ok(items)
+    }
+}
+
+impl<K: Encoder<MsgPack>, V: Encoder<MsgPack>> Encoder<MsgPack> for BTreeMap<K, V>
+where
+    K::Error: Into<Error>,
+    V::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, params: MsgPack) -> Result<(), Error> {
+        header(&mut writer, Kind::Map, self.len())?;
+
+        for (key, value) in self {
+            key.encode(&mut writer, params).map_err(Into::into)?;
+            value.encode(&mut writer, params).map_err(Into::into)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<K: Decoder<MsgPack> + Ord, V: Decoder<MsgPack>> Decoder<MsgPack> for BTreeMap<K, V>
+where
+    K::Error: Into<Error>,
+    V::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: MsgPack) -> Result<Self, Error> {
+        let params = params.with_limits(params.limits.nested()?);
+        let initial = byte(&mut reader)?;
+        let len = len(&mut reader, initial, Kind::Map)?;
+        params.limits.check_len(len)?;
+
+        let mut map = BTreeMap::new();
+        for _ in 0..len {
+            let key = K::decode(&mut reader, params).map_err(Into::into)?;
+            let value = V::decode(&mut reader, params).map_err(Into::into)?;
+
+            if map.insert(key, value).is_some() {
+                return Err(Error::invalid("duplicate map key"));
+            }
+        }
+
+        Ok(map)
+    }
+}
+
+impl<T: Encoder<MsgPack>> Encoder<MsgPack> for Option<T>
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, params: MsgPack) -> Result<(), Error> {
+        match self {
+            Some(value) => value.encode(writer, params).map_err(Into::into),
+            None => Ok(writer.write_all(&[NIL])?),
+        }
+    }
+}
+
+impl<T: Decoder<MsgPack>> Decoder<MsgPack> for Option<T>
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: MsgPack) -> Result<Self, Error> {
+        match byte(&mut reader)? {
+            NIL => Ok(None),
+            initial => {
+                let params = params.with_limits(params.limits.nested()?);
+                T::decode(Prepend::new(initial, reader), params)
+                    .map(Some)
+                    .map_err(Into::into)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DecoderExt, EncoderExt};
+    use alloc::boxed::Box;
+    use alloc::vec;
+
+    #[test]
+    fn nested_containers() {
+        let msgpack = MsgPack::new();
+
+        let value = vec![(1u8, 2u8)];
+        let buf = value.encode_to_vec(msgpack).unwrap();
+        assert_eq!(buf, [0x91, 0x92, 0x01, 0x02]);
+        assert_eq!(Vec::<(u8, u8)>::decode_exact(&buf, msgpack).unwrap(), value);
+
+        let value = [[0u8; 16]; 2];
+        let buf = value.encode_to_vec(msgpack).unwrap();
+        assert_eq!(buf[..4], [0x92, 0xdc, 0x00, 0x10]);
+        assert_eq!(<[[u8; 16]; 2]>::decode_exact(&buf, msgpack).unwrap(), value);
+
+        let error = <(u8, u8)>::decode_exact(&[0x93, 0x01, 0x02, 0x03], msgpack).unwrap_err();
+        assert!(matches!(
+            error,
+            Error::InvalidValue {
+                reason: "wrong number of items",
+                ..
+            }
+        ));
+
+        let error = <[u8; 2]>::decode_exact(&[0x01, 0x02], msgpack).unwrap_err();
+        assert!(matches!(error, Error::InvalidValue { .. }));
+    }
+
+    #[derive(Debug)]
+    struct Node(Vec<Node>);
+
+    impl Decoder<MsgPack> for Node {
+        type Error = Error;
+
+        fn decode(mut reader: impl Read, params: MsgPack) -> Result<Self, Error> {
+            Vec::decode(&mut reader as &mut dyn Read, params).map(Self)
+        }
+    }
+
+    #[derive(Debug)]
+    struct List(Option<Box<List>>);
+
+    impl Decoder<MsgPack> for List {
+        type Error = Error;
+
+        fn decode(mut reader: impl Read, params: MsgPack) -> Result<Self, Error> {
+            Option::decode(&mut reader as &mut dyn Read, params).map(Self)
+        }
+    }
+
+    fn too_deep<T: Decoder<MsgPack, Error = Error> + core::fmt::Debug>(
+        buf: &[u8],
+        msgpack: MsgPack,
+    ) {
+        match T::decode_exact(buf, msgpack) {
+            Err(Error::LimitExceeded { limit, .. }) => assert_eq!(limit, "nesting depth"),
+            result => panic!(
+                "{:02x?} decoded as {:?}",
+                &buf[..core::cmp::min(buf.len(), 4)],
+                result
+            ),
+        }
+    }
+
+    #[test]
+    fn nesting_depth() {
+        let msgpack = MsgPack::new();
+
+        too_deep::<Node>(&vec![0x91; 1_000_000], msgpack);
+
+        let mut buf = vec![0x91; 127];
+        buf.push(0x90);
+        assert_eq!(Node::decode_exact(&buf, msgpack).unwrap().0.len(), 1);
+        too_deep::<Node>(&buf, msgpack.with_limits(Limits::default().depth(127)));
+
+        // A present value decodes the same byte again, consuming no input.
+        too_deep::<List>(&[0x01, 0], msgpack);
+        assert!(List::decode_exact(&[NIL], msgpack).unwrap().0.is_none());
+
+        let empty = msgpack.with_limits(Limits::default().depth(0));
+        too_deep::<BTreeMap<u8, u8>>(&[0x80], empty);
+        too_deep::<Ext>(&[0xd4, 0x01, 0x00], empty);
+        assert!(u8::decode_exact(&[0x01], empty).is_ok());
+    }
+
+    #[test]
+    fn skip_values() {
+        let msgpack = MsgPack::new();
+
+        let mut buf = Vec::new();
+        (
+            1u8,
+            -200i16,
+            1.5f64,
+            String::from("text"),
+            Some(true),
+            None::<u8>,
+        )
+            .encode(&mut buf, msgpack)
+            .unwrap();
+        vec![1u8, 2].encode(&mut buf, Bin(msgpack)).unwrap();
+        Ext {
+            kind: 1,
+            data: vec![0; 3],
+        }
+        .encode(&mut buf, msgpack)
+        .unwrap();
+        Ext {
+            kind: 1,
+            data: vec![0; 4],
+        }
+        .encode(&mut buf, msgpack)
+        .unwrap();
+        let map: BTreeMap<u8, Vec<u8>> = vec![(1, vec![2])].into_iter().collect();
+        map.encode(&mut buf, msgpack).unwrap();
+
+        let mut reader = buf.as_slice();
+        for _ in 0..5 {
+            skip(&mut reader, msgpack.limits()).unwrap();
+        }
+        assert!(reader.is_empty());
+
+        let error = skip(&mut &[0x92, 0x01][..], msgpack.limits()).unwrap_err();
+        assert!(error.is_eof());
+
+        let buf = vec![0x91; 1_000_000];
+        match skip(&mut buf.as_slice(), msgpack.limits()) {
+            Err(Error::LimitExceeded { limit, .. }) => assert_eq!(limit, "nesting depth"),
+            result => panic!("unexpected {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,1022 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! The Protocol Buffers wire format.
+//!
+//! A message is a sequence of fields, each encoded as a key (the field number
+//! and wire type) followed by a payload. Under the `Protobuf` parameters, the
+//! integer types and `bool` encode as varint payloads, `f32` and `f64` as
+//! fixed-width payloads and strings and `Vec<u8>` as length-delimited
+//! payloads. The other scalar types reuse this crate's parameters: `ZigZag`
+//! for `sint32` and `sint64`, `Le` for `fixed32`, `fixed64`, `sfixed32` and
+//! `sfixed64`. Embedded messages use `Framed::new(params, Uleb128)` and
+//! packed repeated fields use `Packed`. The `Wire` trait gives the wire type
+//! of each payload. Groups, the deprecated predecessor of embedded
+//! messages, are kept in a `Message` with the fields they contain.
+//!
+//! The `Field` parameters encode a payload as a complete field. Decoding
+//! with them expects exactly that field next. Since a message may contain
+//! its fields in any order, omit fields or contain fields unknown to the
+//! reader, decoders usually read the whole `Message` first and take each
+//! field from it. Whatever remains is the unknown fields, which encode
+//! unchanged after the known ones.
+//!
+//! ```rust
+//! use codicon::*;
+//! use codicon::protobuf::{Field, Message, Packed};
+//!
+//! #[derive(Debug, Default, PartialEq)]
+//! struct Person {
+//!     name: String,
+//!     id: i32,
+//!     scores: Vec<u32>,
+//!     unknown: Message,
+//! }
+//!
+//! impl Encoder<Protobuf> for Person {
+//!     type Error = Error;
+//!
+//!     fn encode(&self, mut writer: impl Write, params: Protobuf) -> Result<(), Error> {
+//!         self.name.encode(&mut writer, Field::new(1, params))?;
+//!         self.id.encode(&mut writer, Field::new(2, ZigZag))?;
+//!         if !self.scores.is_empty() {
+//!             self.scores.encode(&mut writer, Field::new(3, Packed(params)))?;
+//!         }
+//!         self.unknown.encode(writer, params)
+//!     }
+//! }
+//!
+//! impl Decoder<Protobuf> for Person {
+//!     type Error = Error;
+//!
+//!     fn decode(reader: impl Read, params: Protobuf) -> Result<Self, Error> {
+//!         let mut message = Message::decode(reader, params)?;
+//!
+//!         Ok(Self {
+//!             name: message.take(1, params)?.unwrap_or_default(),
+//!             id: message.take(2, ZigZag)?.unwrap_or_default(),
+//!             scores: message.take_repeated(3, params)?,
+//!             unknown: message,
+//!         })
+//!     }
+//! }
+//!
+//! let person = Person {
+//!     name: "Al".into(),
+//!     id: -2,
+//!     scores: vec![1, 300],
+//!     ..Default::default()
+//! };
+//!
+//! let buf = person.encode_to_vec(Protobuf::new()).unwrap();
+//! assert_eq!(buf, [0x0a, 2, b'A', b'l', 0x10, 0x03, 0x1a, 3, 0x01, 0xac, 0x02]);
+//!
+//! // Fields may arrive in any order, alongside unknown fields.
+//! let buf = [0x10, 0x03, 0x38, 0x07, 0x0a, 2, b'A', b'l', 0x2d, 1, 0, 0, 0];
+//! let decoded = Person::decode_exact(&buf, Protobuf::new()).unwrap();
+//! assert_eq!(decoded.name, "Al");
+//! assert_eq!(decoded.unknown.records().len(), 2);
+//!
+//! let buf = decoded.encode_to_vec(Protobuf::new()).unwrap();
+//! assert_eq!(buf, [0x0a, 2, b'A', b'l', 0x10, 0x03, 0x38, 0x07, 0x2d, 1, 0, 0, 0]);
+//! ```
+//!
+//! The derive macros can implement both traits: a derived `Encoder` encodes
+//! each field with the parameters given by its attribute, in declaration
+//! order. Given `#[codicon(message)]`, a derived `Decoder` takes each field
+//! from a `Message` as above, using `Message::take_field()`, and leaves the
+//! unknown fields to the field without parameters.
+//!
+//! ```rust
+//! # #[cfg(feature = "derive")]
+//! # fn main() {
+//! use codicon::*;
+//! use codicon::protobuf::{Field, Message, Packed};
+//!
+//! #[derive(Encoder, Decoder, Debug, PartialEq)]
+//! #[codicon(params = Protobuf, message)]
+//! struct Point {
+//!     #[codicon(params = Field::new(1, params))]
+//!     x: u32,
+//!     #[codicon(params = Field::new(2, Packed(params)))]
+//!     y: Vec<u32>,
+//!     unknown: Message,
+//! }
+//!
+//! let mut unknown = Message::new();
+//! unknown.push(3, &3u32, Protobuf::new()).unwrap();
+//!
+//! let point = Point { x: 1, y: vec![2], unknown };
+//! let buf = point.encode_to_vec(Protobuf::new()).unwrap();
+//! assert_eq!(buf, [0x08, 0x01, 0x12, 0x01, 0x02, 0x18, 0x03]);
+//! assert_eq!(Point::decode_exact(&buf, Protobuf::new()).unwrap(), point);
+//!
+//! // Fields may arrive in any order, and repeated fields unpacked.
+//! let buf = [0x10, 0x02, 0x18, 0x03, 0x08, 0x01];
+//! assert_eq!(Point::decode_exact(&buf, Protobuf::new()).unwrap(), point);
+//! # }
+//! # #[cfg(not(feature = "derive"))]
+//! # fn main() {}
+//! ```
+
+use crate::container::Concat;
+use crate::framed::{self, Frame};
+use crate::io::{self, BaseRead, Counter, ErrorKind, Read, Write};
+use crate::limits::Limits;
+use crate::{ByteStr, Decoder, Encoder, Error, Framed, Le, Uleb128, ZigZag};
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// The largest field number.
+const MAX_NUMBER: u32 = (1 << 29) - 1;
+
+/// The wire type of the key ending a group.
+const END_GROUP: u32 = 4;
+
+/// Protocol Buffers encoding parameters.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Protobuf {
+    limits: Limits,
+}
+
+impl Protobuf {
+    /// Creates parameters with the default limits.
+    pub const fn new() -> Self {
+        Self {
+            limits: Limits::DEFAULT,
+        }
+    }
+
+    /// Sets the limits checked when decoding.
+    pub fn with_limits(self, limits: Limits) -> Self {
+        Self { limits }
+    }
+
+    /// Returns the limits checked when decoding.
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    fn bytes(&self) -> ByteStr<Uleb128> {
+        ByteStr::new(Uleb128).with_limits(self.limits)
+    }
+}
+
+impl<T: ?Sized> Concat<T> for Protobuf {}
+
+/// The wire type of a field, which determines how to find its end.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum WireType {
+    /// A varint.
+    Varint,
+
+    /// Eight bytes.
+    I64,
+
+    /// A varint length followed by that many bytes.
+    Len,
+
+    /// Four bytes.
+    I32,
+
+    /// Fields up to a key with the same number ending the group.
+    Group,
+}
+
+impl WireType {
+    fn bits(self) -> u32 {
+        match self {
+            Self::Varint => 0,
+            Self::I64 => 1,
+            Self::Len => 2,
+            Self::Group => 3,
+            Self::I32 => 5,
+        }
+    }
+
+    fn from_bits(bits: u32) -> Result<Self, Error> {
+        match bits {
+            0 => Ok(Self::Varint),
+            1 => Ok(Self::I64),
+            2 => Ok(Self::Len),
+            3 => Ok(Self::Group),
+            5 => Ok(Self::I32),
+            _ => Err(Error::invalid("unsupported wire type")),
+        }
+    }
+}
+
+/// Parameters under which `T` encodes as a field payload.
+pub trait Wire<T: ?Sized> {
+    /// The wire type of the payload.
+    const WIRE_TYPE: WireType;
+
+    /// Returns the limits checked when finding the end of a group payload.
+    fn limits(&self) -> Limits {
+        Limits::DEFAULT
+    }
+
+    /// Removes every field with the given number from a message, decoding
+    /// the last one as by `Message::take()`.
+    fn take(self, message: &mut Message, number: u32) -> Result<Option<T>, Error>
+    where
+        Self: framed::DecodeFrame<T> + Clone + Sized,
+        T: Sized,
+    {
+        message.take(number, self)
+    }
+}
+
+macro_rules! wire {
+    ($params:ty: $($t:ty)+ => $wire:ident) => {
+        $(
+            impl Wire<$t> for $params {
+                const WIRE_TYPE: WireType = WireType::$wire;
+            }
+        )+
+    };
+}
+
+wire!(Protobuf: u32 u64 i32 i64 bool => Varint);
+wire!(Protobuf: f32 => I32);
+wire!(Protobuf: f64 => I64);
+wire!(Protobuf: str String [u8] Vec<u8> => Len);
+wire!(Uleb128: u32 u64 => Varint);
+wire!(ZigZag: i32 i64 => Varint);
+wire!(Le: u32 i32 f32 => I32);
+wire!(Le: u64 i64 f64 => I64);
+
+impl<T: ?Sized, P> Wire<T> for Framed<P, Uleb128> {
+    const WIRE_TYPE: WireType = WireType::Len;
+}
+
+/// Parameters for packed repeated fields.
+///
+/// The elements are encoded with the parameters `P`, which must give them a
+/// varint or fixed-width wire type, and prefixed with their total length.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Packed<P>(pub P);
+
+impl<T, P: Wire<T>> Wire<[T]> for Packed<P> {
+    const WIRE_TYPE: WireType = WireType::Len;
+}
+
+impl<T, P: Wire<T> + framed::DecodeFrame<T> + Clone> Wire<Vec<T>> for Packed<P> {
+    const WIRE_TYPE: WireType = WireType::Len;
+
+    /// Removes every field with the given number from a message, decoding
+    /// each element as by `Message::take_repeated()`.
+    fn take(self, message: &mut Message, number: u32) -> Result<Option<Vec<T>>, Error> {
+        message.take_repeated(number, self.0).map(Some)
+    }
+}
+
+/// Parameters which encode a payload as a field with the given number.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Field<P> {
+    number: u32,
+    params: P,
+}
+
+impl<P> Field<P> {
+    /// Creates parameters for field `number` with payload parameters `params`.
+    pub const fn new(number: u32, params: P) -> Self {
+        Self { number, params }
+    }
+
+    /// Returns the field number.
+    pub fn number(&self) -> u32 {
+        self.number
+    }
+}
+
+/// A field of a message.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Record {
+    /// The field number.
+    pub number: u32,
+
+    /// The payload.
+    pub value: Value,
+}
+
+/// The payload of a field, as given by its wire type.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Value {
+    /// A varint.
+    Varint(u64),
+
+    /// Eight bytes, as a little-endian integer.
+    I64(u64),
+
+    /// Length-delimited bytes.
+    Len(Vec<u8>),
+
+    /// Four bytes, as a little-endian integer.
+    I32(u32),
+
+    /// The fields of a group.
+    Group(Vec<Record>),
+}
+
+impl Value {
+    fn wire_type(&self) -> WireType {
+        match self {
+            Self::Varint(..) => WireType::Varint,
+            Self::I64(..) => WireType::I64,
+            Self::Len(..) => WireType::Len,
+            Self::I32(..) => WireType::I32,
+            Self::Group(..) => WireType::Group,
+        }
+    }
+
+    /// Encodes the payload, without the key ending a group.
+    fn encode(&self, mut writer: impl Write) -> Result<(), Error> {
+        match self {
+            Self::Varint(value) => value.encode(writer, Uleb128),
+            Self::I64(value) => value.encode(writer, Le),
+            Self::Len(bytes) => bytes.encode(writer, ByteStr::new(Uleb128)),
+            Self::I32(value) => Ok(writer.write_all(&value.to_le_bytes())?),
+            Self::Group(records) => encode_records(&mut writer as &mut dyn Write, records),
+        }
+    }
+
+    /// Decodes the payload of field `number`, with the key ending a group.
+    fn decode(
+        mut reader: impl Read,
+        number: u32,
+        wire: WireType,
+        params: Protobuf,
+    ) -> Result<Self, Error> {
+        Ok(match wire {
+            WireType::Varint => Self::Varint(u64::decode(reader, Uleb128)?),
+            WireType::I64 => Self::I64(u64::decode(reader, Le)?),
+            WireType::Len => Self::Len(Vec::decode(reader, params.bytes())?),
+            WireType::I32 => Self::I32(u32::decode(reader, Le)?),
+            WireType::Group => {
+                let params = params.with_limits(params.limits.nested()?);
+                let reader = &mut reader as &mut dyn Read;
+                Self::Group(decode_records(reader, Some(number), params)?)
+            }
+        })
+    }
+
+    /// Decodes the payload with the given parameters.
+    ///
+    /// Only the varint or fixed-width head of the payload is encoded again;
+    /// the bytes of a length-delimited payload are read where they are.
+    fn get<T, P: Wire<T> + framed::DecodeFrame<T>>(&self, params: P) -> Result<T, Error> {
+        if self.wire_type() != P::WIRE_TYPE {
+            return Err(Error::invalid("unexpected wire type"));
+        }
+
+        let mut head = [0u8; 10];
+        let mut group = Vec::new();
+        let (head, body): (&[u8], &[u8]) = match self {
+            Self::Varint(value) => (varint(&mut head, *value)?, &[]),
+            Self::I64(value) => {
+                head[..8].copy_from_slice(&value.to_le_bytes());
+                (&head[..8], &[])
+            }
+            Self::Len(bytes) => (varint(&mut head, bytes.len() as u64)?, bytes),
+            Self::I32(value) => {
+                head[..4].copy_from_slice(&value.to_le_bytes());
+                (&head[..4], &[])
+            }
+            Self::Group(records) => {
+                encode_records(&mut group, records)?;
+                (&[], &group)
+            }
+        };
+
+        let mut frame = Frame {
+            reader: Payload { head, body },
+            remaining: head.len() + body.len(),
+        };
+
+        let value = framed::DecodeFrame::decode(params, &mut frame)?;
+        match frame.remaining {
+            0 => Ok(value),
+            _ => Err(Error::invalid("value underruns field")),
+        }
+    }
+}
+
+/// Encodes `value` as a varint into `buf`, returning the encoded bytes.
+fn varint(buf: &mut [u8; 10], value: u64) -> Result<&[u8], Error> {
+    let mut writer = &mut buf[..];
+    value.encode(&mut writer, Uleb128)?;
+    let len = 10 - writer.len();
+    Ok(&buf[..len])
+}
+
+/// A reader over the head of a payload followed by its body.
+struct Payload<'a> {
+    head: &'a [u8],
+    body: &'a [u8],
+}
+
+impl BaseRead for Payload<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.head.is_empty() {
+            false => self.head.read(buf),
+            true => self.body.read(buf),
+        }
+    }
+}
+
+fn key(mut writer: impl Write, number: u32, bits: u32) -> Result<(), Error> {
+    if !(1..=MAX_NUMBER).contains(&number) {
+        return Err(Error::invalid("invalid field number"));
+    }
+
+    (number << 3 | bits).encode(&mut writer, Uleb128)
+}
+
+/// Reads a key as the field number and wire type bits, returning `None` at
+/// the end of the input.
+fn read_key(mut reader: impl Read) -> Result<Option<(u32, u32)>, Error> {
+    let mut first = 0u8;
+    loop {
+        match reader.read(core::slice::from_mut(&mut first)) {
+            Ok(0) => return Ok(None),
+            Ok(_) => break,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    let rest = crate::io::Prepend::new(first, reader);
+    let key = u32::decode(rest, Uleb128)?;
+    match key >> 3 {
+        0 => Err(Error::invalid("invalid field number")),
+        number => Ok(Some((number, key & 7))),
+    }
+}
+
+fn encode_records(mut writer: impl Write, records: &[Record]) -> Result<(), Error> {
+    for record in records {
+        let wire = record.value.wire_type();
+        key(&mut writer, record.number, wire.bits())?;
+        record.value.encode(&mut writer)?;
+
+        if wire == WireType::Group {
+            key(&mut writer, record.number, END_GROUP)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads fields until the end of the input, or of the group `group`.
+fn decode_records(
+    mut reader: impl Read,
+    group: Option<u32>,
+    params: Protobuf,
+) -> Result<Vec<Record>, Error> {
+    let mut records = Vec::new();
+
+    loop {
+        let (number, bits) = match read_key(&mut reader)? {
+            Some(key) => key,
+            None if group.is_none() => return Ok(records),
+            None => return Err(Error::eof()),
+        };
+
+        if bits == END_GROUP {
+            return match group == Some(number) {
+                true => Ok(records),
+                false => Err(Error::invalid("unexpected end of group")),
+            };
+        }
+
+        params.limits.check_len(records.len() + 1)?;
+        params.limits.check_alloc::<Record>(records.len() + 1)?;
+
+        let value = Value::decode(&mut reader, number, WireType::from_bits(bits)?, params)?;
+        records.push(Record { number, value });
+    }
+}
+
+macro_rules! varint {
+    ($($t:ident)+) => {
+        $(
+            impl Encoder<Protobuf> for $t {
+                type Error = Error;
+
+                #[inline]
+                fn encode(&self, writer: impl Write, _: Protobuf) -> Result<(), Error> {
+                    // Negative values are sign-extended to 64 bits.
+                    (i64::from(*self) as u64).encode(writer, Uleb128)
+                }
+            }
+
+            impl Decoder<Protobuf> for $t {
+                type Error = Error;
+
+                fn decode(reader: impl Read, _: Protobuf) -> Result<Self, Error> {
+                    // As in C++, values out of range are truncated.
+                    Ok(u64::decode(reader, Uleb128)? as $t)
+                }
+            }
+        )+
+    };
+}
+
+varint!(i32 i64 u32);
+
+impl Encoder<Protobuf> for u64 {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, _: Protobuf) -> Result<(), Error> {
+        self.encode(writer, Uleb128)
+    }
+}
+
+impl Decoder<Protobuf> for u64 {
+    type Error = Error;
+
+    #[inline]
+    fn decode(reader: impl Read, _: Protobuf) -> Result<Self, Error> {
+        u64::decode(reader, Uleb128)
+    }
+}
+
+impl Encoder<Protobuf> for bool {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, mut writer: impl Write, _: Protobuf) -> Result<(), Error> {
+        Ok(writer.write_all(&[*self as u8])?)
+    }
+}
+
+impl Decoder<Protobuf> for bool {
+    type Error = Error;
+
+    fn decode(reader: impl Read, _: Protobuf) -> Result<Self, Error> {
+        match u64::decode(reader, Uleb128)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Error::invalid("invalid bool")),
+        }
+    }
+}
+
+macro_rules! fixed {
+    ($($t:ident)+) => {
+        $(
+            impl Encoder<Protobuf> for $t {
+                type Error = Error;
+
+                #[inline]
+                fn encode(&self, writer: impl Write, _: Protobuf) -> Result<(), Error> {
+                    self.encode(writer, Le)
+                }
+            }
+
+            impl Decoder<Protobuf> for $t {
+                type Error = Error;
+
+                #[inline]
+                fn decode(reader: impl Read, _: Protobuf) -> Result<Self, Error> {
+                    $t::decode(reader, Le)
+                }
+            }
+        )+
+    };
+}
+
+fixed!(f32 f64);
+
+impl Encoder<Protobuf> for [u8] {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: Protobuf) -> Result<(), Error> {
+        self.encode(writer, params.bytes())
+    }
+}
+
+impl Encoder<Protobuf> for Vec<u8> {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: Protobuf) -> Result<(), Error> {
+        self.encode(writer, params.bytes())
+    }
+}
+
+impl Decoder<Protobuf> for Vec<u8> {
+    type Error = Error;
+
+    #[inline]
+    fn decode(reader: impl Read, params: Protobuf) -> Result<Self, Error> {
+        Vec::decode(reader, params.bytes())
+    }
+}
+
+impl Encoder<Protobuf> for str {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: Protobuf) -> Result<(), Error> {
+        self.encode(writer, params.bytes())
+    }
+}
+
+impl Encoder<Protobuf> for String {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: Protobuf) -> Result<(), Error> {
+        self.encode(writer, params.bytes())
+    }
+}
+
+impl Decoder<Protobuf> for String {
+    type Error = Error;
+
+    #[inline]
+    fn decode(reader: impl Read, params: Protobuf) -> Result<Self, Error> {
+        String::decode(reader, params.bytes())
+    }
+}
+
+impl<T, P: Wire<T> + framed::EncodeFrame<T> + Clone> Encoder<Packed<P>> for [T] {
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, params: Packed<P>) -> Result<(), Error> {
+        if let WireType::Len | WireType::Group = P::WIRE_TYPE {
+            return Err(Error::invalid("cannot pack length-delimited values"));
+        }
+
+        let mut counter = Counter::default();
+        for item in self {
+            framed::EncodeFrame::encode(params.0.clone(), item, &mut counter)?;
+        }
+
+        counter.count().encode(&mut writer, Uleb128)?;
+        for item in self {
+            framed::EncodeFrame::encode(params.0.clone(), item, &mut writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T, P: Wire<T> + framed::EncodeFrame<T> + Clone> Encoder<Packed<P>> for Vec<T> {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: Packed<P>) -> Result<(), Error> {
+        self.as_slice().encode(writer, params)
+    }
+}
+
+impl<T, P: Wire<T> + framed::DecodeFrame<T> + Clone> Decoder<Packed<P>> for Vec<T> {
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: Packed<P>) -> Result<Self, Error> {
+        let len = usize::decode(&mut reader, Uleb128)?;
+        let mut frame = Frame {
+            reader,
+            remaining: len,
+        };
+
+        let mut items = Vec::new();
+        while frame.remaining > 0 {
+            items.push(framed::DecodeFrame::decode(params.0.clone(), &mut frame)?);
+        }
+
+        Ok(items)
+    }
+}
+
+impl<T: ?Sized, P: Wire<T> + framed::EncodeFrame<T>> Encoder<Field<P>> for T {
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, params: Field<P>) -> Result<(), Error> {
+        key(&mut writer, params.number, P::WIRE_TYPE.bits())?;
+        framed::EncodeFrame::encode(params.params, self, &mut writer)?;
+
+        match P::WIRE_TYPE {
+            WireType::Group => key(writer, params.number, END_GROUP),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<T, P: Wire<T> + framed::DecodeFrame<T>> Decoder<Field<P>> for T {
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: Field<P>) -> Result<Self, Error> {
+        match read_key(&mut reader)? {
+            // A group has no length, so find its end before decoding it.
+            Some((number, bits)) if number == params.number && bits == P::WIRE_TYPE.bits() => {
+                match P::WIRE_TYPE {
+                    WireType::Group => {
+                        let limits = Protobuf::new().with_limits(params.params.limits());
+                        Value::decode(reader, number, WireType::Group, limits)?.get(params.params)
+                    }
+                    _ => framed::DecodeFrame::decode(params.params, reader),
+                }
+            }
+            Some(..) => Err(Error::invalid("unexpected field")),
+            None => Err(Error::eof()),
+        }
+    }
+}
+
+/// The fields of a message, in the order they were decoded.
+///
+/// Decoding reads fields until the end of the input, so a `Message` must be
+/// the last value of a message; embed it with `Framed` parameters otherwise.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Message {
+    records: Vec<Record>,
+}
+
+impl Message {
+    /// Creates an empty message.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the fields of the message.
+    pub fn records(&self) -> &[Record] {
+        &self.records
+    }
+
+    /// Appends a field encoded with the payload parameters `params`.
+    pub fn push<T: ?Sized, P: Wire<T> + framed::EncodeFrame<T>>(
+        &mut self,
+        number: u32,
+        value: &T,
+        params: P,
+    ) -> Result<(), Error> {
+        if !(1..=MAX_NUMBER).contains(&number) {
+            return Err(Error::invalid("invalid field number"));
+        }
+
+        let limits = Protobuf::new().with_limits(params.limits());
+        let mut buf = Vec::new();
+        framed::EncodeFrame::encode(params, value, &mut buf)?;
+        if P::WIRE_TYPE == WireType::Group {
+            key(&mut buf, number, END_GROUP)?;
+        }
+
+        let value = Value::decode(buf.as_slice(), number, P::WIRE_TYPE, limits)?;
+        self.records.push(Record { number, value });
+        Ok(())
+    }
+
+    /// Removes every field with the given number, decoding the last one.
+    ///
+    /// As for scalar fields in protobuf, when a field occurs more than
+    /// once, the last occurrence wins.
+    pub fn take<T, P: Wire<T> + framed::DecodeFrame<T> + Clone>(
+        &mut self,
+        number: u32,
+        params: P,
+    ) -> Result<Option<T>, Error> {
+        let mut last = None;
+        for record in self.drain(number) {
+            last = Some(record.value.get(params.clone())?);
+        }
+
+        Ok(last)
+    }
+
+    /// Removes every field with the number of `params`, decoding its value.
+    ///
+    /// Fields with `Packed` parameters are repeated and decode as by
+    /// `take_repeated()`; others decode as by `take()`. An absent field
+    /// decodes as its default. Derived decoders of `message` structs take
+    /// each of their numbered fields this way.
+    pub fn take_field<T: Default, P: Wire<T> + framed::DecodeFrame<T> + Clone>(
+        &mut self,
+        params: Field<P>,
+    ) -> Result<T, Error> {
+        let value = params.params.take(self, params.number)?;
+        Ok(value.unwrap_or_default())
+    }
+
+    /// Removes every field with the given number, decoding each element.
+    ///
+    /// Both packed and unpacked encodings of the elements are accepted.
+    pub fn take_repeated<T, P: Wire<T> + framed::DecodeFrame<T> + Clone>(
+        &mut self,
+        number: u32,
+        params: P,
+    ) -> Result<Vec<T>, Error> {
+        let mut items = Vec::new();
+
+        for record in self.drain(number) {
+            match (&record.value, P::WIRE_TYPE) {
+                (Value::Len(bytes), WireType::Varint | WireType::I64 | WireType::I32) => {
+                    let mut frame = Frame {
+                        reader: bytes.as_slice(),
+                        remaining: bytes.len(),
+                    };
+
+                    while frame.remaining > 0 {
+                        items.push(framed::DecodeFrame::decode(params.clone(), &mut frame)?);
+                    }
+                }
+
+                (value, _) => items.push(value.get(params.clone())?),
+            }
+        }
+
+        Ok(items)
+    }
+
+    fn drain(&mut self, number: u32) -> Vec<Record> {
+        let (taken, kept) = core::mem::take(&mut self.records)
+            .into_iter()
+            .partition(|record| record.number == number);
+
+        self.records = kept;
+        taken
+    }
+}
+
+impl Encoder<Protobuf> for Message {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, _: Protobuf) -> Result<(), Error> {
+        encode_records(writer, &self.records)
+    }
+}
+
+impl Decoder<Protobuf> for Message {
+    type Error = Error;
+
+    #[inline]
+    fn decode(reader: impl Read, params: Protobuf) -> Result<Self, Error> {
+        let records = decode_records(reader, None, params)?;
+        Ok(Self { records })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DecoderExt, EncoderExt};
+    use alloc::vec;
+
+    #[test]
+    fn groups() {
+        let protobuf = Protobuf::new();
+
+        // Field 1 is a group holding field 2 and a group 3, then field 4.
+        let buf = [0x0b, 0x10, 0x01, 0x1b, 0x1c, 0x0c, 0x20, 0x02];
+        let message = Message::decode_exact(&buf, protobuf).unwrap();
+
+        let inner = vec![
+            Record {
+                number: 2,
+                value: Value::Varint(1),
+            },
+            Record {
+                number: 3,
+                value: Value::Group(vec![]),
+            },
+        ];
+
+        assert_eq!(message.records()[0].value, Value::Group(inner));
+        assert_eq!(message.records()[1].value, Value::Varint(2));
+        assert_eq!(message.encode_to_vec(protobuf).unwrap(), buf);
+
+        let limited = protobuf.with_limits(Limits::default().depth(1));
+        let error = Message::decode_exact(&buf, limited).unwrap_err();
+        assert!(matches!(error, Error::LimitExceeded { .. }));
+    }
+
+    #[test]
+    fn truncated_varints() {
+        let protobuf = Protobuf::new();
+
+        let buf = 0xffff_ffffu64.encode_to_vec(Uleb128).unwrap();
+        assert_eq!(i32::decode_exact(&buf, protobuf).unwrap(), -1);
+
+        let buf = (1u64 << 32 | 5).encode_to_vec(Uleb128).unwrap();
+        assert_eq!(u32::decode_exact(&buf, protobuf).unwrap(), 5);
+        assert_eq!(i64::decode_exact(&buf, protobuf).unwrap(), 1 << 32 | 5);
+    }
+
+    #[test]
+    fn take_payloads() {
+        let protobuf = Protobuf::new();
+
+        let mut message = Message::new();
+        message.push(1, &-1i64, protobuf).unwrap();
+        message.push(2, &1.5f64, protobuf).unwrap();
+        message.push(3, "abc", protobuf).unwrap();
+        message.push(4, &7u32, Le).unwrap();
+
+        assert_eq!(message.take(1, protobuf).unwrap(), Some(-1i64));
+        assert_eq!(message.take(2, protobuf).unwrap(), Some(1.5f64));
+        assert_eq!(
+            message.take(3, protobuf).unwrap(),
+            Some(String::from("abc"))
+        );
+        assert_eq!(message.take(4, Le).unwrap(), Some(7u32));
+        assert!(message.records().is_empty());
+
+        message.push(1, &1u32, protobuf).unwrap();
+        assert!(message.take::<u32, _>(1, Le).is_err());
+    }
+
+    #[test]
+    fn unbalanced_groups() {
+        let protobuf = Protobuf::new();
+
+        for buf in [&[0x0c][..], &[0x0b, 0x14], &[0x0b, 0x1b, 0x0c]] {
+            let error = Message::decode_exact(buf, protobuf).unwrap_err();
+            assert!(matches!(
+                error,
+                Error::InvalidValue {
+                    reason: "unexpected end of group",
+                    ..
+                }
+            ));
+        }
+
+        assert!(Message::decode_exact(&[0x0b, 0x10, 0x01], protobuf)
+            .unwrap_err()
+            .is_eof());
+    }
+
+    fn too_deep<T: core::fmt::Debug>(result: Result<T, Error>) {
+        match result {
+            Err(Error::LimitExceeded { limit, .. }) => assert_eq!(limit, "nesting depth"),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn deep_groups() {
+        let buf = vec![0x0b; 1_000_000];
+        too_deep(Message::decode_exact(&buf, Protobuf::new()));
+
+        let mut buf = vec![0x0b; 128];
+        buf.extend(core::iter::repeat(0x0c).take(128));
+        assert!(Message::decode_exact(&buf, Protobuf::new()).is_ok());
+        too_deep(Message::decode_exact(
+            &buf,
+            Protobuf::new().with_limits(Limits::default().depth(127)),
+        ));
+    }
+
+    /// Parameters under which a message encodes as a group.
+    #[derive(Copy, Clone)]
+    struct Grouped(Protobuf);
+
+    impl Wire<Message> for Grouped {
+        const WIRE_TYPE: WireType = WireType::Group;
+
+        fn limits(&self) -> Limits {
+            self.0.limits()
+        }
+    }
+
+    impl Encoder<Grouped> for Message {
+        type Error = Error;
+
+        fn encode(&self, writer: impl Write, params: Grouped) -> Result<(), Error> {
+            self.encode(writer, params.0)
+        }
+    }
+
+    impl Decoder<Grouped> for Message {
+        type Error = Error;
+
+        fn decode(reader: impl Read, params: Grouped) -> Result<Self, Error> {
+            Message::decode(reader, params.0)
+        }
+    }
+
+    #[test]
+    fn group_fields() {
+        let protobuf = Protobuf::new();
+
+        let mut inner = Message::new();
+        inner.push(2, &1u32, protobuf).unwrap();
+
+        let buf = inner
+            .encode_to_vec(Field::new(1, Grouped(protobuf)))
+            .unwrap();
+        assert_eq!(buf, [0x0b, 0x10, 0x01, 0x0c]);
+
+        let field = Field::new(1, Grouped(protobuf));
+        assert_eq!(Message::decode_exact(&buf, field).unwrap(), inner);
+
+        let buf = [0x0b, 0x1b, 0x1c, 0x0c];
+        let limited = Grouped(protobuf.with_limits(Limits::default().depth(1)));
+        too_deep(Message::decode_exact(&buf, Field::new(1, limited)));
+    }
+}
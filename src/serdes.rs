@@ -0,0 +1,853 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! A bridge between `serde` and this crate.
+//!
+//! Under the `Serde` parameters, every `Serialize` type implements `Encoder`
+//! and every `DeserializeOwned` type implements `Decoder`, using a pluggable
+//! serde data format implementing `Format`. This lets serde types be passed
+//! through code which is generic over `Encoder` and `Decoder`.
+//!
+//! Conversely, `Serializer` and `Deserializer` drive this crate's encodings
+//! from serde: primitives are encoded with the parameters `P` and lengths with
+//! the parameters `L`, in the style of `bincode`. The `Binary` format uses
+//! them. Since the encoding is not self-describing, types which require
+//! `Deserializer::deserialize_any()` cannot be decoded.
+//!
+//! ```rust
+//! use codicon::*;
+//! use codicon::serdes::Binary;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize, Debug, PartialEq)]
+//! enum Message {
+//!     Ping(u16),
+//!     Text { body: String, urgent: Option<bool> },
+//! }
+//!
+//! let serde = Serde(Binary::new(Be, Uleb128));
+//!
+//! let message = Message::Text { body: "hi".into(), urgent: Some(true) };
+//! let buf = message.encode_to_vec(serde).unwrap();
+//! assert_eq!(buf, [0, 0, 0, 1, 2, b'h', b'i', 1, 1]);
+//! assert_eq!(Message::decode_exact(&buf, serde).unwrap(), message);
+//!
+//! let buf = Message::Ping(7).encode_to_vec(serde).unwrap();
+//! assert_eq!(buf, [0, 0, 0, 0, 0, 7]);
+//! ```
+
+use crate::container::ByteStr;
+use crate::io::{Read, Write};
+use crate::limits::Limits;
+use crate::{Decoder, Encoder, Error};
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Display;
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::ser::{self, Serialize};
+
+/// A serde data format.
+pub trait Format {
+    type Error;
+
+    /// Serializes the value to the writer.
+    fn serialize<T: Serialize + ?Sized>(
+        self,
+        writer: impl Write,
+        value: &T,
+    ) -> Result<(), Self::Error>;
+
+    /// Deserializes a value from the reader.
+    fn deserialize<T: DeserializeOwned>(self, reader: impl Read) -> Result<T, Self::Error>;
+}
+
+/// Parameters which encode and decode with the serde data format `F`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Serde<F>(pub F);
+
+impl<T: Serialize + ?Sized, F: Format> Encoder<Serde<F>> for T {
+    type Error = F::Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: Serde<F>) -> Result<(), Self::Error> {
+        params.0.serialize(writer, self)
+    }
+}
+
+impl<T: DeserializeOwned, F: Format> Decoder<Serde<F>> for T {
+    type Error = F::Error;
+
+    #[inline]
+    fn decode(reader: impl Read, params: Serde<F>) -> Result<Self, Self::Error> {
+        params.0.deserialize(reader)
+    }
+}
+
+macro_rules! scalars {
+    ($($t:ident $encode:ident $decode:ident)+) => {
+        /// Parameters with which every serde primitive type encodes and decodes.
+        ///
+        /// This is implemented for all parameters under which `bool`, the
+        /// integers and the floating point types use `Error`, such as `Le`,
+        /// `Be` and `Ne`.
+        pub trait Scalars: Clone {
+            $(
+                #[doc = concat!("Encodes a `", stringify!($t), "`.")]
+                fn $encode(self, value: $t, writer: impl Write) -> Result<(), Error>;
+
+                #[doc = concat!("Decodes a `", stringify!($t), "`.")]
+                fn $decode(self, reader: impl Read) -> Result<$t, Error>;
+            )+
+        }
+
+        impl<P: Clone> Scalars for P
+        where
+            $($t: Encoder<P, Error = Error> + Decoder<P, Error = Error>,)+
+        {
+            $(
+                #[inline]
+                fn $encode(self, value: $t, writer: impl Write) -> Result<(), Error> {
+                    value.encode(writer, self)
+                }
+
+                #[inline]
+                fn $decode(self, reader: impl Read) -> Result<$t, Error> {
+                    $t::decode(reader, self)
+                }
+            )+
+        }
+    };
+}
+
+scalars! {
+    bool encode_bool decode_bool
+    u8 encode_u8 decode_u8
+    u16 encode_u16 decode_u16
+    u32 encode_u32 decode_u32
+    u64 encode_u64 decode_u64
+    u128 encode_u128 decode_u128
+    i8 encode_i8 decode_i8
+    i16 encode_i16 decode_i16
+    i32 encode_i32 decode_i32
+    i64 encode_i64 decode_i64
+    i128 encode_i128 decode_i128
+    f32 encode_f32 decode_f32
+    f64 encode_f64 decode_f64
+}
+
+/// A serde data format using `Serializer` and `Deserializer`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Binary<P, L> {
+    params: P,
+    len: L,
+    limits: Limits,
+}
+
+impl<P, L> Binary<P, L> {
+    /// Creates a format with primitive parameters `params` and length parameters `len`.
+    pub const fn new(params: P, len: L) -> Self {
+        Self {
+            params,
+            len,
+            limits: Limits::DEFAULT,
+        }
+    }
+
+    /// Sets the limits checked when deserializing.
+    pub fn with_limits(self, limits: Limits) -> Self {
+        Self { limits, ..self }
+    }
+
+    /// Returns the limits checked when deserializing.
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+}
+
+impl<P: Scalars, L: Clone> Format for Binary<P, L>
+where
+    usize: Encoder<L> + Decoder<L>,
+    <usize as Encoder<L>>::Error: Into<Error>,
+    <usize as Decoder<L>>::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn serialize<T: Serialize + ?Sized>(self, writer: impl Write, value: &T) -> Result<(), Error> {
+        value.serialize(&mut Serializer::new(writer, self.params, self.len))
+    }
+
+    fn deserialize<T: DeserializeOwned>(self, reader: impl Read) -> Result<T, Error> {
+        let deserializer = Deserializer::new(reader, self.params, self.len);
+        T::deserialize(&mut deserializer.with_limits(self.limits))
+    }
+}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(message: T) -> Self {
+        Error::custom(message.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: Display>(message: T) -> Self {
+        Error::custom(message.to_string())
+    }
+}
+
+/// A serde serializer which encodes with this crate's encodings.
+///
+/// Primitives are encoded with the parameters `P` and the lengths of strings,
+/// sequences and maps with the parameters `L`. A `char` is encoded as a
+/// `u32`, an `Option` as a `u8` tag followed by any value and an enum variant
+/// as its index as a `u32` followed by its fields. Structs and tuples are
+/// encoded as their fields in order.
+#[derive(Clone, Debug)]
+pub struct Serializer<W, P, L> {
+    writer: W,
+    params: P,
+    len: L,
+}
+
+impl<W, P, L> Serializer<W, P, L> {
+    /// Creates a serializer writing to `writer`.
+    pub const fn new(writer: W, params: P, len: L) -> Self {
+        Self {
+            writer,
+            params,
+            len,
+        }
+    }
+
+    /// Unwraps the inner writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write, P: Scalars, L: Clone> Serializer<W, P, L>
+where
+    usize: Encoder<L>,
+    <usize as Encoder<L>>::Error: Into<Error>,
+{
+    fn len(&mut self, len: Option<usize>) -> Result<(), Error> {
+        let len = len.ok_or(Error::invalid("unknown length"))?;
+        len.encode(&mut self.writer, self.len.clone())
+            .map_err(Into::into)
+    }
+
+    fn variant(&mut self, index: u32) -> Result<(), Error> {
+        self.params.clone().encode_u32(index, &mut self.writer)
+    }
+}
+
+macro_rules! serialize {
+    ($($method:ident $t:ident $encode:ident)+) => {
+        $(
+            fn $method(self, value: $t) -> Result<(), Error> {
+                self.params.clone().$encode(value, &mut self.writer)
+            }
+        )+
+    };
+}
+
+impl<W: Write, P: Scalars, L: Clone> ser::Serializer for &mut Serializer<W, P, L>
+where
+    usize: Encoder<L>,
+    <usize as Encoder<L>>::Error: Into<Error>,
+{
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    serialize! {
+        serialize_bool bool encode_bool
+        serialize_u8 u8 encode_u8
+        serialize_u16 u16 encode_u16
+        serialize_u32 u32 encode_u32
+        serialize_u64 u64 encode_u64
+        serialize_u128 u128 encode_u128
+        serialize_i8 i8 encode_i8
+        serialize_i16 i16 encode_i16
+        serialize_i32 i32 encode_i32
+        serialize_i64 i64 encode_i64
+        serialize_i128 i128 encode_i128
+        serialize_f32 f32 encode_f32
+        serialize_f64 f64 encode_f64
+    }
+
+    fn serialize_char(self, value: char) -> Result<(), Error> {
+        self.serialize_u32(value.into())
+    }
+
+    fn serialize_str(self, value: &str) -> Result<(), Error> {
+        self.serialize_bytes(value.as_bytes())
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<(), Error> {
+        value.encode(&mut self.writer, ByteStr::new(self.len.clone()))
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        self.serialize_u8(0)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        self.serialize_u8(1)?;
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        index: u32,
+        _: &'static str,
+    ) -> Result<(), Error> {
+        self.variant(index)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        index: u32,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.variant(index)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self, Error> {
+        self.len(len)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        index: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self, Error> {
+        self.variant(index)?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self, Error> {
+        self.len(len)?;
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        index: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self, Error> {
+        self.variant(index)?;
+        Ok(self)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+macro_rules! compound {
+    ($($trait:ident $method:ident)+) => {
+        $(
+            impl<'a, W: Write, P: Scalars, L: Clone> ser::$trait for &'a mut Serializer<W, P, L>
+            where
+                usize: Encoder<L>,
+                <usize as Encoder<L>>::Error: Into<Error>,
+            {
+                type Ok = ();
+                type Error = Error;
+
+                fn $method<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+                    value.serialize(&mut **self)
+                }
+
+                fn end(self) -> Result<(), Error> {
+                    Ok(())
+                }
+            }
+        )+
+    };
+}
+
+compound! {
+    SerializeSeq serialize_element
+    SerializeTuple serialize_element
+    SerializeTupleStruct serialize_field
+    SerializeTupleVariant serialize_field
+}
+
+impl<W: Write, P: Scalars, L: Clone> ser::SerializeMap for &mut Serializer<W, P, L>
+where
+    usize: Encoder<L>,
+    <usize as Encoder<L>>::Error: Into<Error>,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+macro_rules! fields {
+    ($($trait:ident)+) => {
+        $(
+            impl<'a, W: Write, P: Scalars, L: Clone> ser::$trait for &'a mut Serializer<W, P, L>
+            where
+                usize: Encoder<L>,
+                <usize as Encoder<L>>::Error: Into<Error>,
+            {
+                type Ok = ();
+                type Error = Error;
+
+                fn serialize_field<T: Serialize + ?Sized>(
+                    &mut self,
+                    _: &'static str,
+                    value: &T,
+                ) -> Result<(), Error> {
+                    value.serialize(&mut **self)
+                }
+
+                fn end(self) -> Result<(), Error> {
+                    Ok(())
+                }
+            }
+        )+
+    };
+}
+
+fields!(SerializeStruct SerializeStructVariant);
+
+/// A serde deserializer which decodes with this crate's encodings.
+///
+/// This decodes the encoding produced by `Serializer`. The lengths of
+/// strings, sequences and maps, and the nesting depth of options,
+/// sequences, tuples, maps, structs and enums, are checked against its
+/// `Limits`.
+#[derive(Clone, Debug)]
+pub struct Deserializer<R, P, L> {
+    reader: R,
+    params: P,
+    len: L,
+    limits: Limits,
+}
+
+impl<R, P, L> Deserializer<R, P, L> {
+    /// Creates a deserializer reading from `reader`.
+    pub const fn new(reader: R, params: P, len: L) -> Self {
+        Self {
+            reader,
+            params,
+            len,
+            limits: Limits::DEFAULT,
+        }
+    }
+
+    /// Sets the limits checked when deserializing.
+    pub fn with_limits(self, limits: Limits) -> Self {
+        Self { limits, ..self }
+    }
+
+    /// Unwraps the inner reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read, P: Scalars, L: Clone> Deserializer<R, P, L>
+where
+    usize: Decoder<L>,
+    <usize as Decoder<L>>::Error: Into<Error>,
+{
+    fn len(&mut self) -> Result<usize, Error> {
+        let len = usize::decode(&mut self.reader, self.len.clone()).map_err(Into::into)?;
+        self.limits.check_len(len)?;
+        Ok(len)
+    }
+
+    fn bytes(&mut self) -> Result<Vec<u8>, Error> {
+        let params = ByteStr::new(self.len.clone()).with_limits(self.limits);
+        Vec::decode(&mut self.reader, params)
+    }
+
+    /// Deserializes a nested value, failing if nested too deeply.
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T, Error>) -> Result<T, Error> {
+        let limits = self.limits;
+        self.limits = limits.nested()?;
+        let result = f(self);
+        self.limits = limits;
+        result
+    }
+}
+
+macro_rules! deserialize {
+    ($($method:ident $visit:ident $decode:ident)+) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                visitor.$visit(self.params.clone().$decode(&mut self.reader)?)
+            }
+        )+
+    };
+}
+
+impl<'de, R: Read, P: Scalars, L: Clone> de::Deserializer<'de> for &mut Deserializer<R, P, L>
+where
+    usize: Decoder<L>,
+    <usize as Decoder<L>>::Error: Into<Error>,
+{
+    type Error = Error;
+
+    deserialize! {
+        deserialize_bool visit_bool decode_bool
+        deserialize_u8 visit_u8 decode_u8
+        deserialize_u16 visit_u16 decode_u16
+        deserialize_u32 visit_u32 decode_u32
+        deserialize_u64 visit_u64 decode_u64
+        deserialize_u128 visit_u128 decode_u128
+        deserialize_i8 visit_i8 decode_i8
+        deserialize_i16 visit_i16 decode_i16
+        deserialize_i32 visit_i32 decode_i32
+        deserialize_i64 visit_i64 decode_i64
+        deserialize_i128 visit_i128 decode_i128
+        deserialize_f32 visit_f32 decode_f32
+        deserialize_f64 visit_f64 decode_f64
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Error> {
+        Err(Error::custom("self-describing input required"))
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let value = self.params.clone().decode_u32(&mut self.reader)?;
+        visitor.visit_char(char::from_u32(value).ok_or(Error::invalid("invalid char"))?)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let bytes = self.bytes()?;
+        visitor.visit_string(String::from_utf8(bytes).map_err(|_| Error::invalid("invalid UTF-8"))?)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_byte_buf(self.bytes()?)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.params.clone().decode_u8(&mut self.reader)? {
+            0 => visitor.visit_none(),
+            1 => self.nested(|de| visitor.visit_some(de)),
+            _ => Err(Error::invalid("invalid option tag")),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let len = self.len()?;
+        self.nested(|de| visitor.visit_seq(Access { de, len }))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.nested(|de| visitor.visit_seq(Access { de, len }))
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.nested(|de| visitor.visit_seq(Access { de, len }))
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let len = self.len()?;
+        self.nested(|de| visitor.visit_map(Access { de, len }))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let len = fields.len();
+        self.nested(|de| visitor.visit_seq(Access { de, len }))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.nested(|de| visitor.visit_enum(de))
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Error> {
+        Err(Error::custom("self-describing input required"))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Error> {
+        Err(Error::custom("self-describing input required"))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// Accesses the elements of a sequence or the entries of a map.
+struct Access<'a, R, P, L> {
+    de: &'a mut Deserializer<R, P, L>,
+    len: usize,
+}
+
+impl<'de, 'a, R: Read, P: Scalars, L: Clone> de::SeqAccess<'de> for Access<'a, R, P, L>
+where
+    usize: Decoder<L>,
+    <usize as Decoder<L>>::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        match self.len.checked_sub(1) {
+            Some(len) => {
+                self.len = len;
+                seed.deserialize(&mut *self.de).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl<'de, 'a, R: Read, P: Scalars, L: Clone> de::MapAccess<'de> for Access<'a, R, P, L>
+where
+    usize: Decoder<L>,
+    <usize as Decoder<L>>::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        de::SeqAccess::next_element_seed(self, seed)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl<'de, R: Read, P: Scalars, L: Clone> de::EnumAccess<'de> for &mut Deserializer<R, P, L>
+where
+    usize: Decoder<L>,
+    <usize as Decoder<L>>::Error: Into<Error>,
+{
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Error> {
+        let index = self.params.clone().decode_u32(&mut self.reader)?;
+        let value = seed.deserialize(IntoDeserializer::<Error>::into_deserializer(index))?;
+        Ok((value, self))
+    }
+}
+
+impl<'de, R: Read, P: Scalars, L: Clone> de::VariantAccess<'de> for &mut Deserializer<R, P, L>
+where
+    usize: Decoder<L>,
+    <usize as Decoder<L>>::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(Access { de: self, len })
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let len = fields.len();
+        visitor.visit_seq(Access { de: self, len })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Be, DecoderExt, EncoderExt, Uleb128};
+
+    use alloc::boxed::Box;
+    use alloc::vec;
+    use serde::Deserialize;
+
+    #[derive(serde::Serialize, Deserialize, Debug, PartialEq)]
+    enum List {
+        Nil,
+        Cons(u8, Box<List>),
+    }
+
+    fn format(depth: usize) -> Serde<Binary<Be, Uleb128>> {
+        Serde(Binary::new(Be, Uleb128).with_limits(Limits::NONE.depth(depth)))
+    }
+
+    fn exceeded<T: DeserializeOwned + core::fmt::Debug>(buf: &[u8], depth: usize) {
+        match T::decode_exact(buf, format(depth)) {
+            Err(Error::LimitExceeded { limit, .. }) => assert_eq!(limit, "nesting depth"),
+            result => panic!("decoded {:?}", result),
+        }
+    }
+
+    #[test]
+    fn nested_enums() {
+        let list = (0..100).fold(List::Nil, |tail, i| List::Cons(i, Box::new(tail)));
+        let buf = list.encode_to_vec(format(0)).unwrap();
+
+        assert_eq!(List::decode_exact(&buf, format(101)).unwrap(), list);
+        exceeded::<List>(&buf, 100);
+    }
+
+    #[test]
+    fn nested_containers() {
+        let value = vec![vec![Some((1u8, Some(2u8)))]];
+        let buf = value.encode_to_vec(format(0)).unwrap();
+
+        assert_eq!(
+            Vec::<Vec<Option<(u8, Option<u8>)>>>::decode_exact(&buf, format(5)).unwrap(),
+            value
+        );
+        exceeded::<Vec<Vec<Option<(u8, Option<u8>)>>>>(&buf, 4);
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[serde(try_from = "u8")]
+    struct Even;
+
+    impl core::convert::TryFrom<u8> for Even {
+        type Error = String;
+
+        fn try_from(value: u8) -> Result<Self, String> {
+            match value % 2 {
+                0 => Ok(Self),
+                _ => Err(alloc::format!("{} is odd", value)),
+            }
+        }
+    }
+
+    #[test]
+    fn custom_message() {
+        match Even::decode_exact(&[3], format(1)) {
+            Err(Error::Custom { message, .. }) => assert_eq!(message.as_str(), "3 is odd"),
+            result => panic!("decoded {:?}", result),
+        }
+    }
+
+    #[test]
+    fn detailed_messages() {
+        // List has two variants, so index 5 is out of range.
+        match List::decode_exact(&[0, 0, 0, 5], format(1)) {
+            Err(Error::Custom { message, .. }) => {
+                assert!(message.as_str().contains("5"), "{}", message.as_str());
+            }
+            result => panic!("decoded {:?}", result),
+        }
+    }
+}
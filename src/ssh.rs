@@ -0,0 +1,442 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! The data types of the SSH protocols (RFC 4251).
+//!
+//! Under the `Ssh` parameters, `u8` encodes as a `byte`, `bool` as a
+//! `boolean` (decoding any nonzero value as `true`), `u32` as a `uint32` and
+//! `u64` as a `uint64`. Byte strings and strings encode as a `string`, and
+//! `Vec<String>` as a `name-list`. Arrays encode their elements without a
+//! length, as `byte[n]` does. Integers and nonnegative big-endian magnitudes
+//! encode as an `mpint` under the `Mpint` parameters.
+//!
+//! ```rust
+//! use codicon::*;
+//! use codicon::ssh::Mpint;
+//!
+//! let ssh = Ssh::new();
+//! assert_eq!(true.encode_to_vec(ssh).unwrap(), [1]);
+//! assert_eq!(0x1234u32.encode_to_vec(ssh).unwrap(), [0, 0, 0x12, 0x34]);
+//! assert_eq!("ssh".encode_to_vec(ssh).unwrap(), [0, 0, 0, 3, b's', b's', b'h']);
+//!
+//! let names = vec!["zlib".to_string(), "none".into()];
+//! assert_eq!(names.encode_to_vec(ssh).unwrap(), b"\0\0\0\x09zlib,none");
+//!
+//! assert_eq!(0i32.encode_to_vec(Mpint(ssh)).unwrap(), [0, 0, 0, 0]);
+//! assert_eq!(0x80u8.encode_to_vec(Mpint(ssh)).unwrap(), [0, 0, 0, 2, 0x00, 0x80]);
+//! assert_eq!((-0x1234i16).encode_to_vec(Mpint(ssh)).unwrap(), [0, 0, 0, 2, 0xed, 0xcc]);
+//! assert_eq!([0u8, 0x80][..].encode_to_vec(Mpint(ssh)).unwrap(), [0, 0, 0, 2, 0x00, 0x80]);
+//!
+//! assert!(i32::decode_exact(&[0, 0, 0, 2, 0x00, 0x7f], Mpint(ssh)).is_err());
+//! assert!(Vec::<u8>::decode_exact(&[0, 0, 0, 1, 0xff], Mpint(ssh)).is_err());
+//! ```
+//!
+//! Messages and key formats can use the derive macros:
+//!
+//! ```rust
+//! # #[cfg(feature = "derive")]
+//! # fn main() {
+//! use codicon::*;
+//! use codicon::ssh::Mpint;
+//!
+//! #[derive(Encoder, Decoder, Debug, PartialEq)]
+//! #[codicon(params = Ssh)]
+//! struct RsaPublicKey {
+//!     algorithm: String,
+//!     #[codicon(params = Mpint(params))]
+//!     e: Vec<u8>,
+//!     #[codicon(params = Mpint(params))]
+//!     n: Vec<u8>,
+//! }
+//!
+//! let ssh = Ssh::new();
+//! let key = RsaPublicKey {
+//!     algorithm: "ssh-rsa".into(),
+//!     e: vec![0x01, 0x00, 0x01],
+//!     n: vec![0xc5, 0x3f],
+//! };
+//!
+//! let buf = key.encode_to_vec(ssh).unwrap();
+//! assert_eq!(&buf[11..], [0, 0, 0, 3, 0x01, 0x00, 0x01, 0, 0, 0, 3, 0x00, 0xc5, 0x3f]);
+//! assert_eq!(RsaPublicKey::decode_exact(&buf, ssh).unwrap(), key);
+//! # }
+//! # #[cfg(not(feature = "derive"))]
+//! # fn main() {}
+//! ```
+
+use crate::container::Concat;
+use crate::int;
+use crate::io::{self, Read, Write};
+use crate::limits::Limits;
+use crate::{Be, Decoder, Encoder, Error};
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+/// SSH encoding parameters.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Ssh {
+    limits: Limits,
+}
+
+impl Ssh {
+    /// Creates parameters with the default limits.
+    pub const fn new() -> Self {
+        Self {
+            limits: Limits::DEFAULT,
+        }
+    }
+
+    /// Sets the limits checked when decoding.
+    pub fn with_limits(self, limits: Limits) -> Self {
+        Self { limits }
+    }
+
+    /// Returns the limits checked when decoding.
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+}
+
+impl<T: ?Sized> Concat<T> for Ssh {}
+
+/// Parameters for SSH `mpint` values.
+///
+/// Integers encode as their minimal two's complement under these
+/// parameters. `[u8]` and `Vec<u8>` hold the big-endian magnitude of a
+/// nonnegative integer, which decodes without leading zeros.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Mpint(pub Ssh);
+
+fn write_string(mut writer: impl Write, bytes: &[u8]) -> Result<(), Error> {
+    let len = u32::try_from(bytes.len()).map_err(|_| Error::invalid("length exceeds u32"))?;
+    len.encode(&mut writer, Be)?;
+    Ok(writer.write_all(bytes)?)
+}
+
+/// Reads a `string` without trusting its length with a large allocation.
+fn read_string(mut reader: impl Read, limits: Limits) -> Result<Vec<u8>, Error> {
+    let len = u32::decode(&mut reader, Be)?;
+    let len = usize::try_from(len).map_err(|_| Error::limit("length exceeds usize"))?;
+    limits.check_len(len)?;
+    limits.check_alloc::<u8>(len)?;
+
+    Ok(io::read_vec(reader, len)?)
+}
+
+/// Whether the first byte of an `mpint` only repeats the sign of the next,
+/// or is a zero which should be empty.
+fn redundant(bytes: &[u8]) -> bool {
+    bytes == [0x00] || int::redundant(bytes)
+}
+
+impl Encoder<Ssh> for u8 {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, _: Ssh) -> Result<(), Error> {
+        self.encode(writer, Be)
+    }
+}
+
+impl Decoder<Ssh> for u8 {
+    type Error = Error;
+
+    #[inline]
+    fn decode(reader: impl Read, _: Ssh) -> Result<Self, Error> {
+        u8::decode(reader, Be)
+    }
+}
+
+impl Encoder<Ssh> for bool {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, _: Ssh) -> Result<(), Error> {
+        u8::from(*self).encode(writer, Be)
+    }
+}
+
+impl Decoder<Ssh> for bool {
+    type Error = Error;
+
+    #[inline]
+    fn decode(reader: impl Read, _: Ssh) -> Result<Self, Error> {
+        Ok(u8::decode(reader, Be)? != 0)
+    }
+}
+
+impl Encoder<Ssh> for u32 {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, _: Ssh) -> Result<(), Error> {
+        self.encode(writer, Be)
+    }
+}
+
+impl Decoder<Ssh> for u32 {
+    type Error = Error;
+
+    #[inline]
+    fn decode(reader: impl Read, _: Ssh) -> Result<Self, Error> {
+        u32::decode(reader, Be)
+    }
+}
+
+impl Encoder<Ssh> for u64 {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, _: Ssh) -> Result<(), Error> {
+        self.encode(writer, Be)
+    }
+}
+
+impl Decoder<Ssh> for u64 {
+    type Error = Error;
+
+    #[inline]
+    fn decode(reader: impl Read, _: Ssh) -> Result<Self, Error> {
+        u64::decode(reader, Be)
+    }
+}
+
+impl Encoder<Ssh> for [u8] {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, _: Ssh) -> Result<(), Error> {
+        write_string(writer, self)
+    }
+}
+
+impl Encoder<Ssh> for Vec<u8> {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, _: Ssh) -> Result<(), Error> {
+        write_string(writer, self)
+    }
+}
+
+impl Decoder<Ssh> for Vec<u8> {
+    type Error = Error;
+
+    #[inline]
+    fn decode(reader: impl Read, params: Ssh) -> Result<Self, Error> {
+        read_string(reader, params.limits)
+    }
+}
+
+impl Encoder<Ssh> for str {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, _: Ssh) -> Result<(), Error> {
+        write_string(writer, self.as_bytes())
+    }
+}
+
+impl Encoder<Ssh> for String {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, _: Ssh) -> Result<(), Error> {
+        write_string(writer, self.as_bytes())
+    }
+}
+
+impl Decoder<Ssh> for String {
+    type Error = Error;
+
+    fn decode(reader: impl Read, params: Ssh) -> Result<Self, Error> {
+        let bytes = read_string(reader, params.limits)?;
+        String::from_utf8(bytes).map_err(|_| Error::invalid("invalid UTF-8"))
+    }
+}
+
+impl Encoder<Ssh> for [String] {
+    type Error = Error;
+
+    fn encode(&self, writer: impl Write, _: Ssh) -> Result<(), Error> {
+        for name in self {
+            if name.is_empty() || !name.is_ascii() || name.contains(',') {
+                return Err(Error::invalid("invalid name"));
+            }
+        }
+
+        write_string(writer, self.join(",").as_bytes())
+    }
+}
+
+impl Encoder<Ssh> for Vec<String> {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: Ssh) -> Result<(), Error> {
+        self.as_slice().encode(writer, params)
+    }
+}
+
+impl Decoder<Ssh> for Vec<String> {
+    type Error = Error;
+
+    fn decode(reader: impl Read, params: Ssh) -> Result<Self, Error> {
+        let list = read_string(reader, params.limits)?;
+        if list.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut names = Vec::new();
+        for name in list.split(|byte| *byte == b',') {
+            if name.is_empty() || !name.is_ascii() {
+                return Err(Error::invalid("invalid name"));
+            }
+
+            params.limits.check_len(names.len() + 1)?;
+            names.push(name.iter().map(|byte| char::from(*byte)).collect());
+        }
+
+        Ok(names)
+    }
+}
+
+macro_rules! mpint {
+    ($($t:ident)+) => {
+        $(
+            impl Encoder<Mpint> for $t {
+                type Error = Error;
+
+                fn encode(&self, writer: impl Write, _: Mpint) -> Result<(), Error> {
+                    let (buf, start) =
+                        int::encode(*self).ok_or(Error::invalid("integer out of range"))?;
+
+                    match &buf[start..] {
+                        [0x00] => write_string(writer, &[]),
+                        bytes => write_string(writer, bytes),
+                    }
+                }
+            }
+
+            impl Decoder<Mpint> for $t {
+                type Error = Error;
+
+                fn decode(mut reader: impl Read, _: Mpint) -> Result<Self, Error> {
+                    let len = u32::decode(&mut reader, Be)?;
+
+                    let mut buf = [0u8; int::MAX_LEN];
+                    let bytes = match usize::try_from(len) {
+                        Ok(len) if len <= buf.len() => &mut buf[..len],
+                        _ => return Err(Error::invalid("integer out of range")),
+                    };
+
+                    reader.read_exact(bytes)?;
+                    if redundant(bytes) {
+                        return Err(Error::invalid("non-minimal mpint"));
+                    }
+
+                    int::decode(bytes).ok_or(Error::invalid("integer out of range"))
+                }
+            }
+        )+
+    };
+}
+
+mpint!(u8 u16 u32 u64 u128 usize i8 i16 i32 i64 i128 isize);
+
+impl Encoder<Mpint> for [u8] {
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, _: Mpint) -> Result<(), Error> {
+        let start = self
+            .iter()
+            .position(|byte| *byte != 0)
+            .unwrap_or(self.len());
+        let magnitude = &self[start..];
+
+        match magnitude.first() {
+            Some(0x80..=0xff) => {
+                let len = u32::try_from(magnitude.len() + 1)
+                    .map_err(|_| Error::invalid("length exceeds u32"))?;
+                len.encode(&mut writer, Be)?;
+                writer.write_all(&[0])?;
+                Ok(writer.write_all(magnitude)?)
+            }
+            _ => write_string(writer, magnitude),
+        }
+    }
+}
+
+impl Encoder<Mpint> for Vec<u8> {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: Mpint) -> Result<(), Error> {
+        self.as_slice().encode(writer, params)
+    }
+}
+
+impl Decoder<Mpint> for Vec<u8> {
+    type Error = Error;
+
+    fn decode(reader: impl Read, params: Mpint) -> Result<Self, Error> {
+        let mut bytes = read_string(reader, params.0.limits)?;
+        if redundant(&bytes) {
+            return Err(Error::invalid("non-minimal mpint"));
+        }
+
+        match bytes.first() {
+            Some(0x80..=0xff) => Err(Error::invalid("negative mpint")),
+            Some(0x00) => {
+                bytes.remove(0);
+                Ok(bytes)
+            }
+            _ => Ok(bytes),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DecoderExt;
+
+    fn invalid<T: Decoder<Mpint, Error = Error> + core::fmt::Debug>(bytes: &[u8], reason: &str) {
+        match T::decode_exact(bytes, Mpint(Ssh::new())) {
+            Err(Error::InvalidValue { reason: actual, .. }) => assert_eq!(actual, reason),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn non_canonical_mpint() {
+        // Zero has no bytes at all.
+        invalid::<i32>(&[0, 0, 0, 1, 0x00], "non-minimal mpint");
+        invalid::<Vec<u8>>(&[0, 0, 0, 1, 0x00], "non-minimal mpint");
+
+        // A leading byte which only repeats the sign of the next.
+        invalid::<i32>(&[0, 0, 0, 2, 0x00, 0x7f], "non-minimal mpint");
+        invalid::<i32>(&[0, 0, 0, 2, 0xff, 0x80], "non-minimal mpint");
+        invalid::<u64>(&[0, 0, 0, 3, 0x00, 0x00, 0x80], "non-minimal mpint");
+        invalid::<Vec<u8>>(&[0, 0, 0, 2, 0x00, 0x01], "non-minimal mpint");
+
+        // Magnitudes are never negative.
+        invalid::<Vec<u8>>(&[0, 0, 0, 1, 0x80], "negative mpint");
+        invalid::<u8>(&[0, 0, 0, 1, 0xff], "integer out of range");
+        invalid::<i8>(&[0, 0, 0, 2, 0x00, 0x80], "integer out of range");
+        invalid::<u128>(&[0, 0, 0, 18, 0x01], "integer out of range");
+
+        // The leading zero of a positive value with its top bit set is required.
+        let buf = [0, 0, 0, 2, 0x00, 0x80];
+        assert_eq!(u8::decode_exact(&buf, Mpint(Ssh::new())).unwrap(), 0x80);
+        assert_eq!(
+            Vec::<u8>::decode_exact(&buf, Mpint(Ssh::new())).unwrap(),
+            [0x80]
+        );
+        assert_eq!(
+            i16::decode_exact(&[0, 0, 0, 1, 0x80], Mpint(Ssh::new())).unwrap(),
+            -0x80
+        );
+    }
+}
@@ -0,0 +1,389 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Incremental decoding from and encoding to non-blocking streams.
+//!
+//! `Decoder::decode()` reads until the value is complete, so if a
+//! non-blocking reader runs out of bytes part way through a value, the
+//! bytes already consumed are lost. `Incremental` instead buffers input as it
+//! arrives and only consumes it once a complete value can be decoded.
+//!
+//! Each attempt decodes from the start of the buffered bytes, so this works
+//! with any decoder which reports running out of input as
+//! `Error::UnexpectedEof`, including all of the built-in formats and
+//! containers. The buffer grows until a value is complete, so bound it with
+//! `Incremental::with_limits()` when reading untrusted input.
+//!
+//! ```rust
+//! use codicon::*;
+//! use std::task::Poll;
+//!
+//! let mut decoder = Incremental::<Vec<u16>, _>::new(Prefixed::new(Uleb128, Be));
+//!
+//! assert!(decoder.feed(&[2, 0x12]).is_pending());
+//! assert!(matches!(decoder.feed(&[0x34, 0x56]), Poll::Pending));
+//!
+//! match decoder.feed(&[0x78, 0x01]) {
+//!     Poll::Ready(Ok(value)) => assert_eq!(value, [0x1234, 0x5678]),
+//!     _ => unreachable!(),
+//! }
+//!
+//! assert_eq!(decoder.buffered(), [0x01]);
+//! ```
+//!
+//! Likewise, if a non-blocking writer would block part way through
+//! `Encoder::encode()`, the bytes already written cannot be recovered.
+//! `EncodeBuffer` instead encodes values into memory and then writes them out
+//! across as many calls as the writer requires. In an event loop (such as
+//! one built on `mio`), keep write interest registered for a stream while its
+//! buffer is not empty and call `EncodeBuffer::write_to()` whenever the
+//! stream becomes writable.
+//!
+//! ```rust
+//! use codicon::*;
+//! use std::io::{ErrorKind, Write};
+//! use std::task::Poll;
+//!
+//! // A writer which accepts at most two bytes before it would block.
+//! struct Socket(Vec<u8>);
+//!
+//! impl Write for Socket {
+//!     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+//!         match 2usize.saturating_sub(self.0.len()).min(buf.len()) {
+//!             0 => Err(ErrorKind::WouldBlock.into()),
+//!             n => Ok(self.0.write(&buf[..n])?),
+//!         }
+//!     }
+//!
+//!     fn flush(&mut self) -> std::io::Result<()> {
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let mut buffer = EncodeBuffer::new();
+//! buffer.push(&0x12345678u32, Be).unwrap();
+//!
+//! let mut socket = Socket(Vec::new());
+//! assert!(buffer.write_to(&mut socket).is_pending());
+//! assert_eq!(buffer.pending(), [0x56, 0x78]);
+//!
+//! // The socket becomes writable again.
+//! let mut written = std::mem::take(&mut socket.0);
+//! assert!(matches!(buffer.write_to(&mut socket), Poll::Ready(Ok(()))));
+//! written.extend(socket.0);
+//! assert_eq!(written, [0x12, 0x34, 0x56, 0x78]);
+//! assert!(buffer.is_empty());
+//! ```
+
+use crate::io::{self, BaseRead, ErrorKind, Read, Write};
+use crate::limits::Limits;
+use crate::{Decoder, Encoder, Error};
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::task::Poll;
+
+/// A reader over the start of a buffer which records how many more bytes
+/// were wanted once the buffer ran out.
+pub(crate) struct Partial<'a> {
+    pub(crate) buf: &'a [u8],
+    pub(crate) wanted: usize,
+}
+
+impl BaseRead for Partial<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.buf.is_empty() {
+            self.wanted = buf.len();
+        }
+
+        let n = core::cmp::min(buf.len(), self.buf.len());
+        buf[..n].copy_from_slice(&self.buf[..n]);
+        self.buf = &self.buf[n..];
+        Ok(n)
+    }
+}
+
+/// A decoder which resumes when more input arrives.
+#[derive(Clone, Debug)]
+pub struct Incremental<T, P> {
+    buf: Vec<u8>,
+    needed: usize,
+    params: P,
+    limits: Limits,
+    value: PhantomData<fn() -> T>,
+}
+
+impl<T: Decoder<P>, P: Clone> Incremental<T, P>
+where
+    T::Error: Into<Error>,
+{
+    /// Creates a decoder which decodes values with the given parameters.
+    pub fn new(params: P) -> Self {
+        Self {
+            buf: Vec::new(),
+            needed: 0,
+            params,
+            limits: Limits::DEFAULT,
+            value: PhantomData,
+        }
+    }
+
+    /// Sets the limits on buffered input.
+    ///
+    /// Buffering more bytes than the byte limit fails with
+    /// `Error::LimitExceeded`. The parameters check their own limits.
+    pub fn with_limits(self, limits: Limits) -> Self {
+        Self { limits, ..self }
+    }
+
+    /// Returns the limits on buffered input.
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    /// Returns the bytes which have been buffered but not yet decoded.
+    pub fn buffered(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Attempts to decode a value from the buffered bytes.
+    ///
+    /// Returns `Poll::Pending` if more bytes are required. Once a value is
+    /// decoded, its bytes are removed from the buffer; any remaining bytes
+    /// are kept for the next value. If decoding fails, the buffer is left
+    /// unchanged.
+    ///
+    /// Each attempt decodes from the start of the buffer. To avoid decoding
+    /// again for every byte that arrives, no attempt is made until as many
+    /// bytes are buffered as the previous attempt asked for. Values which
+    /// are read in many small pieces, such as long sequences, are still
+    /// decoded once per piece, so frame them with `Framed` where that cost
+    /// matters.
+    pub fn poll(&mut self) -> Poll<Result<T, Error>> {
+        if self.buf.len() < self.needed {
+            return Poll::Pending;
+        }
+
+        let mut reader = Partial {
+            buf: &self.buf,
+            wanted: 0,
+        };
+
+        let result = T::decode(&mut reader, self.params.clone()).map_err(Into::into);
+        let consumed = self.buf.len() - reader.buf.len();
+        let wanted = reader.wanted;
+
+        match result {
+            Ok(value) => {
+                self.buf.drain(..consumed);
+                self.needed = 0;
+                Poll::Ready(Ok(value))
+            }
+
+            Err(e) if e.is_eof() => {
+                self.needed = self.buf.len() + core::cmp::max(wanted, 1);
+                Poll::Pending
+            }
+
+            Err(e) => Poll::Ready(Err(e.at(consumed))),
+        }
+    }
+
+    /// Buffers `bytes` and then attempts to decode a value.
+    ///
+    /// Fails with `Error::LimitExceeded`, without buffering any of `bytes`,
+    /// if the buffer would exceed the byte limit.
+    pub fn feed(&mut self, bytes: &[u8]) -> Poll<Result<T, Error>> {
+        let len = self.buf.len().saturating_add(bytes.len());
+        if let Err(e) = self.limits.check_bytes(len) {
+            return Poll::Ready(Err(e.at(self.buf.len())));
+        }
+
+        self.buf.extend_from_slice(bytes);
+        self.poll()
+    }
+
+    /// Reads from a non-blocking reader until a value can be decoded.
+    ///
+    /// Returns `Poll::Pending` if the reader would block before a value is
+    /// complete. If the reader reaches its end first, this fails with
+    /// `Error::UnexpectedEof`; check `buffered()` to distinguish the end of
+    /// a stream from a truncated value. Reading stops at the byte limit,
+    /// failing with `Error::LimitExceeded` if no value has been decoded.
+    pub fn read_from(&mut self, mut reader: impl Read) -> Poll<Result<T, Error>> {
+        let mut chunk = [0u8; 4096];
+
+        loop {
+            if let Poll::Ready(result) = self.poll() {
+                return Poll::Ready(result);
+            }
+
+            let len = self.buf.len();
+            let max = core::cmp::min(chunk.len(), self.limits.remaining_bytes(len));
+            if max == 0 {
+                return Poll::Ready(Err(Error::limit("total bytes").at(len)));
+            }
+
+            match reader.read(&mut chunk[..max]) {
+                Ok(0) => return Poll::Ready(Err(Error::eof().at(len))),
+                Ok(n) => self.buf.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Poll::Pending,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Poll::Ready(Err(e.into())),
+            }
+        }
+    }
+}
+
+/// Encoded bytes waiting to be written to a non-blocking writer.
+#[derive(Clone, Debug, Default)]
+pub struct EncodeBuffer {
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl EncodeBuffer {
+    /// Creates an empty buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns whether all encoded bytes have been written.
+    pub fn is_empty(&self) -> bool {
+        self.pos == self.buf.len()
+    }
+
+    /// Returns the encoded bytes which have not yet been written.
+    pub fn pending(&self) -> &[u8] {
+        &self.buf[self.pos..]
+    }
+
+    /// Encodes a value after any bytes already pending.
+    ///
+    /// If encoding fails, no bytes of the value are kept.
+    pub fn push<T: Encoder<P> + ?Sized, P>(
+        &mut self,
+        value: &T,
+        params: P,
+    ) -> Result<(), T::Error> {
+        let len = self.buf.len();
+        let result = value.encode(&mut self.buf, params);
+
+        if result.is_err() {
+            self.buf.truncate(len);
+        }
+
+        result
+    }
+
+    /// Writes pending bytes to a non-blocking writer.
+    ///
+    /// Returns `Poll::Ready(Ok(()))` once every pending byte is written, or
+    /// `Poll::Pending` if the writer would block first. The writer is not
+    /// flushed.
+    pub fn write_to(&mut self, mut writer: impl Write) -> Poll<Result<(), Error>> {
+        while !self.is_empty() {
+            match writer.write(&self.buf[self.pos..]) {
+                Ok(0) => return Poll::Ready(Err(io::Error::from(ErrorKind::WriteZero).into())),
+                Ok(n) => self.pos += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    self.buf.drain(..self.pos);
+                    self.pos = 0;
+                    return Poll::Pending;
+                }
+                Err(e) => return Poll::Ready(Err(e.into())),
+            }
+        }
+
+        self.buf.clear();
+        self.pos = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Be, Prefixed, Uleb128};
+    use core::cell::Cell;
+
+    /// Parameters counting the decoding attempts of a `u64`.
+    #[derive(Clone)]
+    struct Counting<'a>(&'a Cell<usize>);
+
+    impl Decoder<Counting<'_>> for u64 {
+        type Error = Error;
+
+        fn decode(reader: impl Read, params: Counting<'_>) -> Result<Self, Error> {
+            params.0.set(params.0.get() + 1);
+            u64::decode(reader, Be)
+        }
+    }
+
+    #[test]
+    fn byte_at_a_time() {
+        let attempts = Cell::new(0);
+        let mut decoder = Incremental::<u64, _>::new(Counting(&attempts));
+
+        for byte in 1..8 {
+            assert!(decoder.feed(&[byte]).is_pending());
+        }
+
+        assert!(matches!(
+            decoder.feed(&[8]),
+            Poll::Ready(Ok(0x0102030405060708))
+        ));
+        assert_eq!(attempts.get(), 2);
+        assert!(decoder.buffered().is_empty());
+    }
+
+    #[test]
+    fn feed_limit() {
+        let params = Prefixed::new(Uleb128, Be);
+        let limits = Limits::NONE.bytes(4);
+        let mut decoder = Incremental::<Vec<u8>, _>::new(params).with_limits(limits);
+
+        assert!(decoder.feed(&[9, 1, 2]).is_pending());
+        match decoder.feed(&[3, 4]) {
+            Poll::Ready(Err(Error::LimitExceeded { offset, limit })) => {
+                assert_eq!(limit, "total bytes");
+                assert_eq!(offset, Some(3));
+            }
+            _ => panic!("limit not enforced"),
+        }
+
+        assert_eq!(decoder.buffered(), [9, 1, 2]);
+    }
+
+    #[test]
+    fn read_limit() {
+        let params = Prefixed::new(Uleb128, Be);
+        let limits = Limits::NONE.bytes(4);
+
+        let mut decoder = Incremental::<Vec<u8>, _>::new(params).with_limits(limits);
+        let result = decoder.read_from(&[3, 1, 2, 3, 9][..]);
+        assert!(matches!(result, Poll::Ready(Ok(v)) if v == [1, 2, 3]));
+
+        let mut decoder = Incremental::<Vec<u8>, _>::new(params).with_limits(limits);
+        let result = decoder.read_from(&[9, 1, 2, 3, 4, 5][..]);
+        assert!(matches!(
+            result,
+            Poll::Ready(Err(Error::LimitExceeded { .. }))
+        ));
+        assert_eq!(decoder.buffered(), [9, 1, 2, 3]);
+    }
+
+    #[test]
+    fn invalid() {
+        let mut decoder = Incremental::<u8, _>::new(Uleb128);
+        let result = decoder.feed(&[0x80, 0x02]);
+        assert!(matches!(
+            result,
+            Poll::Ready(Err(Error::InvalidValue {
+                offset: Some(2),
+                ..
+            }))
+        ));
+        assert_eq!(decoder.buffered(), [0x80, 0x02]);
+    }
+}
@@ -0,0 +1,319 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! The TLS presentation language (RFC 8446, section 3).
+//!
+//! Under the `Tls` parameters, `u8`, `u16`, `u32` and `u64` encode as the
+//! big-endian `uint8`, `uint16`, `uint32` and `uint64`, and `Uint24` as a
+//! `uint24`. Arrays encode as fixed-length vectors, whose elements follow
+//! each other without a length.
+//!
+//! Variable-length vectors, such as `opaque data<a..b>`, use the `Vector`
+//! parameters with the bounds `a` and `b`. The vector is prefixed with its
+//! length in bytes, whose width is the number of bytes needed to hold `b`.
+//! Encoding and decoding both reject lengths outside of the bounds.
+//!
+//! ```rust
+//! use codicon::*;
+//! use codicon::tls::{Uint24, Vector};
+//!
+//! let tls = Tls;
+//! assert_eq!(0x0303u16.encode_to_vec(tls).unwrap(), [0x03, 0x03]);
+//! assert_eq!(Uint24(0x10000).encode_to_vec(tls).unwrap(), [0x01, 0x00, 0x00]);
+//!
+//! // opaque legacy_session_id<0..32>;
+//! let session = Vector::new(0, 32, tls);
+//! assert_eq!(vec![7u8; 2].encode_to_vec(session).unwrap(), [2, 7, 7]);
+//! assert!(vec![7u8; 33].encode_to_vec(session).is_err());
+//!
+//! // opaque ProtocolName<1..2^8-1>;
+//! // ProtocolName protocol_name_list<2..2^16-1>;
+//! let names = Vector::new(2, 0xffff, Vector::new(1, 0xff, tls));
+//! let list = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+//! let buf = list.encode_to_vec(names).unwrap();
+//! assert_eq!(buf, b"\x00\x0c\x02h2\x08http/1.1");
+//! assert_eq!(Vec::<Vec<u8>>::decode_exact(&buf, names).unwrap(), list);
+//! assert!(Vec::<Vec<u8>>::decode_exact(&[0, 1, 0], names).is_err());
+//! ```
+//!
+//! Structures and enumerated types can use the derive macros:
+//!
+//! ```rust
+//! # #[cfg(feature = "derive")]
+//! # fn main() {
+//! use codicon::*;
+//! use codicon::tls::Vector;
+//!
+//! #[derive(Encoder, Decoder, Debug, PartialEq)]
+//! #[codicon(params = Tls)]
+//! struct Extension {
+//!     extension_type: u16,
+//!     #[codicon(params = Vector::new(0, 0xffff, params))]
+//!     extension_data: Vec<u8>,
+//! }
+//!
+//! let tls = Tls;
+//! let extension = Extension {
+//!     extension_type: 16,
+//!     extension_data: vec![1, 2, 3],
+//! };
+//!
+//! let buf = extension.encode_to_vec(tls).unwrap();
+//! assert_eq!(buf, [0, 16, 0, 3, 1, 2, 3]);
+//! assert_eq!(Extension::decode_exact(&buf, tls).unwrap(), extension);
+//! # }
+//! # #[cfg(not(feature = "derive"))]
+//! # fn main() {}
+//! ```
+
+use crate::container::Concat;
+use crate::framed::{self, Frame};
+use crate::io::{self, Read, Write};
+use crate::{Be, ConstEncodedLen, Decoder, Encoder, Error};
+
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+/// TLS encoding parameters.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Tls;
+
+impl<T: ?Sized> Concat<T> for Tls {}
+
+/// Parameters for variable-length vectors, `T data<min..max>`.
+///
+/// The elements are encoded with the parameters `P`, and `min` and `max`
+/// bound the length of the vector in bytes.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Vector<P> {
+    min: u32,
+    max: u32,
+    params: P,
+}
+
+impl<P> Vector<P> {
+    /// Creates parameters for vectors of `min` to `max` bytes.
+    pub const fn new(min: u32, max: u32, params: P) -> Self {
+        Self { min, max, params }
+    }
+
+    /// Returns the width of the length prefix in bytes.
+    pub fn width(&self) -> usize {
+        match self.max {
+            0..=0xff => 1,
+            0x100..=0xffff => 2,
+            0x1_0000..=0xff_ffff => 3,
+            _ => 4,
+        }
+    }
+
+    /// Returns the length in bytes of the items of a vector.
+    fn items_len<T>(&self, items: &[T]) -> Result<usize, Error>
+    where
+        P: framed::EncodeFrame<T> + Clone,
+    {
+        let mut len = 0usize;
+        for item in items {
+            let item = framed::EncodeFrame::encoded_len(self.params.clone(), item)?;
+            len = len.saturating_add(item);
+        }
+
+        Ok(len)
+    }
+
+    fn check(&self, len: usize) -> Result<u32, Error> {
+        match u32::try_from(len) {
+            Ok(len) if (self.min..=self.max).contains(&len) => Ok(len),
+            _ => Err(Error::invalid("vector length out of bounds")),
+        }
+    }
+}
+
+/// A 24-bit unsigned integer, the `uint24` type.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Uint24(pub u32);
+
+macro_rules! uint {
+    ($($t:ident)+) => {
+        $(
+            impl Encoder<Tls> for $t {
+                type Error = Error;
+
+                #[inline]
+                fn encode(&self, writer: impl Write, _: Tls) -> Result<(), Error> {
+                    self.encode(writer, Be)
+                }
+
+                #[inline]
+                fn encoded_len(&self, _: Tls) -> Result<usize, Error> {
+                    Ok(<Self as ConstEncodedLen<Tls>>::ENCODED_LEN)
+                }
+            }
+
+            impl ConstEncodedLen<Tls> for $t {
+                const ENCODED_LEN: usize = core::mem::size_of::<$t>();
+            }
+
+            impl Decoder<Tls> for $t {
+                type Error = Error;
+
+                #[inline]
+                fn decode(reader: impl Read, _: Tls) -> Result<Self, Error> {
+                    $t::decode(reader, Be)
+                }
+            }
+        )+
+    };
+}
+
+uint!(u8 u16 u32 u64);
+
+impl Encoder<Tls> for Uint24 {
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, _: Tls) -> Result<(), Error> {
+        match self.0.to_be_bytes() {
+            [0, bytes @ ..] => Ok(writer.write_all(&bytes)?),
+            _ => Err(Error::invalid("integer out of range")),
+        }
+    }
+
+    #[inline]
+    fn encoded_len(&self, _: Tls) -> Result<usize, Error> {
+        Ok(<Self as ConstEncodedLen<Tls>>::ENCODED_LEN)
+    }
+}
+
+impl ConstEncodedLen<Tls> for Uint24 {
+    const ENCODED_LEN: usize = 3;
+}
+
+impl Decoder<Tls> for Uint24 {
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, _: Tls) -> Result<Self, Error> {
+        let mut buf = [0u8; 4];
+        reader.read_exact(&mut buf[1..])?;
+        Ok(Self(u32::from_be_bytes(buf)))
+    }
+}
+
+impl<T, P: framed::EncodeFrame<T> + Clone> Encoder<Vector<P>> for [T] {
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, params: Vector<P>) -> Result<(), Error> {
+        let width = params.width();
+        let len = params.check(params.items_len(self)?)?;
+        writer.write_all(&len.to_be_bytes()[4 - width..])?;
+
+        for item in self {
+            framed::EncodeFrame::encode(params.params.clone(), item, &mut writer)?;
+        }
+
+        Ok(())
+    }
+
+    fn encoded_len(&self, params: Vector<P>) -> Result<usize, Error> {
+        let len = params.check(params.items_len(self)?)?;
+        Ok(params.width() + len as usize)
+    }
+}
+
+impl<T, P: framed::EncodeFrame<T> + Clone> Encoder<Vector<P>> for Vec<T> {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: Vector<P>) -> Result<(), Error> {
+        self.as_slice().encode(writer, params)
+    }
+
+    #[inline]
+    fn encoded_len(&self, params: Vector<P>) -> Result<usize, Error> {
+        self.as_slice().encoded_len(params)
+    }
+}
+
+impl<T, P: framed::DecodeFrame<T> + Clone> Decoder<Vector<P>> for Vec<T> {
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: Vector<P>) -> Result<Self, Error> {
+        let width = params.width();
+        let mut buf = [0u8; 4];
+        reader.read_exact(&mut buf[4 - width..])?;
+        let len = params.check(u32::from_be_bytes(buf) as usize)? as usize;
+
+        let mut items = io::with_capacity(len);
+
+        let mut frame = Frame {
+            reader,
+            remaining: len,
+        };
+
+        while frame.remaining > 0 {
+            let remaining = frame.remaining;
+            let item = match framed::DecodeFrame::decode(params.params.clone(), &mut frame) {
+                Err(e) if e.is_eof() && frame.remaining == 0 => {
+                    return Err(Error::invalid("value overruns vector"))
+                }
+                result => result?,
+            };
+
+            // An empty element would never reach the end of the vector.
+            if frame.remaining == remaining {
+                return Err(Error::invalid("empty vector element"));
+            }
+
+            items.push(item);
+        }
+
+        Ok(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DecoderExt, EncoderExt};
+    use alloc::vec;
+
+    fn invalid<T: core::fmt::Debug>(result: Result<T, Error>, expected: &str) {
+        match result {
+            Err(Error::InvalidValue { reason, .. }) => assert_eq!(reason, expected),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let vector = Vector::new(2, 4, Tls);
+        const BOUNDS: &str = "vector length out of bounds";
+
+        invalid(vec![1u8].encode_to_vec(vector), BOUNDS);
+        invalid(vec![1u8; 5].encode_to_vec(vector), BOUNDS);
+        invalid(vec![1u16; 3].encoded_len(vector), BOUNDS);
+        invalid(Vec::<u8>::decode_exact(&[1, 1], vector), BOUNDS);
+        invalid(Vec::<u8>::decode_exact(&[5, 1, 2, 3, 4, 5], vector), BOUNDS);
+
+        // An element may not run past the end of the vector.
+        let vector = Vector::new(0, 0xff, Tls);
+        invalid(
+            Vec::<u16>::decode_exact(&[3, 0, 1, 0], vector),
+            "value overruns vector",
+        );
+
+        let nested = Vector::new(0, 0xff, vector);
+        invalid(
+            Vec::<Vec<u8>>::decode_exact(&[2, 2, 1, 1], nested),
+            "value overruns vector",
+        );
+    }
+
+    #[test]
+    fn encoded_len() {
+        let names = Vector::new(2, 0xffff, Vector::new(1, 0xff, Tls));
+        let list = vec![vec![7u8; 2], vec![8u8; 0x100 - 1]];
+
+        let buf = list.encode_to_vec(names).unwrap();
+        assert_eq!(list.encoded_len(names).unwrap(), buf.len());
+        assert_eq!(buf.len(), 2 + (1 + 2) + (1 + 0xff));
+    }
+}
@@ -0,0 +1,511 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! The XDR format (RFC 4506).
+//!
+//! Under the `Xdr` parameters, `i32` and `u32` encode as XDR integers, `i64`
+//! and `u64` as hyper integers, `f32` and `f64` as floating point numbers and
+//! `bool` as a boolean. Strings encode as XDR strings, `Vec` and slices as
+//! variable-length arrays, arrays as fixed-length arrays and `Option` as
+//! optional data. Opaque data uses the `Opaque` parameters instead, since
+//! byte arrays are arrays of integers under `Xdr`. Every item is padded with
+//! zeros to a multiple of four bytes, and decoding rejects nonzero padding.
+//!
+//! The maximum length of strings, variable-length opaque data and
+//! variable-length arrays is unbounded unless set with `Xdr::max_len`. The
+//! bound applies to the value encoded with the parameters, not to the
+//! elements of an array.
+//!
+//! Each variable-length array and present optional value takes a level of
+//! the depth limit of the parameters' `Limits`, which bounds the recursion
+//! of recursive types. A linked list of optional data thus nests one level
+//! per element, and needs a higher depth limit than the default 128 when
+//! it may be longer.
+//!
+//! ```rust
+//! use codicon::*;
+//! use codicon::xdr::Opaque;
+//!
+//! let xdr = Xdr::new();
+//! assert_eq!((-2i32).encode_to_vec(xdr).unwrap(), [0xff, 0xff, 0xff, 0xfe]);
+//! assert_eq!(1u64.encode_to_vec(xdr).unwrap(), [0, 0, 0, 0, 0, 0, 0, 1]);
+//! assert_eq!(true.encode_to_vec(xdr).unwrap(), [0, 0, 0, 1]);
+//! assert_eq!("hi".encode_to_vec(xdr).unwrap(), [0, 0, 0, 2, b'h', b'i', 0, 0]);
+//! assert_eq!(Some(7u32).encode_to_vec(xdr).unwrap(), [0, 0, 0, 1, 0, 0, 0, 7]);
+//! assert_eq!([1u32, 2].encode_to_vec(xdr).unwrap(), [0, 0, 0, 1, 0, 0, 0, 2]);
+//! assert_eq!([1u8, 2, 3].encode_to_vec(Opaque(xdr)).unwrap(), [1, 2, 3, 0]);
+//! assert_eq!(vec![1u8].encode_to_vec(Opaque(xdr)).unwrap(), [0, 0, 0, 1, 1, 0, 0, 0]);
+//!
+//! assert!("hello".encode_to_vec(xdr.max_len(4)).is_err());
+//! assert!(String::decode_exact(&[0, 0, 0, 5, b'h', b'e', b'l', b'l', b'o', 0, 0, 0], xdr.max_len(4)).is_err());
+//! assert!(<[u8; 1]>::decode_exact(&[1, 0, 0, 1], Opaque(xdr)).is_err());
+//! ```
+//!
+//! Discriminated unions are enums using the derive macros with an `i32` or
+//! `u32` tag. Structures use the derive macros too, and each field may give
+//! its own bound.
+//!
+//! ```rust
+//! # #[cfg(feature = "derive")]
+//! # fn main() {
+//! use codicon::*;
+//!
+//! #[derive(Encoder, Decoder, Debug, PartialEq)]
+//! #[codicon(params = Xdr)]
+//! struct File {
+//!     #[codicon(params = params.max_len(255))]
+//!     name: String,
+//!     size: u64,
+//! }
+//!
+//! #[derive(Encoder, Decoder, Debug, PartialEq)]
+//! #[codicon(params = Xdr, tag = i32)]
+//! enum Lookup {
+//!     Ok(File),
+//!     #[codicon(id = 2)]
+//!     NotFound,
+//! }
+//!
+//! let xdr = Xdr::new();
+//! let lookup = Lookup::Ok(File { name: "a".into(), size: 3 });
+//! let buf = lookup.encode_to_vec(xdr).unwrap();
+//! assert_eq!(buf, [0, 0, 0, 0, 0, 0, 0, 1, b'a', 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3]);
+//! assert_eq!(Lookup::decode_exact(&buf, xdr).unwrap(), lookup);
+//! assert_eq!(Lookup::NotFound.encode_to_vec(xdr).unwrap(), [0, 0, 0, 2]);
+//! assert!(Lookup::decode_exact(&[0, 0, 0, 1], xdr).is_err());
+//! # }
+//! # #[cfg(not(feature = "derive"))]
+//! # fn main() {}
+//! ```
+
+use crate::container::Concat;
+use crate::io::{self, Read, Write};
+use crate::limits::Limits;
+use crate::{Be, ConstEncodedLen, Decoder, Encoder, Error};
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+/// XDR encoding parameters.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Xdr {
+    max: u32,
+    limits: Limits,
+}
+
+impl Default for Xdr {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Xdr {
+    /// Creates unbounded parameters with the default limits.
+    pub const fn new() -> Self {
+        Self {
+            max: u32::MAX,
+            limits: Limits::DEFAULT,
+        }
+    }
+
+    /// Sets the maximum length of a string, opaque data or array.
+    ///
+    /// This is the bound written as `<max>` in XDR type declarations.
+    pub const fn max_len(self, max: u32) -> Self {
+        Self { max, ..self }
+    }
+
+    /// Sets the limits checked when decoding.
+    pub fn with_limits(self, limits: Limits) -> Self {
+        Self { limits, ..self }
+    }
+
+    /// Returns the limits checked when decoding.
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    fn unbounded(self) -> Self {
+        self.max_len(u32::MAX)
+    }
+
+    fn write_len(&self, writer: impl Write, len: usize) -> Result<(), Error> {
+        match u32::try_from(len) {
+            Ok(len) if len <= self.max => len.encode(writer, Be),
+            _ => Err(Error::invalid("length exceeds bound")),
+        }
+    }
+
+    fn read_len(&self, reader: impl Read) -> Result<usize, Error> {
+        let len = u32::decode(reader, Be)?;
+        if len > self.max {
+            return Err(Error::invalid("length exceeds bound"));
+        }
+
+        let len = usize::try_from(len).map_err(|_| Error::limit("length exceeds usize"))?;
+        self.limits.check_len(len)?;
+        Ok(len)
+    }
+}
+
+impl<T: ?Sized> Concat<T> for Xdr {}
+
+/// Parameters for XDR opaque data.
+///
+/// Byte arrays encode as fixed-length opaque data under these parameters,
+/// and `[u8]` and `Vec<u8>` as variable-length opaque data.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Opaque(pub Xdr);
+
+/// The number of zeros following `len` bytes.
+#[inline]
+fn padding(len: usize) -> usize {
+    (4 - len % 4) % 4
+}
+
+fn write_padded(mut writer: impl Write, bytes: &[u8]) -> Result<(), Error> {
+    writer.write_all(bytes)?;
+    Ok(writer.write_all(&[0; 3][..padding(bytes.len())])?)
+}
+
+fn read_padding(mut reader: impl Read, len: usize) -> Result<(), Error> {
+    let mut buf = [0u8; 3];
+    let buf = &mut buf[..padding(len)];
+    reader.read_exact(buf)?;
+
+    match buf.iter().all(|byte| *byte == 0) {
+        true => Ok(()),
+        false => Err(Error::invalid("nonzero padding")),
+    }
+}
+
+/// Reads `len` padded bytes without trusting `len` with a large allocation.
+fn read_padded(mut reader: impl Read, len: usize, limits: Limits) -> Result<Vec<u8>, Error> {
+    limits.check_alloc::<u8>(len)?;
+    let bytes = io::read_vec(&mut reader, len)?;
+    read_padding(reader, len)?;
+    Ok(bytes)
+}
+
+macro_rules! scalar {
+    ($($t:ident)+) => {
+        $(
+            impl Encoder<Xdr> for $t {
+                type Error = Error;
+
+                #[inline]
+                fn encode(&self, writer: impl Write, _: Xdr) -> Result<(), Error> {
+                    self.encode(writer, Be)
+                }
+
+                #[inline]
+                fn encoded_len(&self, _: Xdr) -> Result<usize, Error> {
+                    Ok(<Self as ConstEncodedLen<Xdr>>::ENCODED_LEN)
+                }
+            }
+
+            impl ConstEncodedLen<Xdr> for $t {
+                const ENCODED_LEN: usize = core::mem::size_of::<$t>();
+            }
+
+            impl Decoder<Xdr> for $t {
+                type Error = Error;
+
+                #[inline]
+                fn decode(reader: impl Read, _: Xdr) -> Result<Self, Error> {
+                    $t::decode(reader, Be)
+                }
+            }
+        )+
+    };
+}
+
+scalar!(i32 u32 i64 u64 f32 f64);
+
+impl Encoder<Xdr> for bool {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, _: Xdr) -> Result<(), Error> {
+        u32::from(*self).encode(writer, Be)
+    }
+
+    #[inline]
+    fn encoded_len(&self, _: Xdr) -> Result<usize, Error> {
+        Ok(<Self as ConstEncodedLen<Xdr>>::ENCODED_LEN)
+    }
+}
+
+impl ConstEncodedLen<Xdr> for bool {
+    const ENCODED_LEN: usize = 4;
+}
+
+impl Decoder<Xdr> for bool {
+    type Error = Error;
+
+    fn decode(reader: impl Read, _: Xdr) -> Result<Self, Error> {
+        match u32::decode(reader, Be)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Error::invalid("invalid bool")),
+        }
+    }
+}
+
+impl<const N: usize> Encoder<Opaque> for [u8; N] {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, _: Opaque) -> Result<(), Error> {
+        write_padded(writer, self)
+    }
+}
+
+impl<const N: usize> Decoder<Opaque> for [u8; N] {
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, _: Opaque) -> Result<Self, Error> {
+        let mut bytes = [0u8; N];
+        reader.read_exact(&mut bytes)?;
+        read_padding(reader, N)?;
+        Ok(bytes)
+    }
+}
+
+impl Encoder<Opaque> for [u8] {
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, params: Opaque) -> Result<(), Error> {
+        params.0.write_len(&mut writer, self.len())?;
+        write_padded(writer, self)
+    }
+}
+
+impl Encoder<Opaque> for Vec<u8> {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: Opaque) -> Result<(), Error> {
+        self.as_slice().encode(writer, params)
+    }
+}
+
+impl Decoder<Opaque> for Vec<u8> {
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: Opaque) -> Result<Self, Error> {
+        let len = params.0.read_len(&mut reader)?;
+        read_padded(reader, len, params.0.limits)
+    }
+}
+
+impl Encoder<Xdr> for str {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: Xdr) -> Result<(), Error> {
+        self.as_bytes().encode(writer, Opaque(params))
+    }
+}
+
+impl Encoder<Xdr> for String {
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: Xdr) -> Result<(), Error> {
+        self.as_str().encode(writer, params)
+    }
+}
+
+impl Decoder<Xdr> for String {
+    type Error = Error;
+
+    fn decode(reader: impl Read, params: Xdr) -> Result<Self, Error> {
+        let bytes = Vec::decode(reader, Opaque(params))?;
+        String::from_utf8(bytes).map_err(|_| Error::invalid("invalid UTF-8"))
+    }
+}
+
+impl<T: Encoder<Xdr>> Encoder<Xdr> for [T]
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, params: Xdr) -> Result<(), Error> {
+        params.write_len(&mut writer, self.len())?;
+
+        for item in self {
+            item.encode(&mut writer, params.unbounded())
+                .map_err(Into::into)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: Encoder<Xdr>> Encoder<Xdr> for Vec<T>
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    #[inline]
+    fn encode(&self, writer: impl Write, params: Xdr) -> Result<(), Error> {
+        self.as_slice().encode(writer, params)
+    }
+}
+
+impl<T: Decoder<Xdr>> Decoder<Xdr> for Vec<T>
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: Xdr) -> Result<Self, Error> {
+        let len = params.read_len(&mut reader)?;
+        params.limits.check_alloc::<T>(len)?;
+        let params = params.with_limits(params.limits.nested()?);
+
+        let mut items = io::with_capacity(len);
+
+        for _ in 0..len {
+            items.push(T::decode(&mut reader, params.unbounded()).map_err(Into::into)?);
+        }
+
+        Ok(items)
+    }
+}
+
+impl<T: Encoder<Xdr>> Encoder<Xdr> for Option<T>
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn encode(&self, mut writer: impl Write, params: Xdr) -> Result<(), Error> {
+        self.is_some().encode(&mut writer, params)?;
+
+        match self {
+            Some(value) => value.encode(writer, params).map_err(Into::into),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: Decoder<Xdr>> Decoder<Xdr> for Option<T>
+where
+    T::Error: Into<Error>,
+{
+    type Error = Error;
+
+    fn decode(mut reader: impl Read, params: Xdr) -> Result<Self, Error> {
+        match bool::decode(&mut reader, params)? {
+            true => {
+                let params = params.with_limits(params.limits.nested()?);
+                T::decode(reader, params).map(Some).map_err(Into::into)
+            }
+            false => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DecoderExt, EncoderExt};
+    use alloc::boxed::Box;
+    use alloc::vec;
+
+    fn nonzero<T: Decoder<P, Error = Error> + core::fmt::Debug, P>(bytes: &[u8], params: P) {
+        let error = T::decode_exact(bytes, params).unwrap_err();
+        assert!(matches!(
+            error,
+            Error::InvalidValue {
+                reason: "nonzero padding",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn bad_padding() {
+        let xdr = Xdr::new();
+
+        nonzero::<String, _>(&[0, 0, 0, 2, b'h', b'i', 0, 1], xdr);
+        nonzero::<String, _>(&[0, 0, 0, 1, b'h', 0x80, 0, 0], xdr);
+        nonzero::<Vec<u8>, _>(&[0, 0, 0, 3, 1, 2, 3, 0xff], Opaque(xdr));
+        nonzero::<[u8; 1], _>(&[1, 0, 1, 0], Opaque(xdr));
+
+        // The padding must be present, even at the end of the input.
+        assert!(String::decode_exact(&[0, 0, 0, 2, b'h', b'i', 0], xdr)
+            .unwrap_err()
+            .is_eof());
+
+        // Lengths which are a multiple of four have no padding.
+        let buf = vec![0u8, 0, 0, 4, 1, 2, 3, 4];
+        assert_eq!(
+            Vec::<u8>::decode_exact(&buf, Opaque(xdr)).unwrap(),
+            [1, 2, 3, 4]
+        );
+        assert_eq!(vec![1u8, 2, 3, 4].encode_to_vec(Opaque(xdr)).unwrap(), buf);
+    }
+
+    /// A linked list, `struct List { int value; List *next; }`.
+    #[derive(Debug)]
+    struct List {
+        value: i32,
+        next: Option<Box<List>>,
+    }
+
+    impl Decoder<Xdr> for List {
+        type Error = Error;
+
+        fn decode(mut reader: impl Read, params: Xdr) -> Result<Self, Error> {
+            let mut reader = &mut reader as &mut dyn Read;
+            let value = i32::decode(&mut reader, params)?;
+            let next = Option::decode(reader, params)?;
+            Ok(Self { value, next })
+        }
+    }
+
+    fn too_deep<T: core::fmt::Debug>(result: Result<T, Error>) {
+        match result {
+            Err(Error::LimitExceeded { limit, .. }) => assert_eq!(limit, "nesting depth"),
+            result => panic!("unexpected {:?}", result),
+        }
+    }
+
+    /// Returns a list of `len` elements.
+    fn list(len: usize) -> Vec<u8> {
+        let mut buf = Vec::new();
+        for i in 0..len {
+            buf.extend_from_slice(&[0, 0, 0, i as u8, 0, 0, 0, (i + 1 < len) as u8]);
+        }
+
+        buf
+    }
+
+    #[test]
+    fn nesting_depth() {
+        let xdr = Xdr::new();
+
+        let buf = [0, 0, 0, 0, 0, 0, 0, 1].repeat(200_000);
+        too_deep(List::decode_exact(&buf, xdr));
+
+        let head = List::decode_exact(&list(129), xdr).unwrap();
+        assert_eq!(head.value, 0);
+        assert_eq!(head.next.unwrap().value, 1);
+        too_deep(List::decode_exact(&list(130), xdr));
+
+        let deep = xdr.with_limits(Limits::default().depth(1000));
+        assert!(List::decode_exact(&list(1000), deep).is_ok());
+
+        let shallow = xdr.with_limits(Limits::default().depth(1));
+        assert!(Vec::<u32>::decode_exact(&[0, 0, 0, 1, 0, 0, 0, 1], shallow).is_ok());
+        too_deep(Vec::<Vec<u32>>::decode_exact(
+            &[0, 0, 0, 1, 0, 0, 0, 0],
+            shallow,
+        ));
+    }
+}